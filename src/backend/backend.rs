@@ -0,0 +1,187 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The interface the miner uses to drive a solver, whichever way the
+//! solver is run. It follows the plugin interface: functions return the
+//! plugin's codes, with 0 meaning success.
+
+use cuckoo_sys::manager::PluginLibrary;
+use error::error::CuckooMinerError;
+use manager::metadata::CuckooPluginMetadata;
+
+/// #Description
+///
+/// A solver the miner can mine with, either synchronously via
+/// [solve](#tymethod.solve), or asynchronously by pushing headers to the
+/// solver's input queue and reading solutions from its output queue.
+/// See the plugin functions of the same name in
+/// [PluginLibrary](struct.PluginLibrary.html) for the details of each.
+///
+
+pub trait SolverBackend: Send + Sync {
+	/// The full path to the plugin, used to identify the solver in
+	/// errors and stats
+	fn full_path(&self) -> &str;
+
+	/// The number of nonces in the solver's solutions
+	fn proof_size(&self) -> usize;
+
+	/// What the solver is and what it mines
+	fn metadata(&self) -> &CuckooPluginMetadata;
+
+	/// Whether the solver implements the given optional plugin function
+	fn exports(&self, function: &str) -> bool;
+
+	/// Searches for a solution in the graph generated from `header`,
+	/// returning 1 and filling in `solution` if one is found
+	fn solve(&self, header: &[u8], cuckoo_size: &mut u32, solution: &mut [u32]) -> u32;
+
+	/// The JSON list of parameters the solver declares
+	fn parameter_list(&self) -> Result<String, CuckooMinerError>;
+
+	/// Reads the current value of a parameter
+	fn get_parameter(&self, name: &str, device_id: u32, value: &mut u32) -> u32;
+
+	/// Sets a parameter
+	fn set_parameter(&self, name: &str, device_id: u32, value: u32) -> u32;
+
+	/// The JSON stats of each of the solver's devices
+	fn stats(&self) -> Result<String, CuckooMinerError>;
+
+	/// Whether the solver's input queue has room for more headers
+	fn is_queue_under_limit(&self) -> bool;
+
+	/// Adds a header to the solver's input queue
+	fn push_to_input_queue(&self, id: u32, data: &[u8], nonce: &[u8; 8]) -> u32;
+
+	/// Reads a solution from the solver's output queue, returning whether
+	/// there was one. Errors reading it are logged and reported as no
+	/// solution
+	fn read_from_output_queue(
+		&self,
+		id: &mut u32,
+		solution: &mut [u32],
+		cuckoo_size: &mut u32,
+		nonce: &mut [u8; 8],
+	) -> bool;
+
+	/// Clears the solver's input and output queues
+	fn clear_queues(&self);
+
+	/// Starts processing the input queue
+	fn start_processing(&self) -> u32;
+
+	/// Asks the solver to stop processing
+	fn stop_processing(&self) -> u32;
+
+	/// Resets the solver once it has stopped, so processing can start
+	/// again
+	fn reset_processing(&self) -> u32;
+
+	/// Whether processing has stopped since
+	/// [stop_processing](#tymethod.stop_processing) was called
+	fn has_processing_stopped(&self) -> bool;
+}
+
+impl SolverBackend for PluginLibrary {
+	fn full_path(&self) -> &str {
+		&self.lib_full_path
+	}
+
+	fn proof_size(&self) -> usize {
+		self.proof_size
+	}
+
+	fn metadata(&self) -> &CuckooPluginMetadata {
+		&self.metadata
+	}
+
+	fn exports(&self, function: &str) -> bool {
+		PluginLibrary::exports(self, function)
+	}
+
+	fn solve(&self, header: &[u8], cuckoo_size: &mut u32, solution: &mut [u32]) -> u32 {
+		self.call_cuckoo(header, cuckoo_size, solution)
+	}
+
+	fn parameter_list(&self) -> Result<String, CuckooMinerError> {
+		PluginLibrary::parameter_list(self)
+	}
+
+	fn get_parameter(&self, name: &str, device_id: u32, value: &mut u32) -> u32 {
+		self.call_cuckoo_get_parameter(name.as_bytes(), device_id, value)
+	}
+
+	fn set_parameter(&self, name: &str, device_id: u32, value: u32) -> u32 {
+		self.call_cuckoo_set_parameter(name.as_bytes(), device_id, value)
+	}
+
+	fn stats(&self) -> Result<String, CuckooMinerError> {
+		PluginLibrary::stats(self)
+	}
+
+	fn is_queue_under_limit(&self) -> bool {
+		self.call_cuckoo_is_queue_under_limit() == 1
+	}
+
+	fn push_to_input_queue(&self, id: u32, data: &[u8], nonce: &[u8; 8]) -> u32 {
+		self.call_cuckoo_push_to_input_queue(id, data, nonce)
+	}
+
+	fn read_from_output_queue(
+		&self,
+		id: &mut u32,
+		solution: &mut [u32],
+		cuckoo_size: &mut u32,
+		nonce: &mut [u8; 8],
+	) -> bool {
+		match self.call_cuckoo_read_from_output_queue(id, solution, cuckoo_size, nonce) {
+			0 => false,
+			1 => true,
+			code => {
+				// e.g. 3 if the solution doesn't fit the buffer
+				error!(
+					"{}",
+					CuckooMinerError::plugin_call(
+						&self.lib_full_path,
+						"cuckoo_read_from_output_queue",
+						code,
+						String::from("reading a solution"),
+					)
+				);
+				false
+			}
+		}
+	}
+
+	fn clear_queues(&self) {
+		self.call_cuckoo_clear_queues()
+	}
+
+	fn start_processing(&self) -> u32 {
+		self.call_cuckoo_start_processing()
+	}
+
+	fn stop_processing(&self) -> u32 {
+		self.call_cuckoo_stop_processing()
+	}
+
+	fn reset_processing(&self) -> u32 {
+		self.call_cuckoo_reset_processing()
+	}
+
+	fn has_processing_stopped(&self) -> bool {
+		self.call_cuckoo_has_processing_stopped() != 0
+	}
+}
@@ -0,0 +1,884 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runs a plugin in a separate host process, so that a plugin which
+//! crashes (e.g. with a segfault, or SIGILL on a CPU missing the
+//! instructions it was built for) only takes down its host.
+//!
+//! The host is the `cuckoo-plugin-host` executable built with this crate,
+//! which just calls [run_plugin_host](fn.run_plugin_host.html). It reads
+//! one JSON request per line on stdin, makes the call into the plugin,
+//! and writes one JSON response per line. Anything the plugin itself
+//! prints to stdout is redirected to stderr, so it can't corrupt the
+//! responses.
+//!
+//! If the host dies, or doesn't respond in time and is killed, the call
+//! that was in flight fails with
+//! [HostUnavailable](enum.PluginReturnCode.html), and a new host is
+//! started with the plugin reloaded, the parameters set so far set again,
+//! and processing restarted if it was running.
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::{thread, time};
+
+use serde_json;
+
+use super::backend::SolverBackend;
+use cuckoo_sys::manager::{PluginLibrary, OPTIONAL_FUNCTIONS};
+use error::error::{CuckooMinerError, PluginReturnCode};
+use manager::metadata::CuckooPluginMetadata;
+
+/// Environment variable giving the path to the plugin host executable,
+/// if it isn't alongside the running executable
+
+pub const PLUGIN_HOST_ENV: &str = "CUCKOO_PLUGIN_HOST";
+
+/// The name of the plugin host executable, without any extension
+
+pub const PLUGIN_HOST_NAME: &str = "cuckoo-plugin-host";
+
+/// The number of times in a row a host is restarted before giving up
+/// on the plugin
+
+const MAX_RESTARTS: u32 = 5;
+
+/// How long a host is given to exit after its input is closed, before
+/// it's killed
+
+const SHUTDOWN_TIMEOUT_MS: u64 = 1000;
+
+/// How long a host is given to respond to a request, other than a
+/// solve, before it's killed
+
+pub const DEFAULT_REQUEST_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+
+/// How long a host is given to respond to a solve, which searches a
+/// whole graph, before it's killed
+
+const SOLVE_TIMEOUT: time::Duration = time::Duration::from_secs(600);
+
+/// A call to make in the host, one per line on its stdin
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum HostRequest {
+	/// Load the plugin. Must be the first request
+	Load {
+		/// Full path to the plugin
+		path: String,
+	},
+	/// cuckoo_call
+	Solve {
+		/// The header to solve
+		header: Vec<u8>,
+	},
+	/// cuckoo_parameter_list
+	ParameterList,
+	/// cuckoo_get_parameter
+	GetParameter {
+		/// Parameter name
+		name: String,
+		/// Device the parameter applies to
+		device_id: u32,
+	},
+	/// cuckoo_set_parameter
+	SetParameter {
+		/// Parameter name
+		name: String,
+		/// Device the parameter applies to
+		device_id: u32,
+		/// The value to set
+		value: u32,
+	},
+	/// cuckoo_get_stats
+	Stats,
+	/// cuckoo_is_queue_under_limit
+	IsQueueUnderLimit,
+	/// cuckoo_push_to_input_queue
+	PushToInputQueue {
+		/// Queue id
+		id: u32,
+		/// The header
+		data: Vec<u8>,
+		/// The nonce in the header
+		nonce: [u8; 8],
+	},
+	/// cuckoo_read_from_output_queue
+	ReadFromOutputQueue,
+	/// cuckoo_clear_queues
+	ClearQueues,
+	/// cuckoo_start_processing
+	StartProcessing,
+	/// cuckoo_stop_processing
+	StopProcessing,
+	/// cuckoo_reset_processing
+	ResetProcessing,
+	/// cuckoo_has_processing_stopped
+	HasProcessingStopped,
+}
+
+/// What a host reports about the plugin it has loaded
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedPlugin {
+	/// The plugin's proof size
+	pub proof_size: usize,
+
+	/// The plugin's metadata
+	pub metadata: CuckooPluginMetadata,
+
+	/// The optional functions the plugin exports
+	pub exports: Vec<String>,
+}
+
+/// The result of a request, one per line on the host's stdout
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum HostResponse {
+	/// The plugin was loaded
+	Loaded(HostedPlugin),
+	/// A function's return code
+	Code(u32),
+	/// The result of a function returning a value
+	Value {
+		/// Return code
+		code: u32,
+		/// The value read
+		value: u32,
+	},
+	/// The result of a function returning JSON
+	Json(String),
+	/// The result of a solve
+	Solution {
+		/// Return code
+		code: u32,
+		/// The cuckoo size solved at
+		cuckoo_size: u32,
+		/// The solution nonces, if found
+		nonces: Vec<u32>,
+	},
+	/// The result of reading the output queue
+	Output {
+		/// Return code
+		code: u32,
+		/// Queue id of the solution
+		id: u32,
+		/// The cuckoo size solved at
+		cuckoo_size: u32,
+		/// The solution nonces, if found
+		nonces: Vec<u32>,
+		/// The nonce in the solved header
+		nonce: [u8; 8],
+	},
+	/// The request failed
+	Error(String),
+}
+
+/// #Description
+///
+/// Finds the plugin host executable: the path in `CUCKOO_PLUGIN_HOST` if
+/// set, otherwise `cuckoo-plugin-host` in the running executable's
+/// directory or its parent.
+///
+/// #Returns
+///
+/// * Some(path) if found
+/// * None otherwise
+///
+
+pub fn default_host_path() -> Option<PathBuf> {
+	if let Some(p) = env::var_os(PLUGIN_HOST_ENV) {
+		return Some(PathBuf::from(p));
+	}
+	let exe = env::current_exe().ok()?;
+	let name = format!("{}{}", PLUGIN_HOST_NAME, env::consts::EXE_SUFFIX);
+	exe.ancestors()
+		.skip(1)
+		.take(2)
+		.map(|d| d.join(&name))
+		.find(|p| p.is_file())
+}
+
+/// A running host process
+struct HostProcess {
+	child: Child,
+	requests: ChildStdin,
+	/// Response lines, read from the host's stdout on their own thread
+	/// so a request can give up waiting on them
+	responses: Receiver<io::Result<String>>,
+}
+
+impl HostProcess {
+	/// Starts a host and loads the plugin into it
+	fn start(host_path: &Path, plugin_path: &str) -> Result<(HostProcess, HostedPlugin), CuckooMinerError> {
+		let mut child = Command::new(host_path)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::inherit())
+			.spawn()
+			.map_err(|e| CuckooMinerError::IoError {
+				context: format!("Error starting plugin host {}", host_path.display()),
+				source: e,
+			})?;
+		let (requests, responses) = match (child.stdin.take(), child.stdout.take()) {
+			(Some(i), Some(o)) => (i, read_responses(o)),
+			_ => {
+				let _ = child.kill();
+				return Err(CuckooMinerError::PluginIOError(format!(
+					"{} - Couldn't connect to plugin host",
+					plugin_path
+				)));
+			}
+		};
+		let mut host = HostProcess {
+			child: child,
+			requests: requests,
+			responses: responses,
+		};
+		let response = host.request(
+			&HostRequest::Load {
+				path: String::from(plugin_path),
+			},
+			DEFAULT_REQUEST_TIMEOUT,
+		);
+		match response {
+			Ok(HostResponse::Loaded(p)) => Ok((host, p)),
+			Ok(HostResponse::Error(e)) => {
+				host.shutdown();
+				Err(CuckooMinerError::PluginIOError(e))
+			}
+			Ok(r) => {
+				host.shutdown();
+				Err(CuckooMinerError::PluginIOError(format!(
+					"{} - Unexpected response from plugin host: {:?}",
+					plugin_path, r
+				)))
+			}
+			Err(e) => {
+				let status = host.shutdown();
+				Err(CuckooMinerError::IoError {
+					context: format!("Plugin host exited ({}) loading {}", status, plugin_path),
+					source: e,
+				})
+			}
+		}
+	}
+
+	/// Sends a request and waits for the response, killing the host if
+	/// it doesn't respond within the timeout
+	fn request(&mut self, request: &HostRequest, timeout: time::Duration) -> io::Result<HostResponse> {
+		let mut line = serde_json::to_string(request)?;
+		line.push('\n');
+		self.requests.write_all(line.as_bytes())?;
+		self.requests.flush()?;
+		match self.responses.recv_timeout(timeout) {
+			Ok(response) => Ok(serde_json::from_str(&response?)?),
+			Err(RecvTimeoutError::Timeout) => {
+				let _ = self.child.kill();
+				Err(io::Error::new(
+					io::ErrorKind::TimedOut,
+					format!("plugin host didn't respond within {}ms", timeout.as_millis()),
+				))
+			}
+			Err(RecvTimeoutError::Disconnected) => {
+				Err(io::Error::new(io::ErrorKind::UnexpectedEof, "plugin host exited"))
+			}
+		}
+	}
+
+	/// Closes the host's input, so it unloads the plugin and exits,
+	/// killing it if it doesn't. Returns how it exited
+	fn shutdown(self) -> String {
+		let HostProcess { mut child, requests, .. } = self;
+		drop(requests);
+		for _ in 0..SHUTDOWN_TIMEOUT_MS / 10 {
+			match child.try_wait() {
+				Ok(Some(status)) => return status.to_string(),
+				Ok(None) => thread::sleep(time::Duration::from_millis(10)),
+				Err(e) => return e.to_string(),
+			}
+		}
+		let _ = child.kill();
+		match child.wait() {
+			Ok(status) => format!("killed, {}", status),
+			Err(e) => e.to_string(),
+		}
+	}
+}
+
+/// Reads response lines from a host's stdout until it closes, on a
+/// thread of their own
+fn read_responses(stdout: ChildStdout) -> Receiver<io::Result<String>> {
+	let (sender, receiver) = mpsc::channel();
+	thread::spawn(move || {
+		for line in BufReader::new(stdout).lines() {
+			let failed = line.is_err();
+			if sender.send(line).is_err() || failed {
+				break;
+			}
+		}
+	});
+	receiver
+}
+
+/// The host, and what's needed to restart it
+struct HostState {
+	host: Option<HostProcess>,
+	/// Parameters set so far, set again after a restart
+	parameters: Vec<(String, u32, u32)>,
+	/// Whether processing was started, and is restarted after a restart
+	processing: bool,
+	/// Restarts since the last successful request
+	restarts: u32,
+	/// How long the host is given to respond to a request other than a
+	/// solve
+	request_timeout: time::Duration,
+}
+
+/// #Description
+///
+/// A plugin loaded into a separate host process. Calls are proxied to
+/// the host one at a time, so unlike an in-process
+/// [PluginLibrary](struct.PluginLibrary.html), reading stats waits for
+/// any synchronous [solve](trait.SolverBackend.html#tymethod.solve) in
+/// progress to finish.
+///
+/// The host is shut down when this is dropped.
+///
+
+pub struct IsolatedPlugin {
+	host_path: PathBuf,
+	lib_full_path: String,
+	plugin: HostedPlugin,
+	state: Mutex<HostState>,
+}
+
+impl IsolatedPlugin {
+	/// #Description
+	///
+	/// Starts a host process, found via
+	/// [default_host_path](fn.default_host_path.html), and loads the
+	/// plugin into it.
+	///
+	/// #Arguments
+	///
+	/// * `lib_full_path` The full path to the plugin
+	///
+	/// #Returns
+	///
+	/// * Ok(IsolatedPlugin) if the plugin was loaded
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if the host
+	/// can't be found or started, or the plugin can't be loaded
+	///
+
+	pub fn new(lib_full_path: &str) -> Result<IsolatedPlugin, CuckooMinerError> {
+		match default_host_path() {
+			Some(p) => IsolatedPlugin::with_host(&p, lib_full_path),
+			None => Err(CuckooMinerError::PluginNotLoadedError(format!(
+				"{} - Can't find {} to host the plugin, set {} to its path",
+				lib_full_path, PLUGIN_HOST_NAME, PLUGIN_HOST_ENV
+			))),
+		}
+	}
+
+	/// #Description
+	///
+	/// Starts the given host executable, and loads the plugin into it.
+	///
+	/// #Arguments
+	///
+	/// * `host_path` The path to the plugin host executable
+	///
+	/// * `lib_full_path` The full path to the plugin
+	///
+	/// #Returns
+	///
+	/// * Ok(IsolatedPlugin) if the plugin was loaded
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if the host
+	/// can't be started, or the plugin can't be loaded
+	///
+
+	pub fn with_host(host_path: &Path, lib_full_path: &str) -> Result<IsolatedPlugin, CuckooMinerError> {
+		let (host, plugin) = HostProcess::start(host_path, lib_full_path)?;
+		debug!("Loaded plugin {} in host process {}", lib_full_path, host.child.id());
+		Ok(IsolatedPlugin {
+			host_path: host_path.to_path_buf(),
+			lib_full_path: String::from(lib_full_path),
+			plugin: plugin,
+			state: Mutex::new(HostState {
+				host: Some(host),
+				parameters: Vec::new(),
+				processing: false,
+				restarts: 0,
+				request_timeout: DEFAULT_REQUEST_TIMEOUT,
+			}),
+		})
+	}
+
+	/// #Description
+	///
+	/// Sets how long the host is given to respond to a request before
+	/// it's killed and restarted, for every request but a solve. Defaults
+	/// to [DEFAULT_REQUEST_TIMEOUT](constant.DEFAULT_REQUEST_TIMEOUT.html).
+	///
+	/// #Arguments
+	///
+	/// * `timeout` How long to wait for each response
+	///
+
+	pub fn set_request_timeout(&self, timeout: time::Duration) {
+		self.state.lock().unwrap().request_timeout = timeout;
+	}
+
+	/// The process id of the current host, if it's running
+	pub fn host_id(&self) -> Option<u32> {
+		self.state.lock().unwrap().host.as_ref().map(|h| h.child.id())
+	}
+
+	/// Makes a request in the host, restarting the host if it has died.
+	/// Returns None if the host died during the request, or couldn't be
+	/// restarted
+	fn call(&self, request: &HostRequest) -> Option<HostResponse> {
+		let mut state = self.state.lock().unwrap();
+		if state.host.is_none() {
+			self.restart(&mut state);
+		}
+		let timeout = match *request {
+			HostRequest::Solve { .. } => SOLVE_TIMEOUT,
+			_ => state.request_timeout,
+		};
+		let result = match state.host {
+			Some(ref mut h) => h.request(request, timeout),
+			None => return None,
+		};
+		match result {
+			Ok(r) => {
+				state.restarts = 0;
+				Some(r)
+			}
+			Err(e) => {
+				if let Some(h) = state.host.take() {
+					let status = h.shutdown();
+					warn!("{} - Plugin host failed: {} ({})", self.lib_full_path, e, status);
+				}
+				self.restart(&mut state);
+				None
+			}
+		}
+	}
+
+	/// Starts a new host, restoring the parameters and processing state
+	/// of the last one
+	fn restart(&self, state: &mut HostState) {
+		if state.restarts >= MAX_RESTARTS {
+			return;
+		}
+		state.restarts += 1;
+		let mut host = match HostProcess::start(&self.host_path, &self.lib_full_path) {
+			Ok((h, _)) => h,
+			Err(e) => {
+				error!("{} - Couldn't restart plugin host: {}", self.lib_full_path, e);
+				return;
+			}
+		};
+		let mut requests: Vec<HostRequest> = state
+			.parameters
+			.iter()
+			.map(|p| HostRequest::SetParameter {
+				name: p.0.clone(),
+				device_id: p.1,
+				value: p.2,
+			})
+			.collect();
+		if state.processing {
+			requests.push(HostRequest::StartProcessing);
+		}
+		for r in &requests {
+			let error = match host.request(r, state.request_timeout) {
+				Ok(HostResponse::Code(0)) => continue,
+				Ok(HostResponse::Code(c)) => format!("{:?} failed: {}", r, PluginReturnCode::from_code(c)),
+				Ok(response) => format!("Unexpected response to {:?}: {:?}", r, response),
+				Err(e) => e.to_string(),
+			};
+			let status = host.shutdown();
+			error!("{} - Plugin host failed after restart: {} ({})", self.lib_full_path, error, status);
+			return;
+		}
+		info!("{} - Restarted plugin host (attempt {})", self.lib_full_path, state.restarts);
+		state.host = Some(host);
+	}
+
+	/// Makes a request returning only a code
+	fn call_code(&self, request: &HostRequest) -> u32 {
+		match self.call(request) {
+			Some(HostResponse::Code(c)) => c,
+			_ => PluginReturnCode::HostUnavailable.code(),
+		}
+	}
+
+	/// Makes a request returning JSON
+	fn call_json(&self, request: &HostRequest, function: &str) -> Result<String, CuckooMinerError> {
+		match self.call(request) {
+			Some(HostResponse::Json(j)) => Ok(j),
+			Some(HostResponse::Error(e)) => Err(CuckooMinerError::PluginIOError(e)),
+			_ => Err(CuckooMinerError::plugin_call(
+				&self.lib_full_path,
+				function,
+				PluginReturnCode::HostUnavailable.code(),
+				String::from("calling the plugin host"),
+			)),
+		}
+	}
+}
+
+impl Drop for IsolatedPlugin {
+	fn drop(&mut self) {
+		if let Ok(mut state) = self.state.lock() {
+			if let Some(h) = state.host.take() {
+				let status = h.shutdown();
+				debug!("{} - Plugin host exited ({})", self.lib_full_path, status);
+			}
+		}
+	}
+}
+
+impl SolverBackend for IsolatedPlugin {
+	fn full_path(&self) -> &str {
+		&self.lib_full_path
+	}
+
+	fn proof_size(&self) -> usize {
+		self.plugin.proof_size
+	}
+
+	fn metadata(&self) -> &CuckooPluginMetadata {
+		&self.plugin.metadata
+	}
+
+	fn exports(&self, function: &str) -> bool {
+		!OPTIONAL_FUNCTIONS.contains(&function) || self.plugin.exports.iter().any(|f| f == function)
+	}
+
+	fn solve(&self, header: &[u8], cuckoo_size: &mut u32, solution: &mut [u32]) -> u32 {
+		if solution.len() < self.plugin.proof_size {
+			return 3;
+		}
+		let request = HostRequest::Solve {
+			header: header.to_vec(),
+		};
+		match self.call(&request) {
+			Some(HostResponse::Solution {
+				code,
+				cuckoo_size: size,
+				nonces,
+			}) => {
+				if code == 1 {
+					if nonces.len() > solution.len() {
+						return 3;
+					}
+					solution[..nonces.len()].copy_from_slice(&nonces);
+				}
+				*cuckoo_size = size;
+				code
+			}
+			_ => PluginReturnCode::HostUnavailable.code(),
+		}
+	}
+
+	fn parameter_list(&self) -> Result<String, CuckooMinerError> {
+		self.call_json(&HostRequest::ParameterList, "cuckoo_parameter_list")
+	}
+
+	fn get_parameter(&self, name: &str, device_id: u32, value: &mut u32) -> u32 {
+		let request = HostRequest::GetParameter {
+			name: String::from(name),
+			device_id: device_id,
+		};
+		match self.call(&request) {
+			Some(HostResponse::Value { code, value: v }) => {
+				*value = v;
+				code
+			}
+			_ => PluginReturnCode::HostUnavailable.code(),
+		}
+	}
+
+	fn set_parameter(&self, name: &str, device_id: u32, value: u32) -> u32 {
+		let code = self.call_code(&HostRequest::SetParameter {
+			name: String::from(name),
+			device_id: device_id,
+			value: value,
+		});
+		if code == 0 {
+			let mut state = self.state.lock().unwrap();
+			state.parameters.retain(|p| p.0 != name || p.1 != device_id);
+			state.parameters.push((String::from(name), device_id, value));
+		}
+		code
+	}
+
+	fn stats(&self) -> Result<String, CuckooMinerError> {
+		self.call_json(&HostRequest::Stats, "cuckoo_get_stats")
+	}
+
+	fn is_queue_under_limit(&self) -> bool {
+		self.call_code(&HostRequest::IsQueueUnderLimit) == 1
+	}
+
+	fn push_to_input_queue(&self, id: u32, data: &[u8], nonce: &[u8; 8]) -> u32 {
+		self.call_code(&HostRequest::PushToInputQueue {
+			id: id,
+			data: data.to_vec(),
+			nonce: *nonce,
+		})
+	}
+
+	fn read_from_output_queue(
+		&self,
+		id: &mut u32,
+		solution: &mut [u32],
+		cuckoo_size: &mut u32,
+		nonce: &mut [u8; 8],
+	) -> bool {
+		match self.call(&HostRequest::ReadFromOutputQueue) {
+			Some(HostResponse::Output {
+				code,
+				id: i,
+				cuckoo_size: size,
+				nonces,
+				nonce: n,
+			}) => {
+				if code != 1 {
+					if code != 0 {
+						error!(
+							"{}",
+							CuckooMinerError::plugin_call(
+								&self.lib_full_path,
+								"cuckoo_read_from_output_queue",
+								code,
+								String::from("reading a solution"),
+							)
+						);
+					}
+					return false;
+				}
+				if nonces.len() > solution.len() {
+					return false;
+				}
+				solution[..nonces.len()].copy_from_slice(&nonces);
+				*id = i;
+				*cuckoo_size = size;
+				*nonce = n;
+				true
+			}
+			_ => false,
+		}
+	}
+
+	fn clear_queues(&self) {
+		self.call(&HostRequest::ClearQueues);
+	}
+
+	fn start_processing(&self) -> u32 {
+		let code = self.call_code(&HostRequest::StartProcessing);
+		self.state.lock().unwrap().processing = code == 0;
+		code
+	}
+
+	fn stop_processing(&self) -> u32 {
+		self.state.lock().unwrap().processing = false;
+		self.call_code(&HostRequest::StopProcessing)
+	}
+
+	fn reset_processing(&self) -> u32 {
+		self.call_code(&HostRequest::ResetProcessing)
+	}
+
+	fn has_processing_stopped(&self) -> bool {
+		// a host that has died isn't processing
+		match self.call(&HostRequest::HasProcessingStopped) {
+			Some(HostResponse::Code(c)) => c != 0,
+			_ => true,
+		}
+	}
+}
+
+/// Makes a request of the loaded plugin, in the host
+fn handle_request(library: &PluginLibrary, request: HostRequest) -> HostResponse {
+	match request {
+		HostRequest::Load { .. } => HostResponse::Error(format!(
+			"{} - Plugin already loaded",
+			library.lib_full_path
+		)),
+		HostRequest::Solve { header } => {
+			let mut cuckoo_size = 0;
+			let mut nonces = vec![0; library.proof_size];
+			let code = library.call_cuckoo(&header, &mut cuckoo_size, &mut nonces);
+			if code != 1 {
+				nonces.clear();
+			}
+			HostResponse::Solution {
+				code: code,
+				cuckoo_size: cuckoo_size,
+				nonces: nonces,
+			}
+		}
+		HostRequest::ParameterList => match library.parameter_list() {
+			Ok(j) => HostResponse::Json(j),
+			Err(e) => HostResponse::Error(e.to_string()),
+		},
+		HostRequest::GetParameter { name, device_id } => {
+			let mut value = 0;
+			let code = library.call_cuckoo_get_parameter(name.as_bytes(), device_id, &mut value);
+			HostResponse::Value {
+				code: code,
+				value: value,
+			}
+		}
+		HostRequest::SetParameter {
+			name,
+			device_id,
+			value,
+		} => HostResponse::Code(library.call_cuckoo_set_parameter(name.as_bytes(), device_id, value)),
+		HostRequest::Stats => match library.stats() {
+			Ok(j) => HostResponse::Json(j),
+			Err(e) => HostResponse::Error(e.to_string()),
+		},
+		HostRequest::IsQueueUnderLimit => HostResponse::Code(library.call_cuckoo_is_queue_under_limit()),
+		HostRequest::PushToInputQueue { id, data, nonce } => {
+			HostResponse::Code(library.call_cuckoo_push_to_input_queue(id, &data, &nonce))
+		}
+		HostRequest::ReadFromOutputQueue => {
+			let mut id = 0;
+			let mut cuckoo_size = 0;
+			let mut nonce = [0; 8];
+			let mut nonces = vec![0; library.proof_size];
+			let code = library.call_cuckoo_read_from_output_queue(&mut id, &mut nonces, &mut cuckoo_size, &mut nonce);
+			if code != 1 {
+				nonces.clear();
+			}
+			HostResponse::Output {
+				code: code,
+				id: id,
+				cuckoo_size: cuckoo_size,
+				nonces: nonces,
+				nonce: nonce,
+			}
+		}
+		HostRequest::ClearQueues => {
+			library.call_cuckoo_clear_queues();
+			HostResponse::Code(0)
+		}
+		HostRequest::StartProcessing => HostResponse::Code(library.call_cuckoo_start_processing()),
+		HostRequest::StopProcessing => HostResponse::Code(library.call_cuckoo_stop_processing()),
+		HostRequest::ResetProcessing => HostResponse::Code(library.call_cuckoo_reset_processing()),
+		HostRequest::HasProcessingStopped => HostResponse::Code(library.call_cuckoo_has_processing_stopped()),
+	}
+}
+
+/// Loads the plugin named by a Load request
+fn load_plugin(request: HostRequest) -> (Option<PluginLibrary>, HostResponse) {
+	let path = match request {
+		HostRequest::Load { path } => path,
+		r => {
+			return (
+				None,
+				HostResponse::Error(format!("Expected a plugin to load, got {:?}", r)),
+			)
+		}
+	};
+	match PluginLibrary::new(&path) {
+		Ok(l) => {
+			let exports = OPTIONAL_FUNCTIONS
+				.iter()
+				.filter(|f| l.exports(f))
+				.map(|f| String::from(*f))
+				.collect();
+			let response = HostResponse::Loaded(HostedPlugin {
+				proof_size: l.proof_size,
+				metadata: l.metadata.clone(),
+				exports: exports,
+			});
+			(Some(l), response)
+		}
+		Err(e) => (None, HostResponse::Error(e.to_string())),
+	}
+}
+
+/// The stream responses are written to. On unix, this is the original
+/// stdout, and stdout is pointed at stderr so plugin output can't be
+/// mistaken for a response
+#[cfg(unix)]
+fn response_stream() -> Result<Box<dyn Write>, CuckooMinerError> {
+	use std::fs::File;
+	use std::os::unix::io::FromRawFd;
+	use libc;
+
+	let fd = unsafe { libc::dup(1) };
+	if fd < 0 || unsafe { libc::dup2(2, 1) } < 0 {
+		return Err(CuckooMinerError::IoError {
+			context: String::from("Error redirecting plugin host stdout"),
+			source: io::Error::last_os_error(),
+		});
+	}
+	Ok(Box::new(unsafe { File::from_raw_fd(fd) }))
+}
+
+#[cfg(not(unix))]
+fn response_stream() -> Result<Box<dyn Write>, CuckooMinerError> {
+	Ok(Box::new(io::stdout()))
+}
+
+/// #Description
+///
+/// Runs a plugin host, serving requests from an
+/// [IsolatedPlugin](struct.IsolatedPlugin.html) on stdin until stdin is
+/// closed, then unloading the plugin. This is the whole of the
+/// `cuckoo-plugin-host` executable.
+///
+/// #Returns
+///
+/// * Ok() once stdin is closed
+/// * A [CuckooMinerError](enum.CuckooMinerError.html) if a request
+/// can't be read or a response written
+///
+
+pub fn run_plugin_host() -> Result<(), CuckooMinerError> {
+	let mut responses = response_stream()?;
+	let stdin = io::stdin();
+	let mut library: Option<PluginLibrary> = None;
+	for line in stdin.lock().lines() {
+		let request: HostRequest = serde_json::from_str(&line?)?;
+		let response = match library {
+			Some(ref l) => handle_request(l, request),
+			None => {
+				let (l, r) = load_plugin(request);
+				library = l;
+				r
+			}
+		};
+		let mut line = serde_json::to_string(&response)?;
+		line.push('\n');
+		responses.write_all(line.as_bytes())?;
+		responses.flush()?;
+	}
+	if let Some(l) = library {
+		l.unload();
+	}
+	Ok(())
+}
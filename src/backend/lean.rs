@@ -0,0 +1,622 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A pure-Rust lean solver, built into the crate so that small graphs
+//! (e.g. cuckoo 16 or cuckatoo 19, as used by testnets and tests) can be
+//! mined without building any plugins. It's far slower than the C
+//! plugins, and only supports graphs of up to
+//! [MAX_LEAN_EDGE_BITS](constant.MAX_LEAN_EDGE_BITS.html) edge bits.
+//!
+//! Built-in solvers are selected by setting a
+//! [CuckooMinerConfig](struct.CuckooMinerConfig.html)'s
+//! `plugin_full_path` to a path starting with
+//! [BUILTIN_PREFIX](constant.BUILTIN_PREFIX.html), e.g.
+//! `builtin:lean_rust_cpu_16` or `builtin:cuckatoo_lean_rust_cpu_19`.
+//!
+//! #Example
+//! ```
+//! extern crate cuckoo_miner as cuckoo;
+//! use cuckoo::{CuckooMiner, CuckooMinerConfig, CuckooMinerSolution};
+//!
+//! let mut config = CuckooMinerConfig::new();
+//! config.plugin_full_path = String::from("builtin:lean_rust_cpu_16");
+//! let miner = CuckooMiner::new(vec![config]).unwrap();
+//!
+//! let mut header = [0; 32];
+//! header[0] = 82;
+//! let mut cuckoo_size = 0;
+//! let mut solution = CuckooMinerSolution::new();
+//! assert!(miner.mine(&header, &mut cuckoo_size, &mut solution, 0).unwrap());
+//! assert!(solution.verify(&header).is_ok());
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use super::backend::SolverBackend;
+use cuckoo_sys::manager::DEFAULT_PROOF_SIZE;
+use error::error::{CuckooMinerError, PluginReturnCode};
+use manager::manager::CuckooPluginParameter;
+use manager::memory::NUM_THREADS_PARAM;
+use manager::metadata::{CuckooPluginDeviceType, CuckooPluginMetadata};
+use miner::miner::CuckooMinerDeviceStats;
+use miner::verify::{verify, CuckooGraphType, SipHashKeys};
+
+/// Prefix of the `plugin_full_path` of a built-in solver
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+/// The largest graphs, in edge bits, the built-in solver will mine
+pub const MAX_LEAN_EDGE_BITS: u32 = 24;
+
+/// The built-in solvers listed by the plugin manager
+pub const BUILTIN_SOLVERS: [&str; 2] = ["builtin:lean_rust_cpu_16", "builtin:cuckatoo_lean_rust_cpu_19"];
+
+/// Most threads the solver can be asked to mine with
+const MAX_THREADS: u32 = 64;
+
+/// How long idle workers wait for a header before checking whether
+/// processing has been stopped
+const WORKER_POLL: Duration = Duration::from_millis(100);
+
+/// Whether a plugin path names a built-in solver
+
+pub fn is_builtin_solver(path: &str) -> bool {
+	path.starts_with(BUILTIN_PREFIX)
+}
+
+/// Milliseconds since the epoch, as reported in device stats
+fn now_millis() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs() * 1000 + u64::from(d.subsec_millis()))
+		.unwrap_or(0)
+}
+
+/// The graphs the solver searches, and how to search them
+
+#[derive(Clone, Copy)]
+struct LeanGraph {
+	graph_type: CuckooGraphType,
+	cuckoo_size: u32,
+	edge_bits: u32,
+	proof_size: usize,
+}
+
+impl LeanGraph {
+	/// The endpoint of an edge on one side of the graph, without the
+	/// side bit that cuckoo graphs add
+	fn node(&self, keys: &SipHashKeys, edge: u32, uorv: usize) -> u32 {
+		let mask = (1u64 << self.edge_bits) - 1;
+		(keys.siphash24(2 * u64::from(edge) + uorv as u64) & mask) as u32
+	}
+
+	/// The node an endpoint at `node` continues a cycle through. In a
+	/// cuckoo graph that's the same node, and in a cuckatoo graph it's
+	/// the node's partner
+	fn link(&self, node: u32) -> u32 {
+		match self.graph_type {
+			CuckooGraphType::Cuckoo => node,
+			CuckooGraphType::Cuckatoo => node ^ 1,
+		}
+	}
+
+	/// Whether an edge with an endpoint at `node` can be part of a cycle,
+	/// given the number of live edges at each node (saturating at 2)
+	fn has_partner(&self, degrees: &[u8], node: u32) -> bool {
+		match self.graph_type {
+			CuckooGraphType::Cuckoo => degrees[node as usize] >= 2,
+			CuckooGraphType::Cuckatoo => degrees[node as usize ^ 1] >= 1,
+		}
+	}
+
+	/// Host memory needed to search a graph: the live edge bitmap, one
+	/// side's degree counts, and the edges surviving the first round of
+	/// trimming (roughly 40%) at 12 bytes each
+	fn memory_bytes(&self) -> u64 {
+		let edges = 1u64 << self.edge_bits;
+		edges / 8 + edges + edges * 5
+	}
+
+	/// #Description
+	///
+	/// Searches the graph generated from a header for cycles of the
+	/// proof size.
+	///
+	/// The first round of trimming is lean, keeping only a bitmap of
+	/// live edges and recomputing their endpoints. Edges with an endpoint
+	/// which can't continue a cycle are removed from each side in turn.
+	/// The survivors are then trimmed with their endpoints stored until
+	/// no more can be removed, and the remaining cycles followed from
+	/// each edge.
+	///
+	/// #Returns
+	///
+	/// The verified cycles found, each as ascending edge indices
+	///
+
+	fn find_cycles(&self, header: &[u8]) -> Vec<Vec<u32>> {
+		let keys = SipHashKeys::from_header(header);
+		let num_edges = 1usize << self.edge_bits;
+		let mut alive = vec![!0u64; num_edges.div_ceil(64)];
+		let is_alive = |alive: &[u64], e: usize| alive[e / 64] >> (e % 64) & 1 == 1;
+
+		let mut degrees = vec![0u8; num_edges];
+		for uorv in 0..2 {
+			for d in degrees.iter_mut() {
+				*d = 0;
+			}
+			for e in (0..num_edges).filter(|&e| is_alive(&alive, e)) {
+				let d = &mut degrees[self.node(&keys, e as u32, uorv) as usize];
+				*d = (*d + 1).min(2);
+			}
+			for e in 0..num_edges {
+				if is_alive(&alive, e) && !self.has_partner(&degrees, self.node(&keys, e as u32, uorv)) {
+					alive[e / 64] &= !(1 << (e % 64));
+				}
+			}
+		}
+
+		let mut edges: Vec<(u32, [u32; 2])> = (0..num_edges)
+			.filter(|&e| is_alive(&alive, e))
+			.map(|e| {
+				let e = e as u32;
+				(e, [self.node(&keys, e, 0), self.node(&keys, e, 1)])
+			})
+			.collect();
+		loop {
+			let before = edges.len();
+			for uorv in 0..2 {
+				for d in degrees.iter_mut() {
+					*d = 0;
+				}
+				for e in &edges {
+					let d = &mut degrees[e.1[uorv] as usize];
+					*d = (*d + 1).min(2);
+				}
+				edges.retain(|e| self.has_partner(&degrees, e.1[uorv]));
+			}
+			if edges.len() == before {
+				break;
+			}
+		}
+		debug!("Lean solver: {} edges left after trimming", edges.len());
+
+		let mut adjacency = [HashMap::new(), HashMap::new()];
+		for (i, e) in edges.iter().enumerate() {
+			for (uorv, a) in adjacency.iter_mut().enumerate() {
+				a.entry(e.1[uorv]).or_insert_with(Vec::new).push(i);
+			}
+		}
+		let mut cycles = Vec::new();
+		let mut path = Vec::with_capacity(self.proof_size);
+		for start in 0..edges.len() {
+			path.push(start);
+			self.follow(&edges, &adjacency, &mut path, 1, &mut cycles);
+			path.clear();
+		}
+		cycles.retain(|c| verify(header, c, self.edge_bits, self.graph_type).is_ok());
+		cycles
+	}
+
+	/// Extends the path through every edge linked to its last edge's
+	/// endpoint on side `uorv`, recording it if it closes a cycle of the
+	/// proof size. Each cycle is found once: starting from its lowest
+	/// edge, and leaving that edge on side 1
+	fn follow(
+		&self,
+		edges: &[(u32, [u32; 2])],
+		adjacency: &[HashMap<u32, Vec<usize>>; 2],
+		path: &mut Vec<usize>,
+		uorv: usize,
+		cycles: &mut Vec<Vec<u32>>,
+	) {
+		let last = path[path.len() - 1];
+		let linked = match adjacency[uorv].get(&self.link(edges[last].1[uorv])) {
+			Some(l) => l,
+			None => return,
+		};
+		for &next in linked {
+			if next == last {
+				continue;
+			}
+			if next == path[0] {
+				if path.len() == self.proof_size && uorv == 0 {
+					let mut cycle: Vec<u32> = path.iter().map(|&i| edges[i].0).collect();
+					cycle.sort();
+					cycles.push(cycle);
+				}
+				continue;
+			}
+			if next < path[0] || path.len() == self.proof_size || path.contains(&next) {
+				continue;
+			}
+			path.push(next);
+			self.follow(edges, adjacency, path, uorv ^ 1, cycles);
+			path.pop();
+		}
+	}
+}
+
+/// Stats for the solver's single device
+#[derive(Default)]
+struct LeanStats {
+	last_start_time: u64,
+	last_end_time: u64,
+	last_solution_time: u64,
+	iterations_completed: u32,
+}
+
+/// The queues, parameter values and stats of a solver, shared with its
+/// worker threads
+struct LeanState {
+	input: VecDeque<(u32, Vec<u8>, [u8; 8])>,
+	output: VecDeque<(u32, Vec<u32>, [u8; 8])>,
+	processing: bool,
+	threads: u32,
+	running_workers: u32,
+	values: HashMap<(String, u32), u32>,
+	stats: LeanStats,
+}
+
+struct LeanShared {
+	graph: LeanGraph,
+	state: Mutex<LeanState>,
+	/// Signalled when a header is queued or processing stops
+	work: Condvar,
+}
+
+impl LeanShared {
+	/// Searches a header, recording the search in the stats
+	fn search(&self, header: &[u8]) -> Vec<Vec<u32>> {
+		let start_time = now_millis();
+		let started = Instant::now();
+		self.state.lock().unwrap().stats.last_start_time = start_time;
+		let cycles = self.graph.find_cycles(header);
+		let mut state = self.state.lock().unwrap();
+		let elapsed = started.elapsed();
+		state.stats.last_end_time = now_millis();
+		state.stats.last_solution_time = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+		state.stats.iterations_completed = state.stats.iterations_completed.wrapping_add(1);
+		cycles
+	}
+
+	/// Solves queued headers until processing is stopped
+	fn work(&self) {
+		loop {
+			let (id, header, nonce) = {
+				let mut state = self.state.lock().unwrap();
+				loop {
+					if !state.processing {
+						state.running_workers -= 1;
+						return;
+					}
+					if let Some(h) = state.input.pop_front() {
+						break h;
+					}
+					state = self.work.wait_timeout(state, WORKER_POLL).unwrap().0;
+				}
+			};
+			let cycles = self.search(&header);
+			let mut state = self.state.lock().unwrap();
+			for c in cycles {
+				state.output.push_back((id, c, nonce));
+			}
+		}
+	}
+}
+
+/// #Description
+///
+/// A [SolverBackend](trait.SolverBackend.html) searching graphs with a
+/// pure-Rust lean solver, so no plugins need to be built. Usually created
+/// by [CuckooMiner](struct.CuckooMiner.html) from a config whose
+/// `plugin_full_path` names a built-in solver, and listed by
+/// [load_builtin_solvers](struct.CuckooPluginManager.html#method.load_builtin_solvers).
+///
+/// The solver declares a `NUM_THREADS` parameter, the number of headers
+/// from its input queue it searches at once, and reports a single
+/// device. Every cycle found in a header is put in the output queue.
+///
+
+pub struct LeanSolver {
+	full_path: String,
+	metadata: CuckooPluginMetadata,
+	parameters: Vec<CuckooPluginParameter>,
+	shared: Arc<LeanShared>,
+}
+
+impl LeanSolver {
+	/// #Description
+	///
+	/// Creates the built-in solver named by a path, of the form
+	/// `builtin:<name>_<cuckoo size>`. Names starting with `cuckatoo`
+	/// mine cuckatoo graphs, and others cuckoo graphs, following the
+	/// plugin naming convention.
+	///
+	/// #Arguments
+	///
+	/// * `path` (IN) The solver's path, e.g. `builtin:lean_rust_cpu_16`
+	///
+	/// #Returns
+	///
+	/// * Ok, with the solver, searching for cycles of 42 edges
+	/// * A [CuckooMinerError::PluginNotLoadedError](enum.CuckooMinerError.html)
+	/// if the path doesn't name a built-in solver, or the graph size isn't
+	/// supported
+	///
+
+	pub fn new(path: &str) -> Result<LeanSolver, CuckooMinerError> {
+		if !is_builtin_solver(path) {
+			return Err(CuckooMinerError::PluginNotLoadedError(format!(
+				"{} isn't a built-in solver",
+				path
+			)));
+		}
+		let mut metadata = CuckooPluginMetadata::from_file_name(&path[BUILTIN_PREFIX.len()..]);
+		let cuckoo_size = match metadata.name.rsplit('_').next().and_then(|s| s.parse::<u32>().ok()) {
+			Some(s) => s,
+			None => {
+				return Err(CuckooMinerError::PluginNotLoadedError(format!(
+					"Built-in solver {} doesn't end with a cuckoo size",
+					path
+				)))
+			}
+		};
+		let edge_bits = metadata.graph_type.edge_bits(cuckoo_size);
+		if edge_bits == 0 || edge_bits > MAX_LEAN_EDGE_BITS {
+			return Err(CuckooMinerError::PluginNotLoadedError(format!(
+				"Built-in solver {} has {} edge bits, but only 1 to {} are supported",
+				path, edge_bits, MAX_LEAN_EDGE_BITS
+			)));
+		}
+		let graph = LeanGraph {
+			graph_type: metadata.graph_type,
+			cuckoo_size: cuckoo_size,
+			edge_bits: edge_bits,
+			proof_size: DEFAULT_PROOF_SIZE,
+		};
+		metadata.version = Some(String::from(env!("CARGO_PKG_VERSION")));
+		metadata.device_type = CuckooPluginDeviceType::Cpu;
+		metadata.memory_bytes = Some(graph.memory_bytes());
+		metadata.reported = true;
+		Ok(LeanSolver {
+			full_path: String::from(path),
+			metadata: metadata,
+			parameters: vec![CuckooPluginParameter {
+				name: String::from(NUM_THREADS_PARAM),
+				description: String::from("Number of headers to search at once"),
+				default_value: 1,
+				min_value: 1,
+				max_value: MAX_THREADS,
+			}],
+			shared: Arc::new(LeanShared {
+				graph: graph,
+				state: Mutex::new(LeanState {
+					input: VecDeque::new(),
+					output: VecDeque::new(),
+					processing: false,
+					threads: 1,
+					running_workers: 0,
+					values: HashMap::new(),
+					stats: LeanStats::default(),
+				}),
+				work: Condvar::new(),
+			}),
+		})
+	}
+
+	/// Sets the number of edges in the cycles searched for, which must be
+	/// even. Can't be changed once processing has started
+	pub fn proof_size(mut self, proof_size: usize) -> LeanSolver {
+		if let Some(shared) = Arc::get_mut(&mut self.shared) {
+			shared.graph.proof_size = proof_size;
+		}
+		self
+	}
+
+	/// #Description
+	///
+	/// Searches the graph generated from a header for cycles, in the
+	/// calling thread.
+	///
+	/// #Arguments
+	///
+	/// * `header` (IN) The data to generate the graph from, hashed as a
+	/// plugin would
+	///
+	/// #Returns
+	///
+	/// Every cycle of the proof size found, each as ascending edge
+	/// indices which have been verified
+	///
+
+	pub fn find_cycles(&self, header: &[u8]) -> Vec<Vec<u32>> {
+		self.shared.search(header)
+	}
+
+	fn declared(&self, name: &str) -> Option<&CuckooPluginParameter> {
+		self.parameters.iter().find(|p| p.name == name)
+	}
+}
+
+impl Drop for LeanSolver {
+	fn drop(&mut self) {
+		self.stop_processing();
+	}
+}
+
+impl SolverBackend for LeanSolver {
+	fn full_path(&self) -> &str {
+		&self.full_path
+	}
+
+	fn proof_size(&self) -> usize {
+		self.shared.graph.proof_size
+	}
+
+	fn metadata(&self) -> &CuckooPluginMetadata {
+		&self.metadata
+	}
+
+	fn exports(&self, _function: &str) -> bool {
+		true
+	}
+
+	fn solve(&self, header: &[u8], cuckoo_size: &mut u32, solution: &mut [u32]) -> u32 {
+		*cuckoo_size = self.shared.graph.cuckoo_size;
+		if solution.len() < self.shared.graph.proof_size {
+			return PluginReturnCode::BufferTooShort.code();
+		}
+		match self.shared.search(header).into_iter().next() {
+			Some(c) => {
+				solution[..c.len()].copy_from_slice(&c);
+				1
+			}
+			None => 0,
+		}
+	}
+
+	fn parameter_list(&self) -> Result<String, CuckooMinerError> {
+		Ok(serde_json::to_string(&self.parameters)?)
+	}
+
+	fn get_parameter(&self, name: &str, device_id: u32, value: &mut u32) -> u32 {
+		let default_value = match self.declared(name) {
+			Some(p) => p.default_value,
+			None => return PluginReturnCode::NotFound.code(),
+		};
+		if device_id != 0 {
+			return PluginReturnCode::NoDevice.code();
+		}
+		let state = self.shared.state.lock().unwrap();
+		*value = *state
+			.values
+			.get(&(String::from(name), device_id))
+			.unwrap_or(&default_value);
+		0
+	}
+
+	fn set_parameter(&self, name: &str, device_id: u32, value: u32) -> u32 {
+		match self.declared(name) {
+			None => return PluginReturnCode::NotFound.code(),
+			Some(p) if value < p.min_value || value > p.max_value => {
+				return PluginReturnCode::OutOfRange.code()
+			}
+			Some(_) => {}
+		}
+		if device_id != 0 {
+			return PluginReturnCode::NoDevice.code();
+		}
+		let mut state = self.shared.state.lock().unwrap();
+		state.values.insert((String::from(name), device_id), value);
+		0
+	}
+
+	fn stats(&self) -> Result<String, CuckooMinerError> {
+		let state = self.shared.state.lock().unwrap();
+		let stats = vec![CuckooMinerDeviceStats {
+			plugin_name: None,
+			device_id: String::from("0"),
+			cuckoo_size: self.shared.graph.cuckoo_size.to_string(),
+			device_name: String::from("Rust lean solver"),
+			in_use: 1,
+			has_errored: 0,
+			last_start_time: state.stats.last_start_time,
+			last_end_time: state.stats.last_end_time,
+			last_solution_time: state.stats.last_solution_time,
+			iterations_completed: state.stats.iterations_completed,
+		}];
+		Ok(serde_json::to_string(&stats)?)
+	}
+
+	fn is_queue_under_limit(&self) -> bool {
+		let state = self.shared.state.lock().unwrap();
+		(state.input.len() as u32) < state.threads * 2
+	}
+
+	fn push_to_input_queue(&self, id: u32, data: &[u8], nonce: &[u8; 8]) -> u32 {
+		let mut state = self.shared.state.lock().unwrap();
+		if state.input.len() as u32 >= state.threads * 2 {
+			return PluginReturnCode::OutOfRange.code();
+		}
+		state.input.push_back((id, data.to_vec(), *nonce));
+		self.shared.work.notify_one();
+		0
+	}
+
+	fn read_from_output_queue(
+		&self,
+		id: &mut u32,
+		solution: &mut [u32],
+		cuckoo_size: &mut u32,
+		nonce: &mut [u8; 8],
+	) -> bool {
+		let mut state = self.shared.state.lock().unwrap();
+		match state.output.pop_front() {
+			Some((_, ref s, _)) if s.len() > solution.len() => false,
+			Some((i, s, n)) => {
+				solution[..s.len()].copy_from_slice(&s);
+				*id = i;
+				*cuckoo_size = self.shared.graph.cuckoo_size;
+				*nonce = n;
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn clear_queues(&self) {
+		let mut state = self.shared.state.lock().unwrap();
+		state.input.clear();
+		state.output.clear();
+	}
+
+	fn start_processing(&self) -> u32 {
+		let mut state = self.shared.state.lock().unwrap();
+		if state.processing {
+			return 0;
+		}
+		state.processing = true;
+		state.threads = *state
+			.values
+			.get(&(String::from(NUM_THREADS_PARAM), 0))
+			.unwrap_or(&1);
+		// workers still finishing after a stop carry on instead
+		while state.running_workers < state.threads {
+			state.running_workers += 1;
+			let shared = self.shared.clone();
+			thread::spawn(move || shared.work());
+		}
+		0
+	}
+
+	fn stop_processing(&self) -> u32 {
+		self.shared.state.lock().unwrap().processing = false;
+		self.shared.work.notify_all();
+		0
+	}
+
+	fn reset_processing(&self) -> u32 {
+		self.clear_queues();
+		0
+	}
+
+	fn has_processing_stopped(&self) -> bool {
+		self.shared.state.lock().unwrap().running_workers == 0
+	}
+}
@@ -0,0 +1,377 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A deterministic solver which doesn't search any graphs, but returns
+//! the solutions it has been configured with. Used to test mining
+//! controllers without building any plugins.
+//!
+//! #Example
+//! ```
+//! extern crate cuckoo_miner as cuckoo;
+//! use cuckoo::{CuckooMiner, CuckooMinerSolution, MockBackend};
+//!
+//! let mut header = [0; 32];
+//! header[0] = 82;
+//! let backend = MockBackend::new("mock_cpu_16")
+//! 	.proof_size(3)
+//! 	.solution_for(&header, &[1, 2, 3]);
+//! let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+//!
+//! let mut cuckoo_size = 0;
+//! let mut solution = CuckooMinerSolution::new();
+//! assert!(miner.mine(&header, &mut cuckoo_size, &mut solution, 0).unwrap());
+//! assert_eq!(solution.solution_nonces, vec![1, 2, 3]);
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json;
+
+use super::backend::SolverBackend;
+use cuckoo_sys::manager::DEFAULT_PROOF_SIZE;
+use error::error::CuckooMinerError;
+use manager::manager::CuckooPluginParameter;
+use manager::metadata::{CuckooPluginDeviceType, CuckooPluginMetadata};
+use miner::miner::CuckooMinerDeviceStats;
+use miner::verify::CuckooGraphType;
+
+/// Number of headers the input queue holds if not set
+const DEFAULT_QUEUE_LIMIT: usize = 10;
+
+/// Cuckoo size reported if not set
+const DEFAULT_CUCKOO_SIZE: u32 = 16;
+
+/// The queues and parameter values of a mock
+struct MockState {
+	input: VecDeque<(u32, Vec<u8>, [u8; 8])>,
+	output: VecDeque<(u32, Vec<u32>, [u8; 8])>,
+	processing: bool,
+	stopped_at: Option<Instant>,
+	values: HashMap<(String, u32), u32>,
+	headers_solved: u32,
+}
+
+/// #Description
+///
+/// A [SolverBackend](trait.SolverBackend.html) returning configured
+/// solutions. Headers pushed to its input queue are solved when the
+/// output queue is next read, if processing has been started, so runs are
+/// repeatable. Built up from [new](#method.new), e.g.
+/// `MockBackend::new("mock").solution_for(&header, &nonces)`.
+///
+/// By default it declares a single `NUM_THREADS` parameter, reports
+/// cuckoo size 16 and a proof size of 42, and finds no solutions.
+///
+
+pub struct MockBackend {
+	full_path: String,
+	metadata: CuckooPluginMetadata,
+	proof_size: usize,
+	cuckoo_size: u32,
+	queue_limit: usize,
+	parameters: Vec<CuckooPluginParameter>,
+	solutions: Vec<(Vec<u8>, Vec<u32>)>,
+	every: Option<(u32, Vec<u32>)>,
+	device_errored: bool,
+	start_error: u32,
+	stop_delay: Duration,
+	panic_when_read: bool,
+	state: Mutex<MockState>,
+}
+
+impl MockBackend {
+	/// A mock with the given name, used as its full path and in its
+	/// metadata
+	pub fn new(name: &str) -> MockBackend {
+		let mut metadata = CuckooPluginMetadata::from_file_name(name);
+		metadata.device_type = CuckooPluginDeviceType::Cpu;
+		metadata.edge_bits = Some(CuckooGraphType::Cuckoo.edge_bits(DEFAULT_CUCKOO_SIZE));
+		MockBackend {
+			full_path: String::from(name),
+			metadata: metadata,
+			proof_size: DEFAULT_PROOF_SIZE,
+			cuckoo_size: DEFAULT_CUCKOO_SIZE,
+			queue_limit: DEFAULT_QUEUE_LIMIT,
+			parameters: vec![CuckooPluginParameter {
+				name: String::from("NUM_THREADS"),
+				description: String::from("Number of threads (ignored)"),
+				default_value: 1,
+				min_value: 1,
+				max_value: 32,
+			}],
+			solutions: Vec::new(),
+			every: None,
+			device_errored: false,
+			start_error: 0,
+			stop_delay: Duration::from_secs(0),
+			panic_when_read: false,
+			state: Mutex::new(MockState {
+				input: VecDeque::new(),
+				output: VecDeque::new(),
+				processing: false,
+				stopped_at: None,
+				values: HashMap::new(),
+				headers_solved: 0,
+			}),
+		}
+	}
+
+	/// Sets the graph type and cuckoo size reported with solutions
+	pub fn graph(mut self, graph_type: CuckooGraphType, cuckoo_size: u32) -> MockBackend {
+		self.metadata.graph_type = graph_type;
+		self.metadata.edge_bits = Some(graph_type.edge_bits(cuckoo_size));
+		self.cuckoo_size = cuckoo_size;
+		self
+	}
+
+	/// Sets the proof size
+	pub fn proof_size(mut self, proof_size: usize) -> MockBackend {
+		self.proof_size = proof_size;
+		self
+	}
+
+	/// Sets the number of headers the input queue holds
+	pub fn queue_limit(mut self, queue_limit: usize) -> MockBackend {
+		self.queue_limit = queue_limit;
+		self
+	}
+
+	/// Declares an additional parameter
+	pub fn parameter(mut self, name: &str, default_value: u32, min_value: u32, max_value: u32) -> MockBackend {
+		self.parameters.push(CuckooPluginParameter {
+			name: String::from(name),
+			description: String::new(),
+			default_value: default_value,
+			min_value: min_value,
+			max_value: max_value,
+		});
+		self
+	}
+
+	/// Returns the given nonces as the solution whenever exactly this
+	/// header is solved
+	pub fn solution_for(mut self, header: &[u8], nonces: &[u32]) -> MockBackend {
+		self.solutions.push((header.to_vec(), nonces.to_vec()));
+		self
+	}
+
+	/// Returns the given nonces as the solution for every `n`th header
+	/// solved which doesn't have its own solution. These won't verify
+	/// unless they happen to be a real cycle, so are useful for testing
+	/// solution rejection
+	pub fn solution_every(mut self, n: u32, nonces: &[u32]) -> MockBackend {
+		self.every = Some((n, nonces.to_vec()));
+		self
+	}
+
+	/// Reports the device as having errored in its stats
+	pub fn device_errored(mut self) -> MockBackend {
+		self.device_errored = true;
+		self
+	}
+
+	/// Fails to start processing, returning the given code
+	pub fn start_error(mut self, code: u32) -> MockBackend {
+		self.start_error = code;
+		self
+	}
+
+	/// Keeps reporting that processing hasn't stopped for the given time
+	/// after being told to stop
+	pub fn stop_delay(mut self, delay: Duration) -> MockBackend {
+		self.stop_delay = delay;
+		self
+	}
+
+	/// Panics when the output queue is read while processing, as a
+	/// crashing solver would
+	pub fn panic_when_read(mut self) -> MockBackend {
+		self.panic_when_read = true;
+		self
+	}
+
+	/// Solves a header, counting it towards `solution_every`
+	fn find(&self, state: &mut MockState, header: &[u8]) -> Option<Vec<u32>> {
+		state.headers_solved = state.headers_solved.wrapping_add(1);
+		if let Some(s) = self.solutions.iter().find(|s| s.0 == header) {
+			return Some(s.1.clone());
+		}
+		match self.every {
+			Some((n, ref nonces)) if n > 0 && state.headers_solved.is_multiple_of(n) => Some(nonces.clone()),
+			_ => None,
+		}
+	}
+
+	fn declared(&self, name: &str) -> Option<&CuckooPluginParameter> {
+		self.parameters.iter().find(|p| p.name == name)
+	}
+}
+
+impl SolverBackend for MockBackend {
+	fn full_path(&self) -> &str {
+		&self.full_path
+	}
+
+	fn proof_size(&self) -> usize {
+		self.proof_size
+	}
+
+	fn metadata(&self) -> &CuckooPluginMetadata {
+		&self.metadata
+	}
+
+	fn exports(&self, _function: &str) -> bool {
+		true
+	}
+
+	fn solve(&self, header: &[u8], cuckoo_size: &mut u32, solution: &mut [u32]) -> u32 {
+		let mut state = self.state.lock().unwrap();
+		*cuckoo_size = self.cuckoo_size;
+		match self.find(&mut state, header) {
+			Some(ref n) if n.len() > solution.len() => 3,
+			Some(n) => {
+				solution[..n.len()].copy_from_slice(&n);
+				1
+			}
+			None => 0,
+		}
+	}
+
+	fn parameter_list(&self) -> Result<String, CuckooMinerError> {
+		Ok(serde_json::to_string(&self.parameters)?)
+	}
+
+	fn get_parameter(&self, name: &str, device_id: u32, value: &mut u32) -> u32 {
+		let default_value = match self.declared(name) {
+			Some(p) => p.default_value,
+			None => return 1,
+		};
+		let state = self.state.lock().unwrap();
+		*value = *state
+			.values
+			.get(&(String::from(name), device_id))
+			.unwrap_or(&default_value);
+		0
+	}
+
+	fn set_parameter(&self, name: &str, device_id: u32, value: u32) -> u32 {
+		match self.declared(name) {
+			None => return 1,
+			Some(p) if value < p.min_value || value > p.max_value => return 2,
+			Some(_) => {}
+		}
+		let mut state = self.state.lock().unwrap();
+		state.values.insert((String::from(name), device_id), value);
+		0
+	}
+
+	fn stats(&self) -> Result<String, CuckooMinerError> {
+		let state = self.state.lock().unwrap();
+		let stats = vec![CuckooMinerDeviceStats {
+			plugin_name: None,
+			device_id: String::from("0"),
+			cuckoo_size: self.cuckoo_size.to_string(),
+			device_name: String::from("Mock"),
+			in_use: 1,
+			has_errored: self.device_errored as u32,
+			last_start_time: 0,
+			last_end_time: 0,
+			last_solution_time: 0,
+			iterations_completed: state.headers_solved,
+		}];
+		Ok(serde_json::to_string(&stats)?)
+	}
+
+	fn is_queue_under_limit(&self) -> bool {
+		self.state.lock().unwrap().input.len() < self.queue_limit
+	}
+
+	fn push_to_input_queue(&self, id: u32, data: &[u8], nonce: &[u8; 8]) -> u32 {
+		let mut state = self.state.lock().unwrap();
+		if state.input.len() >= self.queue_limit {
+			return 2;
+		}
+		state.input.push_back((id, data.to_vec(), *nonce));
+		0
+	}
+
+	fn read_from_output_queue(
+		&self,
+		id: &mut u32,
+		solution: &mut [u32],
+		cuckoo_size: &mut u32,
+		nonce: &mut [u8; 8],
+	) -> bool {
+		if self.panic_when_read && !self.has_processing_stopped() {
+			panic!("{} - Mock panicked reading the output queue", self.full_path);
+		}
+		let mut state = self.state.lock().unwrap();
+		if state.processing {
+			while let Some((i, header, n)) = state.input.pop_front() {
+				if let Some(s) = self.find(&mut state, &header) {
+					state.output.push_back((i, s, n));
+				}
+			}
+		}
+		match state.output.pop_front() {
+			Some((i, ref s, _)) if s.len() > solution.len() => {
+				warn!("{} - Mock solution longer than proof size, queue {}", self.full_path, i);
+				false
+			}
+			Some((i, s, n)) => {
+				solution[..s.len()].copy_from_slice(&s);
+				*id = i;
+				*cuckoo_size = self.cuckoo_size;
+				*nonce = n;
+				true
+			}
+			None => false,
+		}
+	}
+
+	fn clear_queues(&self) {
+		let mut state = self.state.lock().unwrap();
+		state.input.clear();
+		state.output.clear();
+	}
+
+	fn start_processing(&self) -> u32 {
+		if self.start_error != 0 {
+			return self.start_error;
+		}
+		self.state.lock().unwrap().processing = true;
+		0
+	}
+
+	fn stop_processing(&self) -> u32 {
+		let mut state = self.state.lock().unwrap();
+		if state.processing {
+			state.processing = false;
+			state.stopped_at = Some(Instant::now());
+		}
+		0
+	}
+
+	fn reset_processing(&self) -> u32 {
+		self.clear_queues();
+		0
+	}
+
+	fn has_processing_stopped(&self) -> bool {
+		let state = self.state.lock().unwrap();
+		!state.processing && state.stopped_at.is_none_or(|t| t.elapsed() >= self.stop_delay)
+	}
+}
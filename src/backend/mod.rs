@@ -0,0 +1,31 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The solvers a miner mines with. A
+//! [SolverBackend](trait.SolverBackend.html) is either a plugin loaded
+//! into this process, a plugin loaded into a separate host process so
+//! that a crashing plugin can't take the caller down with it, or a mock
+//! for testing.
+//!
+
+#![deny(non_upper_case_globals)]
+#![deny(non_camel_case_types)]
+#![deny(non_snake_case)]
+#![deny(unused_mut)]
+#![warn(missing_docs)]
+
+pub mod backend;
+pub mod isolated;
+pub mod lean;
+pub mod mock;
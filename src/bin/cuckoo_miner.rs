@@ -0,0 +1,267 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standalone cuckoo-miner, which mines for a stratum pool or node with
+//! the plugins and parameters given in a TOML config file, printing
+//! device stats as it goes. Built with the `binary` feature.
+//!
+//! Usage: `cuckoo-miner [config file]`, where the config file defaults
+//! to `cuckoo-miner.toml` in the current directory. See
+//! `doc/cuckoo-miner.toml` for an example.
+
+extern crate cuckoo_miner as cuckoo;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate toml;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use cuckoo::{CuckooMiner, CuckooMinerConfig, CuckooMinerJobHandle, CuckooPluginManager,
+	PluginParameters, RandomNonce, StratumClient, StratumShareStats};
+
+const DEFAULT_CONFIG_FILE: &str = "cuckoo-miner.toml";
+
+/// The contents of the config file
+#[derive(Debug, Deserialize)]
+struct MinerConfig {
+	/// Directory containing the plugins
+	#[serde(default = "default_plugin_dir")]
+	plugin_dir: String,
+
+	/// Whether to hash the header before passing it to the plugins
+	#[serde(default)]
+	hash_header: bool,
+
+	/// Whether to run each plugin in a separate host process
+	#[serde(default)]
+	isolate_plugins: bool,
+
+	/// Seconds between printing stats
+	#[serde(default = "default_stats_interval")]
+	stats_interval: u64,
+
+	/// Log filter, in the same format as `RUST_LOG`
+	#[serde(default = "default_log_level")]
+	log_level: String,
+
+	/// The pool or node to mine for
+	pool: PoolConfig,
+
+	/// The plugins to mine with
+	plugin: Vec<PluginConfig>,
+}
+
+/// Stratum endpoint, either a pool or a node's stratum server
+#[derive(Debug, Deserialize)]
+struct PoolConfig {
+	address: String,
+	#[serde(default)]
+	login: String,
+	#[serde(default)]
+	password: String,
+}
+
+/// A plugin to load, by file name without the extension, and the
+/// parameters to set on it
+#[derive(Debug, Deserialize)]
+struct PluginConfig {
+	name: String,
+	#[serde(default)]
+	parameters: Vec<ParameterConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParameterConfig {
+	name: String,
+	#[serde(default)]
+	device: u32,
+	value: u32,
+}
+
+fn default_plugin_dir() -> String {
+	String::from("plugins")
+}
+
+fn default_stats_interval() -> u64 {
+	10
+}
+
+fn default_log_level() -> String {
+	String::from("info")
+}
+
+fn read_config(path: &str) -> Result<MinerConfig, String> {
+	let mut contents = String::new();
+	File::open(path)
+		.and_then(|mut f| f.read_to_string(&mut contents))
+		.map_err(|e| format!("Error reading config file {}: {}", path, e))?;
+	let config: MinerConfig = toml::from_str(&contents)
+		.map_err(|e| format!("Error parsing config file {}: {}", path, e))?;
+	if config.plugin.is_empty() {
+		return Err(format!("No plugins given in config file {}", path));
+	}
+	Ok(config)
+}
+
+fn init_logging(level: &str) {
+	let mut builder = env_logger::LogBuilder::new();
+	builder.parse(level);
+	// RUST_LOG overrides the config file
+	if let Ok(l) = env::var("RUST_LOG") {
+		builder.parse(&l);
+	}
+	let _ = builder.init();
+}
+
+/// Resolves each configured plugin to its full path in the plugin dir,
+/// and checks its parameters against those the plugin declares
+fn plugin_configs(config: &MinerConfig) -> Result<Vec<CuckooMinerConfig>, String> {
+	let mut plugin_manager = CuckooPluginManager::new().map_err(|e| e.to_string())?;
+	let loaded = plugin_manager.load_plugin_dir(config.plugin_dir.clone());
+	for e in plugin_manager.get_load_errors() {
+		warn!("{}", e);
+	}
+	loaded.map_err(|e| format!("Error loading plugins from {}: {}", config.plugin_dir, e))?;
+	let caps = plugin_manager
+		.get_available_plugins("")
+		.map_err(|e| e.to_string())?;
+
+	let mut configs = Vec::new();
+	for p in &config.plugin {
+		let c = caps.iter().find(|c| {
+			Path::new(&c.full_path).file_stem().and_then(|s| s.to_str()) == Some(&p.name[..])
+		});
+		let c = match c {
+			Some(c) => c,
+			None => {
+				let skipped = plugin_manager.get_load_errors().iter().find(|e| {
+					Path::new(&e.full_path).file_stem().and_then(|s| s.to_str()) == Some(&p.name[..])
+				});
+				return Err(match skipped {
+					Some(e) => format!("Plugin {} couldn't be loaded: {}", p.name, e.error),
+					None => format!("Plugin {} not found in {}", p.name, config.plugin_dir),
+				});
+			}
+		};
+		if let Some(ref reason) = c.not_runnable_reason {
+			return Err(format!("Plugin {} can't run on this machine: {}", p.name, reason));
+		}
+		let mut params = PluginParameters::new(c);
+		// CPU plugins mine with a thread per core unless configured
+		if !p.parameters.iter().any(|param| param.name == "NUM_THREADS") {
+			if let Some(threads) = plugin_manager.recommend_num_threads(c) {
+				params = params.set("NUM_THREADS", threads);
+			}
+		}
+		for param in &p.parameters {
+			params = params.set_for_device(&param.name, param.device, param.value);
+		}
+		let mut c = params.build().map_err(|e| e.to_string())?;
+		c.isolate_plugin = config.isolate_plugins;
+		configs.push(c);
+	}
+	Ok(configs)
+}
+
+fn print_stats(job_handle: &CuckooMinerJobHandle, config: &MinerConfig, shares: StratumShareStats) {
+	let mut gps_total = 0.0;
+	for (i, p) in config.plugin.iter().enumerate() {
+		let stats = match job_handle.get_stats(i) {
+			Ok(s) => s,
+			Err(e) => {
+				warn!("Error getting stats for plugin {}: {}", p.name, e);
+				continue;
+			}
+		};
+		for s in stats.iter().filter(|s| s.in_use != 0) {
+			let status = match s.has_errored {
+				0 => "OK",
+				_ => "ERRORED",
+			};
+			let last_graph_time = s.last_solution_time as f64 / 1000000000.0;
+			let gps = 1.0 / last_graph_time;
+			println!(
+				"{} - Device {} ({}) at Cuckoo{} - Status: {} - Last Graph time: {:.3}s; \
+				 Graphs per second: {:.3} - Total Attempts {}",
+				p.name, s.device_id, s.device_name, s.cuckoo_size, status, last_graph_time, gps,
+				s.iterations_completed
+			);
+			if gps.is_finite() {
+				gps_total += gps;
+			}
+		}
+	}
+	println!(
+		"Total graphs per second: {:.3} - Shares submitted: {}, accepted: {}, rejected: {}",
+		gps_total, shares.submitted, shares.accepted, shares.rejected
+	);
+}
+
+fn run(config_path: &str) -> Result<(), String> {
+	let config = read_config(config_path)?;
+	init_logging(&config.log_level);
+
+	let miner_configs = plugin_configs(&config)?;
+	let miner = CuckooMiner::new(miner_configs).map_err(|e| format!("Error loading plugins: {}", e))?;
+
+	let mut client = StratumClient::connect(&config.pool.address).map_err(|e| e.to_string())?;
+	client
+		.login(
+			&config.pool.login,
+			&config.pool.password,
+			&format!("cuckoo-miner/{}", env!("CARGO_PKG_VERSION")),
+		)
+		.map_err(|e| e.to_string())?;
+	info!("Logged in to {}", config.pool.address);
+
+	// nothing sets this, so mining carries on until the pool or the
+	// miner fails
+	let stop = AtomicBool::new(false);
+	let stats_interval = Duration::from_secs(config.stats_interval);
+	let mut next_stats = Instant::now() + stats_interval;
+	client
+		.run_with_callback(
+			miner,
+			config.hash_header,
+			Box::new(RandomNonce::new()),
+			&stop,
+			|job_handle, shares| {
+				if Instant::now() >= next_stats {
+					print_stats(job_handle, &config, shares);
+					next_stats += stats_interval;
+				}
+			},
+		)
+		.map_err(|e| format!("Mining stopped: {}", e))?;
+	Ok(())
+}
+
+fn main() {
+	let args: Vec<String> = env::args().collect();
+	let config_path = args.get(1).map(|s| &s[..]).unwrap_or(DEFAULT_CONFIG_FILE);
+	if let Err(e) = run(config_path) {
+		eprintln!("cuckoo-miner: {}", e);
+		process::exit(1);
+	}
+}
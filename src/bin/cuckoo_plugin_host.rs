@@ -0,0 +1,31 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Hosts a single plugin in its own process on behalf of an
+//! `IsolatedPlugin`, so that the plugin crashing doesn't take down the
+//! miner. Not meant to be run by hand: requests are read from stdin and
+//! responses written to stdout, and it exits once stdin is closed.
+
+extern crate cuckoo_miner as cuckoo;
+extern crate env_logger;
+
+use std::process;
+
+fn main() {
+	let _ = env_logger::init();
+	if let Err(e) = cuckoo::run_plugin_host() {
+		eprintln!("cuckoo-plugin-host: {}", e);
+		process::exit(1);
+	}
+}
@@ -23,12 +23,15 @@
 //! loaded code. Plugins aren't threadsafe, so only one thread should ever
 //! be calling a particular plugin at a time.
 
+use std::cmp;
+use std::io;
 use std::sync::Mutex;
 
 use libloading;
 use libc::*;
 
 use error::error::CuckooMinerError;
+use manager::metadata::{read_metadata, CuckooPluginMetadata};
 
 // PRIVATE MEMBERS
 
@@ -49,6 +52,99 @@ type CuckooStopProcessing = unsafe extern "C" fn() -> uint32_t;
 type CuckooResetProcessing = unsafe extern "C" fn() -> uint32_t;
 type CuckooHasProcessingStopped = unsafe extern "C" fn() -> uint32_t;
 type CuckooGetStats = unsafe extern "C" fn(*mut c_uchar, *mut uint32_t) -> uint32_t;
+type CuckooProofSize = unsafe extern "C" fn() -> u32;
+type CuckooDescription = unsafe extern "C" fn(*mut c_uchar, *mut u32) -> u32;
+type CuckooAbiVersion = unsafe extern "C" fn() -> u32;
+
+/// The proof size (cycle length) assumed for plugins which don't
+/// export a `cuckoo_proof_size` function
+
+pub const DEFAULT_PROOF_SIZE: usize = 42;
+
+/// The largest proof size a plugin may report. Solution buffers are
+/// sized to it, so plugins reporting more are refused at load time
+
+pub const MAX_PROOF_SIZE: usize = 256;
+
+/// The version of the plugin interface this version of cuckoo-miner
+/// implements. Plugins report the version they implement via an
+/// optional `cuckoo_abi_version` function, and plugins which don't are
+/// assumed to implement version 1. Plugins implementing any other
+/// version are refused at load time, before any other functions are
+/// resolved.
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Functions a plugin may leave out. Calls to these on a plugin that
+/// doesn't export them fall back to a default, or return 1 (not found).
+
+pub const OPTIONAL_FUNCTIONS: [&str; 4] = [
+	"cuckoo_abi_version",
+	"cuckoo_proof_size",
+	"cuckoo_description",
+	"cuckoo_get_stats",
+];
+
+/// Size of the buffer first offered to a plugin function returning
+/// JSON. It's doubled each time the plugin returns 3 (too short).
+
+const INITIAL_BUFFER_SIZE: usize = 4096;
+
+/// The largest buffer offered to a plugin function returning JSON
+
+pub const MAX_BUFFER_SIZE: usize = 1 << 20;
+
+/// Calls a plugin function which writes up to `len` bytes to `buffer`
+/// and sets `len` to the number written. The length offered is clamped
+/// to the buffer, and a plugin claiming to have written more than it was
+/// offered is treated as having returned 3 (too short).
+
+fn call_with_buffer<F>(buffer: &mut [u8], len: &mut u32, f: F) -> u32
+where
+	F: FnOnce(*mut c_uchar, *mut u32) -> u32,
+{
+	let offered = cmp::min(*len as usize, buffer.len()) as u32;
+	*len = offered;
+	let ret = f(buffer.as_mut_ptr(), len);
+	if ret == 0 && *len > offered {
+		return 3;
+	}
+	ret
+}
+
+/// Length of a buffer passed to a plugin, or None if it doesn't fit
+/// the u32 the plugin interface uses
+
+fn buffer_len(buffer: &[u8]) -> Option<u32> {
+	if buffer.len() > u32::MAX as usize {
+		None
+	} else {
+		Some(buffer.len() as u32)
+	}
+}
+
+/// Error for a required function missing from a plugin
+
+fn symbol_error(path: &str, symbol: &str, error: io::Error) -> CuckooMinerError {
+	CuckooMinerError::PluginSymbolNotFoundError {
+		plugin: String::from(path),
+		symbol: String::from(symbol),
+		source: error,
+	}
+}
+
+/// Error for a plugin implementing an unsupported interface version
+
+fn check_abi_version(path: &str, abi_version: u32) -> Result<(), CuckooMinerError> {
+	if abi_version != PLUGIN_ABI_VERSION {
+		return Err(CuckooMinerError::PluginAbiError {
+			plugin: String::from(path),
+			expected: PLUGIN_ABI_VERSION,
+			found: abi_version,
+		});
+	}
+	Ok(())
+}
 
 /// Struct to hold instances of loaded plugins
 
@@ -56,6 +152,20 @@ pub struct PluginLibrary {
 	///The full file path to the plugin loaded by this instance
 	pub lib_full_path: String,
 
+	///The proof size (cycle length) the plugin searches for, as reported
+	///by its optional `cuckoo_proof_size` function, or 42 if not exported
+	pub proof_size: usize,
+
+	///The plugin's metadata, as reported by its optional
+	///`cuckoo_description` function, or inferred from its file name
+	pub metadata: CuckooPluginMetadata,
+
+	///The version of the plugin interface the plugin implements
+	pub abi_version: u32,
+
+	// Optional functions the plugin doesn't export
+	missing_functions: Vec<&'static str>,
+
 	loaded_library: Mutex<libloading::Library>,
 	cuckoo_init: Mutex<CuckooInit>,
 	cuckoo_call: Mutex<CuckooCall>,
@@ -70,7 +180,8 @@ pub struct PluginLibrary {
 	cuckoo_stop_processing: Mutex<CuckooStopProcessing>,
 	cuckoo_reset_processing: Mutex<CuckooResetProcessing>,
 	cuckoo_has_processing_stopped: Mutex<CuckooHasProcessingStopped>,
-	cuckoo_get_stats: Mutex<CuckooGetStats>,
+	cuckoo_get_stats: Option<Mutex<CuckooGetStats>>,
+	cuckoo_description: Option<Mutex<CuckooDescription>>,
 }
 
 impl PluginLibrary {
@@ -113,15 +224,12 @@ impl PluginLibrary {
 	pub fn new(lib_full_path: &str) -> Result<PluginLibrary, CuckooMinerError> {
 		debug!("Loading miner plugin: {}", &lib_full_path);
 
-		let result = libloading::Library::new(lib_full_path);
-
-		if let Err(e) = result {
-			return Err(CuckooMinerError::PluginNotFoundError(
-				String::from(format!("{} - {:?}", lib_full_path, e)),
-			));
-		}
-
-		let loaded_library = result.unwrap();
+		let loaded_library = libloading::Library::new(lib_full_path).map_err(|e| {
+			CuckooMinerError::PluginNotFoundError {
+				plugin: String::from(lib_full_path),
+				source: e,
+			}
+		})?;
 		PluginLibrary::load_symbols(loaded_library, lib_full_path)
 	}
 
@@ -129,97 +237,163 @@ impl PluginLibrary {
 		loaded_library: libloading::Library,
 		path: &str
 	) -> Result<PluginLibrary, CuckooMinerError> {
+		let mut missing_functions = Vec::new();
+		for f in OPTIONAL_FUNCTIONS.iter() {
+			let symbol = format!("{}\0", f);
+			if unsafe { loaded_library.get::<*const c_void>(symbol.as_bytes()) }.is_err() {
+				missing_functions.push(*f);
+			}
+		}
 		unsafe {
-			let ret_val = PluginLibrary {
+			// Optional, plugins which predate versioning implement version 1.
+			// Checked first, as other functions may have changed signature
+			let abi_version = match loaded_library.get::<CuckooAbiVersion>(b"cuckoo_abi_version\0") {
+				Ok(cuckoo_abi_version) => {
+					let v = cuckoo_abi_version();
+					check_abi_version(path, v)?;
+					Some(v)
+				}
+				Err(_) => None,
+			};
+
+			// Optional, older plugins only find 42 cycles
+			let proof_size = match loaded_library.get::<CuckooProofSize>(b"cuckoo_proof_size\0") {
+				Ok(cuckoo_proof_size) => cuckoo_proof_size() as usize,
+				Err(_) => DEFAULT_PROOF_SIZE,
+			};
+			if proof_size == 0 || proof_size % 2 != 0 || proof_size > MAX_PROOF_SIZE {
+				return Err(CuckooMinerError::PluginIOError(format!(
+					"{} - Plugin reported invalid proof size {}",
+					path,
+					proof_size
+				)));
+			}
+			let mut ret_val = PluginLibrary {
 				lib_full_path: String::from(path),
+				proof_size: proof_size,
+				metadata: CuckooPluginMetadata::from_file_name(path),
+				abi_version: abi_version.unwrap_or(PLUGIN_ABI_VERSION),
+				missing_functions: missing_functions,
 				cuckoo_init: {
 					let cuckoo_init: libloading::Symbol<CuckooInit> =
-						loaded_library.get(b"cuckoo_init\0").unwrap();
+						loaded_library.get(b"cuckoo_init\0")
+							.map_err(|e| symbol_error(path, "cuckoo_init", e))?;
 					Mutex::new(*cuckoo_init.into_raw())
 				},
 
 				cuckoo_call: {
 					let cuckoo_call: libloading::Symbol<CuckooCall> =
-						loaded_library.get(b"cuckoo_call\0").unwrap();
+						loaded_library.get(b"cuckoo_call\0")
+							.map_err(|e| symbol_error(path, "cuckoo_call", e))?;
 					Mutex::new(*cuckoo_call.into_raw())
 				},
 
 				cuckoo_parameter_list: {
 					let cuckoo_parameter_list:libloading::Symbol<CuckooParameterList> =
-						loaded_library.get(b"cuckoo_parameter_list\0").unwrap();
+						loaded_library.get(b"cuckoo_parameter_list\0")
+							.map_err(|e| symbol_error(path, "cuckoo_parameter_list", e))?;
 					Mutex::new(*cuckoo_parameter_list.into_raw())
 				},
 
 				cuckoo_get_parameter: {
 					let cuckoo_get_parameter:libloading::Symbol<CuckooGetParameter> =
-						loaded_library.get(b"cuckoo_get_parameter\0").unwrap();
+						loaded_library.get(b"cuckoo_get_parameter\0")
+							.map_err(|e| symbol_error(path, "cuckoo_get_parameter", e))?;
 					Mutex::new(*cuckoo_get_parameter.into_raw())
 				},
 
 				cuckoo_set_parameter: {
 					let cuckoo_set_parameter:libloading::Symbol<CuckooSetParameter> =
-						loaded_library.get(b"cuckoo_set_parameter\0").unwrap();
+						loaded_library.get(b"cuckoo_set_parameter\0")
+							.map_err(|e| symbol_error(path, "cuckoo_set_parameter", e))?;
 					Mutex::new(*cuckoo_set_parameter.into_raw())
 				},
 
 				cuckoo_is_queue_under_limit: {
 					let cuckoo_is_queue_under_limit:libloading::Symbol<CuckooIsQueueUnderLimit> =
-						loaded_library.get(b"cuckoo_is_queue_under_limit\0").unwrap();
+						loaded_library.get(b"cuckoo_is_queue_under_limit\0")
+							.map_err(|e| symbol_error(path, "cuckoo_is_queue_under_limit", e))?;
 					Mutex::new(*cuckoo_is_queue_under_limit.into_raw())
 				},
 
 				cuckoo_clear_queues: {
 					let cuckoo_clear_queues:libloading::Symbol<CuckooClearQueues> =
-						loaded_library.get(b"cuckoo_clear_queues\0").unwrap();
+						loaded_library.get(b"cuckoo_clear_queues\0")
+							.map_err(|e| symbol_error(path, "cuckoo_clear_queues", e))?;
 					Mutex::new(*cuckoo_clear_queues.into_raw())
 				},
 
 				cuckoo_push_to_input_queue: {
 					let cuckoo_push_to_input_queue:libloading::Symbol<CuckooPushToInputQueue> =
-						loaded_library.get(b"cuckoo_push_to_input_queue\0").unwrap();
+						loaded_library.get(b"cuckoo_push_to_input_queue\0")
+							.map_err(|e| symbol_error(path, "cuckoo_push_to_input_queue", e))?;
 					Mutex::new(*cuckoo_push_to_input_queue.into_raw())
 				},
 
 				cuckoo_read_from_output_queue: {
 					let cuckoo_read_from_output_queue:libloading::Symbol<CuckooReadFromOutputQueue> =
-						loaded_library.get(b"cuckoo_read_from_output_queue\0").unwrap();
+						loaded_library.get(b"cuckoo_read_from_output_queue\0")
+							.map_err(|e| symbol_error(path, "cuckoo_read_from_output_queue", e))?;
 					Mutex::new(*cuckoo_read_from_output_queue.into_raw())
 				},
 
 				cuckoo_start_processing: {
 					let cuckoo_start_processing:libloading::Symbol<CuckooStartProcessing> =
-						loaded_library.get(b"cuckoo_start_processing\0").unwrap();
+						loaded_library.get(b"cuckoo_start_processing\0")
+							.map_err(|e| symbol_error(path, "cuckoo_start_processing", e))?;
 					Mutex::new(*cuckoo_start_processing.into_raw())
 				},
 
 				cuckoo_stop_processing: {
 					let cuckoo_stop_processing:libloading::Symbol<CuckooStopProcessing> =
-						loaded_library.get(b"cuckoo_stop_processing\0").unwrap();
+						loaded_library.get(b"cuckoo_stop_processing\0")
+							.map_err(|e| symbol_error(path, "cuckoo_stop_processing", e))?;
 					Mutex::new(*cuckoo_stop_processing.into_raw())
 				},
 
 				cuckoo_reset_processing: {
 					let cuckoo_reset_processing:libloading::Symbol<CuckooResetProcessing> =
-						loaded_library.get(b"cuckoo_reset_processing\0").unwrap();
+						loaded_library.get(b"cuckoo_reset_processing\0")
+							.map_err(|e| symbol_error(path, "cuckoo_reset_processing", e))?;
 					Mutex::new(*cuckoo_reset_processing.into_raw())
 				},
 
 				cuckoo_has_processing_stopped: {
 					let cuckoo_has_processing_stopped:libloading::Symbol<CuckooHasProcessingStopped> =
-						loaded_library.get(b"cuckoo_has_processing_stopped\0").unwrap();
+						loaded_library.get(b"cuckoo_has_processing_stopped\0")
+							.map_err(|e| symbol_error(path, "cuckoo_has_processing_stopped", e))?;
 					Mutex::new(*cuckoo_has_processing_stopped.into_raw())
 				},
 
+				// Optional, stats are informational only
 				cuckoo_get_stats: {
-					let cuckoo_get_stats: libloading::Symbol<CuckooGetStats> =
-						loaded_library.get(b"cuckoo_get_stats\0").unwrap();
-					Mutex::new(*cuckoo_get_stats.into_raw())
+					match loaded_library.get::<CuckooGetStats>(b"cuckoo_get_stats\0") {
+						Ok(cuckoo_get_stats) => Some(Mutex::new(*cuckoo_get_stats.into_raw())),
+						Err(_) => None,
+					}
+				},
+
+				// Optional, older plugins don't describe themselves
+				cuckoo_description: {
+					match loaded_library.get::<CuckooDescription>(b"cuckoo_description\0") {
+						Ok(cuckoo_description) => Some(Mutex::new(*cuckoo_description.into_raw())),
+						Err(_) => None,
+					}
 				},
 
 				loaded_library: Mutex::new(loaded_library),
 			};
 
 			ret_val.call_cuckoo_init();
+			ret_val.metadata = read_metadata(&ret_val)?;
+			// A plugin may only report its version in its description
+			match abi_version {
+				Some(v) => ret_val.metadata.abi_version = Some(v),
+				None => if let Some(v) = ret_val.metadata.abi_version {
+					check_abi_version(path, v)?;
+					ret_val.abi_version = v;
+				},
+			}
 			return Ok(ret_val);
 		}
 	}
@@ -274,8 +448,15 @@ impl PluginLibrary {
 		let cuckoo_has_processing_stopped_ref = self.cuckoo_has_processing_stopped.lock().unwrap();
 		drop(cuckoo_has_processing_stopped_ref);
 
-		let cuckoo_get_stats_ref = self.cuckoo_get_stats.lock().unwrap();
-		drop(cuckoo_get_stats_ref);
+		if let Some(ref f) = self.cuckoo_get_stats {
+			let cuckoo_get_stats_ref = f.lock().unwrap();
+			drop(cuckoo_get_stats_ref);
+		}
+
+		if let Some(ref f) = self.cuckoo_description {
+			let cuckoo_description_ref = f.lock().unwrap();
+			drop(cuckoo_description_ref);
+		}
 
 		let loaded_library_ref = self.loaded_library.lock().unwrap();
 		drop(loaded_library_ref);
@@ -321,8 +502,8 @@ impl PluginLibrary {
 	///
 	/// Call to the cuckoo_call function of the currently loaded plugin, which
 	/// will perform a Cuckoo Cycle on the given seed, returning the first 
-	/// solution (a length 42 cycle) that is found. The implementation details 
-	/// are dependent on particular loaded plugin.
+	/// solution (a cycle of length `proof_size`) that is found. The
+	/// implementation details are dependent on particular loaded plugin.
 	///
 	/// #Arguments
 	///
@@ -331,17 +512,17 @@ impl PluginLibrary {
 	/// in the graph. In practice, this is a Grin blockheader, 
 	/// but from the plugin's perspective this can be anything.
 	///
-	/// * `solutions` (OUT) A caller-allocated array of 42 unsigned bytes. This
-	/// currently must be of size 42, corresponding to a conventional 
-	/// cuckoo-cycle solution length. If a solution is found, the solution 
-	/// nonces will be stored in this array, otherwise, they will be left 
-	/// untouched.
+	/// * `solutions` (OUT) A caller-allocated array of u32s. This must be
+	/// at least of size `proof_size`, the cycle length the plugin searches
+	/// for (42 for a conventional cuckoo-cycle solution). If a solution is
+	/// found, the solution nonces will be stored in this array, otherwise,
+	/// they will be left untouched.
 	///
 	/// #Returns
 	///
-	/// 1 if a solution is found, with the 42 solution nonces contained
-	/// within `sol_nonces`. 0 if no solution is found and `sol_nonces`
-	/// remains untouched.
+	/// 1 if a solution is found, with the solution nonces contained
+	/// within `solutions`. 0 if no solution is found and `solutions`
+	/// remains untouched. 3 if `solutions` is shorter than `proof_size`
 	///
 	/// #Example
 	///
@@ -367,9 +548,16 @@ impl PluginLibrary {
 	/// ```
 	///
 
-	pub fn call_cuckoo(&self, header: &[u8], cuckoo_size: &mut u32, solutions: &mut [u32; 42]) -> u32 {
+	pub fn call_cuckoo(&self, header: &[u8], cuckoo_size: &mut u32, solutions: &mut [u32]) -> u32 {
+		if solutions.len() < self.proof_size {
+			return 3;
+		}
+		let header_len = match buffer_len(header) {
+			Some(l) => l,
+			None => return 2,
+		};
 		let cuckoo_call_ref = self.cuckoo_call.lock().unwrap();
-		unsafe { cuckoo_call_ref(header.as_ptr(), header.len() as u32, cuckoo_size, solutions.as_mut_ptr()) }
+		unsafe { cuckoo_call_ref(header.as_ptr(), header_len, cuckoo_size, solutions.as_mut_ptr()) }
 	}
 
 	/// #Description
@@ -384,14 +572,19 @@ impl PluginLibrary {
 	/// with the JSON result array
 	///
 	/// * `param_list_len` (IN-OUT) When called, this should contain the
-	/// maximum number of bytes the plugin should write to `param_list_bytes`.
+	/// maximum number of bytes the plugin should write to `param_list_bytes`,
+	/// and is clamped to its length.
 	/// Upon return, this is filled with the number of bytes that were written to 
 	/// `param_list_bytes`.
 	///
 	/// #Returns
 	///
 	/// 0 if okay, with the result is stored in `param_list_bytes`
-	/// 3 if the provided array is too short
+	/// 3 if the provided array is too short, or the plugin reports writing
+	/// more than it was given
+	///
+	/// See [parameter_list](#method.parameter_list) for a version which
+	/// returns the JSON as a String.
 	///
 	/// #Example
 	///
@@ -419,7 +612,9 @@ impl PluginLibrary {
 		param_list_len: &mut u32,
 	) -> u32 {
 		let cuckoo_parameter_list_ref = self.cuckoo_parameter_list.lock().unwrap();
-		unsafe { cuckoo_parameter_list_ref(param_list_bytes.as_mut_ptr(), param_list_len) }
+		call_with_buffer(param_list_bytes, param_list_len, |b, l| unsafe {
+			cuckoo_parameter_list_ref(b, l)
+		})
 	}
 
 	/// #Description
@@ -458,8 +653,12 @@ impl PluginLibrary {
 	///
 
 	pub fn call_cuckoo_get_parameter(&self, name_bytes: &[u8], device_id: u32, value: &mut u32) -> u32 {
+		let name_len = match buffer_len(name_bytes) {
+			Some(l) => l,
+			None => return 4,
+		};
 		let cuckoo_get_parameter_ref = self.cuckoo_get_parameter.lock().unwrap();
-		unsafe { cuckoo_get_parameter_ref(name_bytes.as_ptr(), name_bytes.len() as u32, device_id, value) }
+		unsafe { cuckoo_get_parameter_ref(name_bytes.as_ptr(), name_len, device_id, value) }
 	}
 
 	/// Sets the value of a parameter in the currently loaded plugin
@@ -497,8 +696,12 @@ impl PluginLibrary {
 	///
 
 	pub fn call_cuckoo_set_parameter(&self, name_bytes: &[u8], device_id: u32, value: u32) -> u32 {
+		let name_len = match buffer_len(name_bytes) {
+			Some(l) => l,
+			None => return 4,
+		};
 		let cuckoo_set_parameter_ref = self.cuckoo_set_parameter.lock().unwrap();
-		unsafe { cuckoo_set_parameter_ref(name_bytes.as_ptr(), name_bytes.len() as u32, device_id, value) }
+		unsafe { cuckoo_set_parameter_ref(name_bytes.as_ptr(), name_len, device_id, value) }
 	}
 
 	/// #Description
@@ -567,8 +770,12 @@ impl PluginLibrary {
 	///
 
 	pub fn call_cuckoo_push_to_input_queue(&self, id: u32, data: &[u8], nonce: &[u8;8]) -> u32 {
+		let data_len = match buffer_len(data) {
+			Some(l) => l,
+			None => return 2,
+		};
 		let cuckoo_push_to_input_queue_ref = self.cuckoo_push_to_input_queue.lock().unwrap();
-		unsafe { cuckoo_push_to_input_queue_ref(id, data.as_ptr(), data.len() as u32, nonce.as_ptr()) }
+		unsafe { cuckoo_push_to_input_queue_ref(id, data.as_ptr(), data_len, nonce.as_ptr()) }
 	}
 
 	/// #Description
@@ -620,8 +827,8 @@ impl PluginLibrary {
 	///
 	/// #Arguments
 	///
-	/// * `sol_nonces` (OUT) A block of 42 u32s in which the solution nonces
-	/// will be stored, if any exist.
+	/// * `solutions` (OUT) A block of at least `proof_size` u32s in which
+	/// the solution nonces will be stored, if any exist.
 	///
 	/// * `nonce` (OUT) A block of 8 u8s representing a Big-Endian u64, used
 	/// for identification purposes so the caller can reconstruct the header 
@@ -631,6 +838,7 @@ impl PluginLibrary {
 	///
 	/// 1 if a solution was popped from the queue
 	/// 0 if a solution is not available
+	/// 3 if `solutions` is shorter than `proof_size`
 	///
 	/// #Example
 	/// ```
@@ -660,10 +868,13 @@ impl PluginLibrary {
 	pub fn call_cuckoo_read_from_output_queue(
 		&self,
 		id: &mut u32,
-		solutions: &mut [u32; 42],
+		solutions: &mut [u32],
 		cuckoo_size: &mut u32,
 		nonce: &mut [u8; 8],
 	) -> u32 {
+		if solutions.len() < self.proof_size {
+			return 3;
+		}
 		let cuckoo_read_from_output_queue_ref = self.cuckoo_read_from_output_queue.lock().unwrap();
 		let ret = unsafe { cuckoo_read_from_output_queue_ref(id, solutions.as_mut_ptr(), cuckoo_size, nonce.as_mut_ptr()) };
 		ret
@@ -864,6 +1075,7 @@ impl PluginLibrary {
 	/// #Returns
 	///
 	/// 0 if okay, with the result is stored in `stat_bytes`
+	/// 1 if the plugin doesn't export `cuckoo_get_stats`
 	/// 3 if the provided array is too short
 	///
 	/// #Example
@@ -888,7 +1100,174 @@ impl PluginLibrary {
 	///
 
 	pub fn call_cuckoo_get_stats(&self, stat_bytes: &mut [u8], stat_bytes_len: &mut u32) -> u32 {
-		let cuckoo_get_stats_ref = self.cuckoo_get_stats.lock().unwrap();
-		unsafe { cuckoo_get_stats_ref(stat_bytes.as_mut_ptr(), stat_bytes_len) }
+		match self.cuckoo_get_stats {
+			Some(ref f) => {
+				let cuckoo_get_stats_ref = f.lock().unwrap();
+				call_with_buffer(stat_bytes, stat_bytes_len, |b, l| unsafe { cuckoo_get_stats_ref(b, l) })
+			}
+			None => 1,
+		}
+	}
+
+	/// #Description
+	///
+	/// Whether the plugin exports the given function. Only functions in
+	/// [OPTIONAL_FUNCTIONS](constant.OPTIONAL_FUNCTIONS.html) can be
+	/// missing from a loaded plugin.
+	///
+
+	pub fn exports(&self, function: &str) -> bool {
+		!self.missing_functions.contains(&function)
+	}
+
+	/// #Description
+	///
+	/// Retrieves a JSON description of the plugin, via its optional
+	/// `cuckoo_description` function, in the form:
+	///
+	/// ```text
+	/// {
+	///    "name": "cuckatoo_lean_cpu_29",
+	///    "version": "1.0.0",
+	///    "abi_version": 1,
+	///    "algorithm": "cuckatoo",
+	///    "edge_bits": 29,
+	///    "device": "cpu",
+	///    "memory_bytes": 201326592
+	/// }
+	/// ```
+	///
+	/// `algorithm` is one of `cuckoo` or `cuckatoo`, and `device` one of
+	/// `cpu` or `cuda`. `memory_bytes`, the host memory the plugin needs
+	/// to mine, is optional.
+	///
+	/// #Arguments
+	///
+	/// * `description_bytes` (OUT) A reference to a block of memory to
+	/// write the description to
+	///
+	/// * `description_len` (IN-OUT) When called, this should contain the
+	/// maximum number of bytes the plugin should write to
+	/// `description_bytes`. Upon return, this is filled with the number of
+	/// bytes that were written.
+	///
+	/// #Returns
+	///
+	/// * None if the plugin doesn't export `cuckoo_description`
+	/// * Some(0) if okay, with the description stored in
+	/// `description_bytes`
+	/// * Some(3) if the provided array is too short
+	///
+
+	pub fn call_cuckoo_description(
+		&self,
+		description_bytes: &mut [u8],
+		description_len: &mut u32,
+	) -> Option<u32> {
+		self.cuckoo_description.as_ref().map(|f| {
+			let cuckoo_description_ref = f.lock().unwrap();
+			call_with_buffer(description_bytes, description_len, |b, l| unsafe {
+				cuckoo_description_ref(b, l)
+			})
+		})
+	}
+
+	/// Calls a plugin function returning a string via a buffer, growing
+	/// the buffer and retrying while the plugin returns 3 (too short)
+
+	fn read_string<F>(&self, function: &str, mut call: F) -> Result<String, CuckooMinerError>
+	where
+		F: FnMut(&mut [u8], &mut u32) -> u32,
+	{
+		let mut size = INITIAL_BUFFER_SIZE;
+		loop {
+			let mut buffer = vec![0; size];
+			let mut len = size as u32;
+			let ret = call(&mut buffer, &mut len);
+			match ret {
+				0 => {
+					buffer.truncate(len as usize);
+					return String::from_utf8(buffer).map_err(|e| CuckooMinerError::Utf8Error {
+						context: format!("Error reading result of {} in plugin {}", function, self.lib_full_path),
+						source: e,
+					});
+				}
+				3 if size < MAX_BUFFER_SIZE => {
+					// the plugin may have said how much it needs
+					size = cmp::min(cmp::max(size * 2, len as usize), MAX_BUFFER_SIZE);
+				}
+				_ => {
+					return Err(CuckooMinerError::plugin_call(
+						&self.lib_full_path,
+						function,
+						ret,
+						format!("writing to a {} byte buffer", size),
+					))
+				}
+			}
+		}
+	}
+
+	/// #Description
+	///
+	/// Returns the plugin's JSON list of parameters, as written by
+	/// [call_cuckoo_parameter_list](#method.call_cuckoo_parameter_list),
+	/// with a buffer grown as needed.
+	///
+	/// #Returns
+	///
+	/// * Ok, with the JSON
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if the plugin
+	/// returns an error, needs more than
+	/// [MAX_BUFFER_SIZE](constant.MAX_BUFFER_SIZE.html) bytes or returns
+	/// invalid UTF-8
+	///
+
+	pub fn parameter_list(&self) -> Result<String, CuckooMinerError> {
+		self.read_string("cuckoo_parameter_list", |b, l| self.call_cuckoo_parameter_list(b, l))
+	}
+
+	/// #Description
+	///
+	/// Returns the plugin's JSON device stats, as written by
+	/// [call_cuckoo_get_stats](#method.call_cuckoo_get_stats), with a
+	/// buffer grown as needed.
+	///
+	/// #Returns
+	///
+	/// * Ok, with the JSON
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if the plugin
+	/// doesn't report stats, returns an error, needs more than
+	/// [MAX_BUFFER_SIZE](constant.MAX_BUFFER_SIZE.html) bytes or returns
+	/// invalid UTF-8
+	///
+
+	pub fn stats(&self) -> Result<String, CuckooMinerError> {
+		self.read_string("cuckoo_get_stats", |b, l| self.call_cuckoo_get_stats(b, l))
+	}
+
+	/// #Description
+	///
+	/// Returns the plugin's JSON description, as written by
+	/// [call_cuckoo_description](#method.call_cuckoo_description), with a
+	/// buffer grown as needed.
+	///
+	/// #Returns
+	///
+	/// * Ok(Some), with the JSON
+	/// * Ok(None) if the plugin doesn't export `cuckoo_description`
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if the plugin
+	/// returns an error, needs more than
+	/// [MAX_BUFFER_SIZE](constant.MAX_BUFFER_SIZE.html) bytes or returns
+	/// invalid UTF-8
+	///
+
+	pub fn description(&self) -> Result<Option<String>, CuckooMinerError> {
+		if self.cuckoo_description.is_none() {
+			return Ok(None);
+		}
+		self.read_string("cuckoo_description", |b, l| {
+			self.call_cuckoo_description(b, l).unwrap_or(1)
+		}).map(Some)
 	}
 }
@@ -15,12 +15,97 @@
 //! Common error type used by all cuckoo-miner modules, as well as any exernal
 //! consumers of the cuckoo-miner crate.
 
+use std::error::Error;
+use std::fmt;
 use std::io;
 use std::string;
+use std::time::Duration;
+
+use regex;
+use serde_json;
+
+/// #Description
+///
+/// The meaning of a non-zero code returned by a plugin function. Not
+/// every function returns every code; see the documentation of the
+/// individual plugin functions.
+///
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginReturnCode {
+	/// 1 - The named property, or the requested item, doesn't exist
+	NotFound,
+
+	/// 2 - The value is outside the allowed range
+	OutOfRange,
+
+	/// 3 - The buffer provided is too short for the result
+	BufferTooShort,
+
+	/// 4 - The name provided is too long
+	NameTooLong,
+
+	/// 5 - The device doesn't exist
+	NoDevice,
+
+	/// 100 - The plugin's host process crashed, or couldn't be
+	/// restarted. Only returned for plugins running in a separate host
+	/// process, never by a plugin itself
+	HostUnavailable,
+
+	/// Any other code
+	Unknown(u32),
+}
+
+impl PluginReturnCode {
+	/// Maps a raw return code to its meaning
+	pub fn from_code(code: u32) -> PluginReturnCode {
+		match code {
+			1 => PluginReturnCode::NotFound,
+			2 => PluginReturnCode::OutOfRange,
+			3 => PluginReturnCode::BufferTooShort,
+			4 => PluginReturnCode::NameTooLong,
+			5 => PluginReturnCode::NoDevice,
+			100 => PluginReturnCode::HostUnavailable,
+			c => PluginReturnCode::Unknown(c),
+		}
+	}
+
+	/// The raw return code
+	pub fn code(&self) -> u32 {
+		match *self {
+			PluginReturnCode::NotFound => 1,
+			PluginReturnCode::OutOfRange => 2,
+			PluginReturnCode::BufferTooShort => 3,
+			PluginReturnCode::NameTooLong => 4,
+			PluginReturnCode::NoDevice => 5,
+			PluginReturnCode::HostUnavailable => 100,
+			PluginReturnCode::Unknown(c) => c,
+		}
+	}
+}
+
+impl fmt::Display for PluginReturnCode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let description = match *self {
+			PluginReturnCode::NotFound => "doesn't exist",
+			PluginReturnCode::OutOfRange => "outside allowed range",
+			PluginReturnCode::BufferTooShort => "buffer too short",
+			PluginReturnCode::NameTooLong => "name too long",
+			PluginReturnCode::NoDevice => "device doesn't exist",
+			PluginReturnCode::HostUnavailable => "plugin host unavailable",
+			PluginReturnCode::Unknown(_) => "unknown error",
+		};
+		write!(f, "{} (code {})", description, self.code())
+	}
+}
 
 /// #Description
 ///
 /// Top level enum for all errors that the cuckoo-miner crate can return.
+/// Errors arising from a particular plugin record the plugin's path, and
+/// errors caused by another error keep it, available via
+/// `Error::source`.
 ///
 
 #[derive(Debug)]
@@ -30,41 +115,210 @@ pub enum CuckooMinerError {
 	PluginNotLoadedError(String),
 
 	/// Occurs when trying to load plugin function that doesn't exist
-	PluginSymbolNotFoundError(String),
+	PluginSymbolNotFoundError {
+		/// Path of the plugin
+		plugin: String,
+		/// The missing function
+		symbol: String,
+		/// The error from the dynamic loader
+		source: io::Error,
+	},
 
-	/// Occurs when attempting to load a plugin that doesn't exist
-	PluginNotFoundError(String),
+	/// Occurs when attempting to load a plugin that doesn't exist,
+	/// or can't be loaded
+	PluginNotFoundError {
+		/// Path of the plugin
+		plugin: String,
+		/// The error from the dynamic loader
+		source: io::Error,
+	},
+
+	/// Occurs when a plugin implements a version of the plugin interface
+	/// this version of cuckoo-miner doesn't support
+	PluginAbiError {
+		/// Path of the plugin
+		plugin: String,
+		/// The interface version cuckoo-miner implements
+		expected: u32,
+		/// The interface version the plugin reported
+		found: u32,
+	},
 
 	/// Occurs when trying to load a plugin directory that doesn't
 	/// contain any plugins
 	NoPluginsFoundError(String),
 
-	/// Unexpected return code from a plugin
-	UnexpectedResultError(u32),
+	/// A plugin filter isn't a valid regular expression
+	PluginFilterError {
+		/// The filter given
+		filter: String,
+		/// The error from the regex parser
+		source: regex::Error,
+	},
+
+	/// A plugin function returned an error code
+	PluginCallError {
+		/// Path of the plugin
+		plugin: String,
+		/// The plugin function which failed
+		function: String,
+		/// What the returned code means
+		code: PluginReturnCode,
+		/// What was being attempted
+		context: String,
+	},
 
 	/// Error setting a parameter
 	ParameterError(String),
 
-	/// IO Error
+	/// The plugins to be loaded need more memory than is available
+	InsufficientMemoryError {
+		/// Estimated bytes needed by all of the plugins together
+		required: u64,
+		/// Bytes of memory available
+		available: u64,
+		/// The estimate for each plugin
+		detail: String,
+	},
+
+	/// A plugin returned something invalid
 	PluginIOError(String),
 
 	/// Plugin processing can't start
 	PluginProcessingError(String),
 
-	/// Error getting stats or stats not implemented
-	StatsError(String),
+	/// An IO error
+	IoError {
+		/// What was being attempted
+		context: String,
+		/// The underlying error
+		source: io::Error,
+	},
+
+	/// Invalid JSON returned by a plugin or server
+	JsonError {
+		/// What was being read
+		context: String,
+		/// The underlying error
+		source: serde_json::Error,
+	},
+
+	/// Invalid UTF-8 returned by a plugin
+	Utf8Error {
+		/// What was being read
+		context: String,
+		/// The underlying error
+		source: string::FromUtf8Error,
+	},
+
+	/// A solution returned by a plugin failed verification
+	InvalidSolutionError(String),
+
+	/// Error communicating with a stratum server
+	StratumError(String),
+
+	/// The job loop stopped because of an error, or panicked
+	JobFailedError(String),
+
+	/// The job loop didn't stop within the time allowed
+	JobStopTimeoutError(Duration),
+}
+
+impl CuckooMinerError {
+	/// Creates a [PluginCallError](#variant.PluginCallError) for a plugin
+	/// function which returned the given code
+	pub fn plugin_call(plugin: &str, function: &str, code: u32, context: String) -> CuckooMinerError {
+		CuckooMinerError::PluginCallError {
+			plugin: String::from(plugin),
+			function: String::from(function),
+			code: PluginReturnCode::from_code(code),
+			context: context,
+		}
+	}
+}
+
+impl fmt::Display for CuckooMinerError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CuckooMinerError::PluginNotLoadedError(ref s) => write!(f, "Plugin not loaded: {}", s),
+			CuckooMinerError::PluginSymbolNotFoundError { ref plugin, ref symbol, ref source } => {
+				write!(f, "Plugin {} doesn't export {}: {}", plugin, symbol, source)
+			}
+			CuckooMinerError::PluginNotFoundError { ref plugin, ref source } => {
+				write!(f, "Error loading plugin {}: {}", plugin, source)
+			}
+			CuckooMinerError::PluginAbiError { ref plugin, expected, found } => write!(
+				f,
+				"Plugin {} is incompatible: it implements plugin ABI version {}, expected version {}",
+				plugin, found, expected
+			),
+			CuckooMinerError::NoPluginsFoundError(ref s) => write!(f, "No plugins found: {}", s),
+			CuckooMinerError::PluginFilterError { ref filter, ref source } => {
+				write!(f, "Invalid plugin filter {}: {}", filter, source)
+			}
+			CuckooMinerError::PluginCallError { ref plugin, ref function, ref code, ref context } => {
+				write!(f, "{} in plugin {} failed {}: {}", function, plugin, context, code)
+			}
+			CuckooMinerError::ParameterError(ref s) => write!(f, "Parameter error: {}", s),
+			CuckooMinerError::InsufficientMemoryError { required, available, ref detail } => write!(
+				f,
+				"Not enough memory: the plugins need an estimated {} MiB, but only {} MiB is available ({})",
+				(required + (1 << 20) - 1) >> 20,
+				available >> 20,
+				detail
+			),
+			CuckooMinerError::PluginIOError(ref s) => write!(f, "Plugin IO error: {}", s),
+			CuckooMinerError::PluginProcessingError(ref s) => write!(f, "Processing error: {}", s),
+			CuckooMinerError::IoError { ref context, ref source } => write!(f, "{}: {}", context, source),
+			CuckooMinerError::JsonError { ref context, ref source } => write!(f, "{}: {}", context, source),
+			CuckooMinerError::Utf8Error { ref context, ref source } => write!(f, "{}: {}", context, source),
+			CuckooMinerError::InvalidSolutionError(ref s) => write!(f, "Invalid solution: {}", s),
+			CuckooMinerError::StratumError(ref s) => write!(f, "Stratum error: {}", s),
+			CuckooMinerError::JobFailedError(ref s) => write!(f, "Job failed: {}", s),
+			CuckooMinerError::JobStopTimeoutError(ref t) => {
+				write!(f, "Job didn't stop within {}ms", t.as_millis())
+			}
+		}
+	}
+}
+
+impl Error for CuckooMinerError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match *self {
+			CuckooMinerError::PluginSymbolNotFoundError { ref source, .. } => Some(source),
+			CuckooMinerError::PluginNotFoundError { ref source, .. } => Some(source),
+			CuckooMinerError::PluginFilterError { ref source, .. } => Some(source),
+			CuckooMinerError::IoError { ref source, .. } => Some(source),
+			CuckooMinerError::JsonError { ref source, .. } => Some(source),
+			CuckooMinerError::Utf8Error { ref source, .. } => Some(source),
+			_ => None,
+		}
+	}
 }
 
 impl From<io::Error> for CuckooMinerError {
 	fn from(error: io::Error) -> Self {
-		CuckooMinerError::PluginIOError(String::from(format!("Error loading plugin: {}", error)))
+		CuckooMinerError::IoError {
+			context: String::from("IO error"),
+			source: error,
+		}
 	}
 }
 
 impl From<string::FromUtf8Error> for CuckooMinerError {
 	fn from(error: string::FromUtf8Error) -> Self {
-		CuckooMinerError::PluginIOError(String::from(
-			format!("Error loading plugin description: {}", error),
-		))
+		CuckooMinerError::Utf8Error {
+			context: String::from("Invalid UTF-8 returned by plugin"),
+			source: error,
+		}
+	}
+}
+
+impl From<serde_json::Error> for CuckooMinerError {
+	fn from(error: serde_json::Error) -> Self {
+		CuckooMinerError::JsonError {
+			context: String::from("Invalid JSON"),
+			source: error,
+		}
 	}
 }
@@ -180,7 +180,8 @@
 //! 
 //! 	// these always get consumed after a notify
 //! 	let miner = cuckoo::CuckooMiner::new(config_vec.clone()).expect("");
-//! 	let job_handle = miner.notify(1, SAMPLE_GRIN_PRE_HEADER_1, SAMPLE_GRIN_POST_HEADER_1, 0).unwrap();
+//! 	let job_handle = miner.notify(1, SAMPLE_GRIN_PRE_HEADER_1, SAMPLE_GRIN_POST_HEADER_1, 0, false,
+//! 		Box::new(cuckoo::RandomNonce::new())).unwrap();
 //! 
 //! 	loop {
 //! 		if let Some(s) = job_handle.get_solution() {
@@ -211,7 +212,7 @@
 //! 		}
 //! 		if time::get_time().sec > deadline {
 //! 			println!("Stopping jobs and waiting for cleanup");
-//! 			job_handle.stop_jobs();
+//! 			job_handle.stop_jobs(std::time::Duration::from_secs(30)).unwrap();
 //! 			break;
 //! 		}
 //! 		# break;
@@ -250,12 +251,45 @@ mod error;
 mod miner;
 mod manager;
 mod cuckoo_sys;
+mod backend;
+#[cfg(feature = "stratum")]
+mod stratum;
 
-pub use error::error::CuckooMinerError;
+pub use error::error::{CuckooMinerError, PluginReturnCode};
 
 pub use miner::miner::{CuckooMinerConfig, CuckooMiner, CuckooMinerSolution, CuckooMinerJobHandle,
-                CuckooMinerDeviceStats};
+                CuckooMinerJobStatus, CuckooMinerDeviceStats, CuckooMinerSolutions, CuckooMinerParameterValue};
 
-pub use manager::manager::{CuckooPluginManager, CuckooPluginCapabilities};
+pub use miner::events::CuckooMinerEvent;
 
-pub use cuckoo_sys::manager::PluginLibrary;
+pub use miner::verify::CuckooGraphType;
+
+pub use miner::benchmark::{CuckooBenchmark, CuckooBenchmarkReport, CuckooBenchmarkResult};
+
+pub use miner::nonce::{NonceStrategy, SequentialNonce, RandomNonce, PartitionedNonce};
+
+pub use manager::manager::{CuckooPluginManager, CuckooPluginCapabilities, CuckooPluginParameter,
+	CuckooPluginLoadError, CuckooPluginFilter};
+
+pub use manager::metadata::{CuckooPluginMetadata, CuckooPluginDeviceType};
+
+pub use manager::host::HostCapabilities;
+
+pub use manager::memory::estimate_plugin_memory;
+
+pub use manager::parameters::PluginParameters;
+
+pub use cuckoo_sys::manager::{PluginLibrary, MAX_BUFFER_SIZE, MAX_PROOF_SIZE, PLUGIN_ABI_VERSION,
+	OPTIONAL_FUNCTIONS};
+
+pub use backend::backend::SolverBackend;
+
+pub use backend::isolated::{IsolatedPlugin, run_plugin_host, default_host_path, PLUGIN_HOST_ENV,
+	PLUGIN_HOST_NAME, DEFAULT_REQUEST_TIMEOUT};
+
+pub use backend::lean::{LeanSolver, BUILTIN_PREFIX, BUILTIN_SOLVERS, MAX_LEAN_EDGE_BITS};
+
+pub use backend::mock::MockBackend;
+
+#[cfg(feature = "stratum")]
+pub use stratum::stratum::{StratumClient, StratumJob, StratumEvent, StratumShareStats};
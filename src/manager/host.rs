@@ -0,0 +1,176 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of the host's CPU features and memory, used to tell
+//! which plugins can run on the host before loading them. Loading a
+//! plugin built for instructions the CPU doesn't have crashes the whole
+//! process with an illegal instruction.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+
+use super::memory::format_mib;
+use super::metadata::{CuckooPluginDeviceType, CuckooPluginMetadata};
+
+/// What the host can run plugins on
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HostCapabilities {
+	/// Whether the CPU supports AVX2, required by the `mean_cpu` plugins
+	pub avx2: bool,
+
+	/// Whether the CPU supports SSE4.1
+	pub sse4_1: bool,
+
+	/// The number of CPU cores available
+	pub cpu_cores: usize,
+
+	/// Total RAM in bytes, if known
+	pub total_memory: Option<u64>,
+
+	/// RAM available for new processes in bytes, if known
+	pub available_memory: Option<u64>,
+
+	/// Whether an NVIDIA driver was found, required by the CUDA plugins
+	pub cuda: bool,
+}
+
+impl HostCapabilities {
+	/// #Description
+	///
+	/// Detects the capabilities of the host this is running on. Memory
+	/// and the NVIDIA driver are only detected on Linux; elsewhere memory
+	/// is unknown and no driver is assumed.
+	///
+
+	pub fn detect() -> HostCapabilities {
+		let (total_memory, available_memory) = detect_memory();
+		HostCapabilities {
+			avx2: detect_avx2(),
+			sse4_1: detect_sse4_1(),
+			cpu_cores: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+			total_memory: total_memory,
+			available_memory: available_memory,
+			cuda: Path::new("/proc/driver/nvidia/version").exists(),
+		}
+	}
+
+	/// #Description
+	///
+	/// Checks whether a plugin can run on this host, from its file name,
+	/// metadata and the memory it needs.
+	///
+	/// #Arguments
+	///
+	/// * `file_name` The plugin's file name, which names its variant
+	/// following the plugin build's naming convention
+	///
+	/// * `metadata` The plugin's metadata
+	///
+	/// * `memory_estimate` The host memory the plugin needs in bytes, if
+	/// known
+	///
+	/// #Returns
+	///
+	/// * Ok() if the plugin can run on this host
+	/// * Err, with the reason, if it can't
+	///
+
+	pub fn check_plugin(
+		&self,
+		file_name: &str,
+		metadata: &CuckooPluginMetadata,
+		memory_estimate: Option<u64>,
+	) -> Result<(), String> {
+		match metadata.device_type {
+			CuckooPluginDeviceType::Cpu => {
+				if is_avx2_variant(file_name) && !self.avx2 {
+					return Err(String::from(
+						"Requires a CPU with AVX2; use a mean_compat_cpu or lean_cpu plugin instead",
+					));
+				}
+			}
+			CuckooPluginDeviceType::Cuda => {
+				if !self.cuda {
+					return Err(String::from("Requires an NVIDIA GPU, but no NVIDIA driver was found"));
+				}
+			}
+			CuckooPluginDeviceType::Unknown => {}
+		}
+		if let (Some(required), Some(available)) = (memory_estimate, self.available_memory) {
+			if required > available {
+				return Err(format!(
+					"Requires about {} of memory, but only {} is available",
+					format_mib(required),
+					format_mib(available)
+				));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Whether a plugin is a `mean_cpu` plugin, which the plugin build
+/// compiles with AVX2. `mean_compat_cpu` plugins are built without it.
+
+pub fn is_avx2_variant(file_name: &str) -> bool {
+	file_name.contains("mean_cpu")
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect_avx2() -> bool {
+	is_x86_feature_detected!("avx2")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn detect_avx2() -> bool {
+	false
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect_sse4_1() -> bool {
+	is_x86_feature_detected!("sse4.1")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn detect_sse4_1() -> bool {
+	false
+}
+
+// Reads total and available memory from /proc/meminfo, in bytes
+fn detect_memory() -> (Option<u64>, Option<u64>) {
+	let mut meminfo = String::new();
+	if File::open("/proc/meminfo")
+		.and_then(|mut f| f.read_to_string(&mut meminfo))
+		.is_err()
+	{
+		return (None, None);
+	}
+	(
+		meminfo_value(&meminfo, "MemTotal:"),
+		meminfo_value(&meminfo, "MemAvailable:"),
+	)
+}
+
+// Parses a line of /proc/meminfo such as `MemTotal:  16318440 kB`
+fn meminfo_value(meminfo: &str, key: &str) -> Option<u64> {
+	meminfo
+		.lines()
+		.find(|l| l.starts_with(key))
+		.and_then(|l| l[key.len()..].split_whitespace().next())
+		.and_then(|v| v.parse::<u64>().ok())
+		.map(|kb| kb * 1024)
+}
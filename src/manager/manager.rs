@@ -41,12 +41,19 @@ use std::env;
 use std::path::Path;
 
 use regex::Regex;
-use glob::glob;
+use glob::{glob, Pattern};
 
 use serde_json;
 
-use cuckoo_sys::manager::PluginLibrary;
+use backend::backend::SolverBackend;
+use backend::lean::{LeanSolver, BUILTIN_PREFIX, BUILTIN_SOLVERS};
+use cuckoo_sys::manager::{PluginLibrary, DEFAULT_PROOF_SIZE};
 use error::error::CuckooMinerError;
+use miner::verify::CuckooGraphType;
+use super::host::{is_avx2_variant, HostCapabilities};
+use super::memory::{estimate_plugin_memory, format_mib, num_threads, NUM_THREADS_PARAM};
+use super::metadata::{CuckooPluginDeviceType, CuckooPluginMetadata};
+use super::parameters::read_declared_parameters;
 
 // OS-specific library extensions
 
@@ -54,14 +61,24 @@ static DLL_SUFFIX: &str = "cuckooplugin";
 
 // Helper function to get the absolute path from a relative path
 
-fn abspath<P: AsRef<Path> + ?Sized>(relpath: &P) -> String {
-	let result = env::current_dir().map(|p| p.join(relpath.as_ref()));
-	let full_path = result.unwrap();
-	String::from(full_path.to_str().unwrap())
+fn abspath<P: AsRef<Path> + ?Sized>(relpath: &P) -> Result<String, CuckooMinerError> {
+	let full_path = env::current_dir()
+		.map(|p| p.join(relpath.as_ref()))
+		.map_err(|e| CuckooMinerError::IoError {
+			context: String::from("Error reading current directory"),
+			source: e,
+		})?;
+	match full_path.to_str() {
+		Some(p) => Ok(String::from(p)),
+		None => Err(CuckooMinerError::NoPluginsFoundError(format!(
+			"Plugin directory {} isn't valid UTF-8",
+			full_path.display()
+		))),
+	}
 }
 
 /// A wrapper for details that a plugin can report via it's cuckoo_description
-/// function. Basic at the moment, but will be extended.
+/// function, along with its parameters.
 #[derive(Debug, Clone)]
 pub struct CuckooPluginCapabilities {
 	/// The full path to the plugin
@@ -70,8 +87,27 @@ pub struct CuckooPluginCapabilities {
 	/// The plugin's file name
 	pub file_name: String,
 
+	/// The plugin's name, version, algorithm, edge bits and device type
+	pub metadata: CuckooPluginMetadata,
+
 	/// The plugin's reported parameters
 	pub parameters: Vec<CuckooPluginParameter>,
+
+	/// The proof size (cycle length) the plugin searches for
+	pub proof_size: usize,
+
+	/// Whether the plugin can run on this host. Plugins which can't
+	/// aren't loaded, so their metadata is inferred from the file name
+	/// and their parameters are empty
+	pub runnable: bool,
+
+	/// Why the plugin can't run on this host, if it can't
+	pub not_runnable_reason: Option<String>,
+
+	/// Estimated host memory, in bytes, the plugin needs to mine with
+	/// its default parameters, if known. See
+	/// [estimate_plugin_memory](fn.estimate_plugin_memory.html)
+	pub memory_estimate: Option<u64>,
 }
 
 impl Default for CuckooPluginCapabilities {
@@ -79,7 +115,12 @@ impl Default for CuckooPluginCapabilities {
 		CuckooPluginCapabilities {
 			full_path: String::from(""),
 			file_name: String::from(""),
+			metadata: CuckooPluginMetadata::from_file_name(""),
 			parameters: Vec::new(),
+			proof_size: DEFAULT_PROOF_SIZE,
+			runnable: true,
+			not_runnable_reason: None,
+			memory_estimate: None,
 		}
 	}
 }
@@ -88,10 +129,19 @@ impl fmt::Display for CuckooPluginCapabilities {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(
 			f,
-			"Path:{}\nParameters:{}\n",
+			"Path:{}\nPlugin:{}\nProof Size:{}\nParameters:{}\n",
 			self.full_path,
+			self.metadata,
+			self.proof_size,
 			serde_json::to_string(&self.parameters).unwrap()
-		)
+		)?;
+		if let Some(m) = self.memory_estimate {
+			writeln!(f, "Memory Estimate:{}", format_mib(m))?;
+		}
+		if let Some(ref r) = self.not_runnable_reason {
+			writeln!(f, "Not runnable:{}", r)?;
+		}
+		Ok(())
 	}
 }
 
@@ -116,6 +166,112 @@ pub struct CuckooPluginParameter {
 	pub max_value: u32,
 }
 
+/// Criteria for selecting plugins by their metadata, for use with
+/// [find_plugins](struct.CuckooPluginManager.html#method.find_plugins).
+/// Criteria which aren't set match any plugin.
+///
+/// #Example
+/// ```
+/// # use cuckoo_miner::{CuckooGraphType, CuckooPluginDeviceType, CuckooPluginFilter};
+/// let filter = CuckooPluginFilter::new()
+/// 	.graph_type(CuckooGraphType::Cuckatoo)
+/// 	.edge_bits(29)
+/// 	.device_type(CuckooPluginDeviceType::Cpu);
+/// ```
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CuckooPluginFilter {
+	/// The plugin's name must contain this
+	pub name: Option<String>,
+
+	/// The plugin must mine this type of graph
+	pub graph_type: Option<CuckooGraphType>,
+
+	/// The plugin must mine graphs with this many edge bits
+	pub edge_bits: Option<u32>,
+
+	/// The plugin must mine on this class of device
+	pub device_type: Option<CuckooPluginDeviceType>,
+
+	/// Whether the plugin must be able to run on this host
+	pub runnable: Option<bool>,
+}
+
+impl CuckooPluginFilter {
+	/// A filter which matches every plugin
+	pub fn new() -> CuckooPluginFilter {
+		CuckooPluginFilter::default()
+	}
+
+	/// Only match plugins whose name contains the given string
+	pub fn name(mut self, name: &str) -> CuckooPluginFilter {
+		self.name = Some(String::from(name));
+		self
+	}
+
+	/// Only match plugins mining the given type of graph
+	pub fn graph_type(mut self, graph_type: CuckooGraphType) -> CuckooPluginFilter {
+		self.graph_type = Some(graph_type);
+		self
+	}
+
+	/// Only match plugins mining graphs with the given number of edge bits
+	pub fn edge_bits(mut self, edge_bits: u32) -> CuckooPluginFilter {
+		self.edge_bits = Some(edge_bits);
+		self
+	}
+
+	/// Only match plugins mining on the given class of device
+	pub fn device_type(mut self, device_type: CuckooPluginDeviceType) -> CuckooPluginFilter {
+		self.device_type = Some(device_type);
+		self
+	}
+
+	/// Only match plugins which can, or can't, run on this host
+	pub fn runnable(mut self, runnable: bool) -> CuckooPluginFilter {
+		self.runnable = Some(runnable);
+		self
+	}
+
+	/// Whether a plugin matches every criterion set
+	pub fn matches(&self, caps: &CuckooPluginCapabilities) -> bool {
+		let m = &caps.metadata;
+		self.name.as_ref().is_none_or(|n| m.name.contains(&n[..]))
+			&& self.graph_type.is_none_or(|g| m.graph_type == g)
+			&& self.edge_bits.is_none_or(|e| m.edge_bits == Some(e))
+			&& self.device_type.is_none_or(|d| m.device_type == d)
+			&& self.runnable.is_none_or(|r| caps.runnable == r)
+	}
+}
+
+// How preferable a plugin is when recommending one
+fn plugin_rank(caps: &CuckooPluginCapabilities) -> u32 {
+	match caps.metadata.device_type {
+		CuckooPluginDeviceType::Cuda => 3,
+		_ if is_avx2_variant(&caps.file_name) => 2,
+		_ if caps.file_name.contains("mean") => 1,
+		_ => 0,
+	}
+}
+
+/// A plugin in the plugin directory which couldn't be loaded or queried,
+/// and has been skipped
+
+#[derive(Debug)]
+pub struct CuckooPluginLoadError {
+	/// The full path to the plugin
+	pub full_path: String,
+
+	/// Why the plugin couldn't be loaded
+	pub error: CuckooMinerError,
+}
+
+impl fmt::Display for CuckooPluginLoadError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Skipped plugin {}: {}", self.full_path, self.error)
+	}
+}
+
 /// A structure that loads and queries all of the plugins in a particular
 /// directory via their [`cuckoo_description`]
 /// (struct.PluginLibrary.html#method.call_cuckoo_description) method
@@ -127,6 +283,12 @@ pub struct CuckooPluginManager {
 	// Holds the current set of plugin capabilities, as returned
 	// from all of the plugins in the plugin directory
 	current_plugin_caps: Option<Vec<CuckooPluginCapabilities>>,
+
+	// The plugins in the plugin directory which couldn't be loaded
+	load_errors: Vec<CuckooPluginLoadError>,
+
+	// What the host can run plugins on
+	host: HostCapabilities,
 }
 
 impl Default for CuckooPluginManager {
@@ -134,6 +296,8 @@ impl Default for CuckooPluginManager {
 		CuckooPluginManager {
 			plugin_dir: String::from("target/debug"),
 			current_plugin_caps: None,
+			load_errors: Vec::new(),
+			host: HostCapabilities::detect(),
 		}
 	}
 }
@@ -143,7 +307,8 @@ impl CuckooPluginManager {
 	///
 	/// Returns a new CuckooPluginManager. The default value of the
 	/// plugin directory is "target/debug" to correspond with cargo's
-	/// default location.
+	/// default location. The host's capabilities are detected, to tell
+	/// which plugins can run on it.
 	///
 	/// #Arguments
 	///
@@ -168,6 +333,10 @@ impl CuckooPluginManager {
 	/// representing the plugins in the directory. This will parse any file
 	/// with the extension `.cuckooplugin`.
 	///
+	/// Plugins which can't be loaded or queried are skipped, and the
+	/// reasons are available from
+	/// [get_load_errors](#method.get_load_errors).
+	///
 	/// #Arguments
 	///
 	/// * `plugin_dir` (IN) The path to the prefered plugin directory. This can
@@ -176,18 +345,71 @@ impl CuckooPluginManager {
 	///
 	/// #Returns
 	///
-	/// * `Ok` if successful 
+	/// * `Ok` if at least one plugin was loaded
 	/// * [CuckooMinerError](enum.CuckooMinerError.html)
 	/// with specific detail if an error is encountered. 
 	///
 
 	pub fn load_plugin_dir(&mut self, plugin_dir: String) -> Result<(), CuckooMinerError> {
 		self.plugin_dir = plugin_dir.clone();
+		self.current_plugin_caps = None;
+		self.load_errors = Vec::new();
 		let caps = self.load_all_plugin_caps(&plugin_dir)?;
 		self.current_plugin_caps = Some(caps);
 		Ok(())
 	}
 
+	/// #Description
+	///
+	/// Adds the [built-in solvers](struct.LeanSolver.html) to the
+	/// available plugins, after any loaded from a plugin directory, so
+	/// they can be listed and filtered alongside them. Can be called
+	/// without loading a plugin directory, to mine without any plugins.
+	/// Loading a plugin directory afterwards replaces them.
+	///
+	/// #Returns
+	///
+	/// * `Ok` if successful
+	/// * a [CuckooMinerError](enum.CuckooMinerError.html)
+	/// with specific detail if an error is encountered.
+	///
+
+	pub fn load_builtin_solvers(&mut self) -> Result<(), CuckooMinerError> {
+		let mut caps = self.current_plugin_caps.take().unwrap_or_default();
+		for path in BUILTIN_SOLVERS.iter() {
+			if caps.iter().any(|c| c.full_path == *path) {
+				continue;
+			}
+			let solver = LeanSolver::new(path)?;
+			let file_name = String::from(&path[BUILTIN_PREFIX.len()..]);
+			let memory_estimate = solver.metadata().memory_bytes;
+			let check = self.host.check_plugin(&file_name, solver.metadata(), memory_estimate);
+			caps.push(CuckooPluginCapabilities {
+				full_path: String::from(*path),
+				file_name: file_name,
+				metadata: solver.metadata().clone(),
+				parameters: read_declared_parameters(&solver)?,
+				proof_size: SolverBackend::proof_size(&solver),
+				runnable: check.is_ok(),
+				not_runnable_reason: check.err(),
+				memory_estimate: memory_estimate,
+			});
+		}
+		self.current_plugin_caps = Some(caps);
+		Ok(())
+	}
+
+	/// #Description
+	///
+	/// Returns the plugins in the most recently loaded plugin directory
+	/// which were skipped, each with the reason it couldn't be loaded.
+	/// Empty if every plugin loaded successfully.
+	///
+
+	pub fn get_load_errors(&self) -> &[CuckooPluginLoadError] {
+		&self.load_errors
+	}
+
 	/// #Description
 	///
 	/// Returns an list of
@@ -240,29 +462,154 @@ impl CuckooPluginManager {
 		&mut self,
 		filter: &str,
 	) -> Result<Vec<CuckooPluginCapabilities>, CuckooMinerError> {
-		if filter.len() == 0 {
-			return Ok(self.current_plugin_caps.as_mut().unwrap().clone());
-		} else {
-			let result = self.current_plugin_caps
-				.as_mut()
-				.unwrap()
-				.clone()
-				.into_iter()
-				.filter(|ref i| {
-					let re = Regex::new(&format!(r"{}", filter)).unwrap();
-					let caps = re.captures(&i.full_path);
-					match caps {
-						Some(_) => return true,
-						None => return false,
-					}
-				})
-				.collect::<Vec<_>>();
-			if result.len() == 0 {
-				return Err(CuckooMinerError::NoPluginsFoundError(
-					format!("For given filter: {}", filter),
-				));
+		let re = Regex::new(filter).map_err(|e| CuckooMinerError::PluginFilterError {
+			filter: String::from(filter),
+			source: e,
+		})?;
+		let caps = self.loaded_caps()?;
+		let result = caps.iter()
+			.filter(|c| re.is_match(&c.full_path))
+			.cloned()
+			.collect::<Vec<_>>();
+		if result.is_empty() {
+			return Err(CuckooMinerError::NoPluginsFoundError(
+				format!("For given filter: {}", filter),
+			));
+		}
+		Ok(result)
+	}
+
+	/// #Description
+	///
+	/// Returns the
+	/// [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html)
+	/// of the plugins in the currently loaded directory whose metadata
+	/// matches the given filter.
+	///
+	/// #Arguments
+	///
+	/// * `filter` A [CuckooPluginFilter](struct.CuckooPluginFilter.html)
+	/// describing the plugins wanted
+	///
+	/// #Returns
+	///
+	/// * Ok, with the matching plugins, if there are any
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if no plugins
+	/// match, or no plugin directory has been loaded
+	///
+
+	pub fn find_plugins(
+		&self,
+		filter: &CuckooPluginFilter,
+	) -> Result<Vec<CuckooPluginCapabilities>, CuckooMinerError> {
+		let caps = self.loaded_caps()?;
+		let result = caps.iter()
+			.filter(|c| filter.matches(c))
+			.cloned()
+			.collect::<Vec<_>>();
+		if result.is_empty() {
+			return Err(CuckooMinerError::NoPluginsFoundError(
+				format!("For given filter: {:?}", filter),
+			));
+		}
+		Ok(result)
+	}
+
+	/// #Description
+	///
+	/// Returns the capabilities detected on the host, which determine
+	/// whether each plugin is runnable.
+	///
+
+	pub fn get_host_capabilities(&self) -> &HostCapabilities {
+		&self.host
+	}
+
+	/// #Description
+	///
+	/// Replaces the detected host capabilities, e.g. to select plugins
+	/// for another machine. Takes effect from the next call to
+	/// [load_plugin_dir](#method.load_plugin_dir).
+	///
+
+	pub fn set_host_capabilities(&mut self, host: HostCapabilities) {
+		self.host = host;
+	}
+
+	/// #Description
+	///
+	/// Recommends the best plugin in the loaded directory that can run on
+	/// this host and mines graphs with the given number of edge bits.
+	/// CUDA plugins are preferred over CPU plugins, and among CPU plugins
+	/// `mean_cpu` is preferred over `mean_compat_cpu`, then `lean_cpu`.
+	///
+	/// #Arguments
+	///
+	/// * `edge_bits` The number of edge bits in the graphs to mine
+	///
+	/// #Returns
+	///
+	/// * Ok, with the recommended plugin's
+	/// [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html)
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if no runnable
+	/// plugin mines graphs of that size
+	///
+
+	pub fn recommend_plugin(&self, edge_bits: u32) -> Result<CuckooPluginCapabilities, CuckooMinerError> {
+		let filter = CuckooPluginFilter::new().edge_bits(edge_bits).runnable(true);
+		let caps = self.loaded_caps()?;
+		// ties go to the first plugin found
+		let mut best: Option<&CuckooPluginCapabilities> = None;
+		for c in caps.iter().filter(|c| filter.matches(c)) {
+			if best.is_none_or(|b| plugin_rank(c) > plugin_rank(b)) {
+				best = Some(c);
 			}
-			return Ok(result);
+		}
+		match best {
+			Some(c) => Ok(c.clone()),
+			None => Err(CuckooMinerError::NoPluginsFoundError(format!(
+				"No runnable plugin for {} edge bits",
+				edge_bits
+			))),
+		}
+	}
+
+	/// #Description
+	///
+	/// Recommends how many threads a CPU plugin should mine with on this
+	/// host: one per CPU core, within the range the plugin declares for
+	/// `NUM_THREADS`.
+	///
+	/// #Arguments
+	///
+	/// * `caps` The plugin's
+	/// [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html)
+	///
+	/// #Returns
+	///
+	/// * Some, with the number of threads, for CPU plugins
+	/// * None if the plugin doesn't run on the CPU or doesn't declare
+	/// `NUM_THREADS`
+	///
+
+	pub fn recommend_num_threads(&self, caps: &CuckooPluginCapabilities) -> Option<u32> {
+		if caps.metadata.device_type != CuckooPluginDeviceType::Cpu {
+			return None;
+		}
+		let cores = self.host.cpu_cores.min(u32::MAX as usize) as u32;
+		caps.parameters
+			.iter()
+			.find(|p| p.name == NUM_THREADS_PARAM)
+			.map(|p| cores.max(p.min_value).min(p.max_value))
+	}
+
+	// The capabilities of the plugins in the loaded directory
+	fn loaded_caps(&self) -> Result<&Vec<CuckooPluginCapabilities>, CuckooMinerError> {
+		match self.current_plugin_caps {
+			Some(ref c) => Ok(c),
+			None => Err(CuckooMinerError::PluginNotLoadedError(String::from(
+				"No plugin directory has been loaded",
+			))),
 		}
 	}
 
@@ -273,28 +620,45 @@ impl CuckooPluginManager {
 		&mut self,
 		full_path: String,
 	) -> Result<CuckooPluginCapabilities, CuckooMinerError> {
-		debug!("Querying plugin at {}", full_path);
-		let library = PluginLibrary::new(&full_path).unwrap();
 		let mut caps = CuckooPluginCapabilities::default();
-
 		caps.full_path = full_path.clone();
-		caps.file_name = String::from("");
-
-		let mut param_list_bytes: [u8; 4096] = [0; 4096];
-		let mut param_list_len = param_list_bytes.len() as u32;
-		// get a list of parameters
-		library.call_cuckoo_parameter_list(&mut param_list_bytes, &mut param_list_len);
-		let mut param_list_vec: Vec<u8> = Vec::new();
-		// result contains null zero
-		for i in 0..param_list_len {
-			param_list_vec.push(param_list_bytes[i as usize].clone());
+		caps.file_name = Path::new(&full_path)
+			.file_name()
+			.and_then(|n| n.to_str())
+			.map(String::from)
+			.unwrap_or_default();
+		caps.metadata = CuckooPluginMetadata::from_file_name(&full_path);
+		caps.memory_estimate = estimate_plugin_memory(&caps.file_name, &caps.metadata, 1);
+
+		// Check before loading, as loading a plugin the CPU can't run
+		// may crash the process
+		if let Err(reason) = self.host.check_plugin(&caps.file_name, &caps.metadata, caps.memory_estimate) {
+			info!("Not loading plugin {}: {}", full_path, reason);
+			caps.runnable = false;
+			caps.not_runnable_reason = Some(reason);
+			return Ok(caps);
 		}
-		let param_list_json = String::from_utf8(param_list_vec)?;
-		caps.parameters = serde_json::from_str(&param_list_json).unwrap();
 
+		debug!("Querying plugin at {}", full_path);
+		let library = PluginLibrary::new(&full_path)?;
+		caps.metadata = library.metadata.clone();
+		caps.proof_size = library.proof_size;
+
+		let parameters = read_declared_parameters(&library);
 		library.unload();
+		caps.parameters = parameters?;
+		caps.memory_estimate = estimate_plugin_memory(
+			&caps.file_name,
+			&caps.metadata,
+			num_threads(&[], &caps.parameters),
+		);
+		// the plugin's own metadata and parameters may change the verdict
+		if let Err(reason) = self.host.check_plugin(&caps.file_name, &caps.metadata, caps.memory_estimate) {
+			caps.runnable = false;
+			caps.not_runnable_reason = Some(reason);
+		}
 
-		return Ok(caps);
+		Ok(caps)
 	}
 
 	/// Loads and fills out the internal plugin capabilites vector from the
@@ -304,25 +668,50 @@ impl CuckooPluginManager {
 		&mut self,
 		plugin_dir: &str,
 	) -> Result<Vec<CuckooPluginCapabilities>, CuckooMinerError> {
-		let lib_full_path = abspath(Path::new(&plugin_dir));
-		let glob_search_path = format!("{}/*.{}", lib_full_path, DLL_SUFFIX);
+		let lib_full_path = abspath(Path::new(&plugin_dir))?;
+		let glob_search_path = format!("{}/*.{}", Pattern::escape(&lib_full_path), DLL_SUFFIX);
+		let entries = glob(&glob_search_path).map_err(|e| {
+			CuckooMinerError::NoPluginsFoundError(format!(
+				"Invalid plugin directory {}: {}",
+				lib_full_path, e
+			))
+		})?;
 
 		let mut result_vec: Vec<CuckooPluginCapabilities> = Vec::new();
 
-		for entry in glob(&glob_search_path).expect("Failed to read glob pattern") {
-			match entry {
-				Ok(path) => {
-					let caps = self.load_plugin_caps(String::from(path.to_str().unwrap()))?;
-					result_vec.push(caps);
+		for entry in entries {
+			let path = match entry {
+				Ok(p) => p,
+				Err(e) => {
+					error!("{}", e);
+					continue;
+				}
+			};
+			let full_path = path.to_string_lossy().into_owned();
+			let caps = match path.to_str() {
+				Some(p) => self.load_plugin_caps(String::from(p)),
+				None => Err(CuckooMinerError::PluginNotLoadedError(
+					String::from("Plugin path isn't valid UTF-8"),
+				)),
+			};
+			match caps {
+				Ok(c) => result_vec.push(c),
+				Err(e) => {
+					let load_error = CuckooPluginLoadError {
+						full_path: full_path,
+						error: e,
+					};
+					warn!("{}", load_error);
+					self.load_errors.push(load_error);
 				}
-				Err(e) => error!("{:?}", e),
 			}
 		}
 
-		if result_vec.len() == 0 {
+		if result_vec.is_empty() {
 			return Err(CuckooMinerError::NoPluginsFoundError(format!(
-				"No plugins found in plugin directory {}",
-				lib_full_path.clone()
+				"No usable plugins found in plugin directory {} ({} skipped)",
+				lib_full_path,
+				self.load_errors.len()
 			)));
 		}
 
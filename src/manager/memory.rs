@@ -0,0 +1,97 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Estimates of the host memory a plugin needs to mine, so that a set
+//! of plugins which won't fit can be refused up front rather than being
+//! killed by the OS part way through.
+//!
+//! A plugin can report its own requirement in its description. Otherwise
+//! it's estimated from the solver variant and edge bits, following the
+//! memory use of the reference solvers:
+//!
+//! * `lean` keeps a bit per edge and two bits per node: 3/8 bytes per edge
+//! * `mean` keeps about 5.5 bytes per edge in its trimming buckets, plus
+//! per-thread buffers of about 1/64 byte per edge
+//!
+//! CUDA plugins mine in GPU memory, so aren't estimated.
+
+use super::metadata::{CuckooPluginDeviceType, CuckooPluginMetadata};
+use super::manager::CuckooPluginParameter;
+
+/// The parameter setting a plugin's thread count
+pub const NUM_THREADS_PARAM: &str = "NUM_THREADS";
+
+/// #Description
+///
+/// Estimates the host memory, in bytes, a plugin needs to mine.
+///
+/// #Arguments
+///
+/// * `file_name` The plugin's file name, which names its solver variant
+///
+/// * `metadata` The plugin's metadata
+///
+/// * `num_threads` The number of threads the plugin will mine with
+///
+/// #Returns
+///
+/// * Some(bytes) if the plugin reports its requirement, or it can be
+/// estimated
+/// * None if the plugin mines on a GPU, or its variant or edge bits
+/// aren't known
+///
+
+pub fn estimate_plugin_memory(
+	file_name: &str,
+	metadata: &CuckooPluginMetadata,
+	num_threads: u32,
+) -> Option<u64> {
+	if let Some(m) = metadata.memory_bytes {
+		return Some(m);
+	}
+	if metadata.device_type == CuckooPluginDeviceType::Cuda {
+		return None;
+	}
+	let edges = 1u64 << metadata.edge_bits?;
+	if file_name.contains("mean") {
+		Some(edges / 2 * 11 + u64::from(num_threads.max(1)) * (edges >> 6))
+	} else if file_name.contains("lean") {
+		Some(edges / 8 * 3)
+	} else {
+		None
+	}
+}
+
+/// The number of threads a plugin will mine with on device 0, given the
+/// parameters set and the parameters the plugin declares
+
+pub fn num_threads(parameters: &[(String, u32, u32)], declared: &[CuckooPluginParameter]) -> u32 {
+	parameters
+		.iter()
+		.find(|p| p.0 == NUM_THREADS_PARAM && p.1 == 0)
+		.map(|p| p.2)
+		.or_else(|| {
+			declared
+				.iter()
+				.find(|p| p.name == NUM_THREADS_PARAM)
+				.map(|p| p.default_value)
+		})
+		.unwrap_or(1)
+}
+
+/// Formats a number of bytes in MiB, for error messages
+
+pub fn format_mib(bytes: u64) -> String {
+	format!("{} MiB", (bytes + (1 << 20) - 1) >> 20)
+}
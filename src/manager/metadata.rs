@@ -0,0 +1,229 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plugin metadata, as reported by a plugin's optional
+//! `cuckoo_description` function, or inferred from the plugin's file
+//! name for plugins which don't export it.
+//!
+//! The plugin build names plugins `<algorithm>_<variant>_<device>_<size>`,
+//! e.g. `cuckatoo_lean_cpu_29` or `cuckoo_cuda_30`, where size is the
+//! cuckoo size. Older plugins omit the algorithm, e.g. `lean_cpu_16`,
+//! and are cuckoo plugins.
+
+use std::fmt;
+use std::path::Path;
+
+use serde_json;
+
+use cuckoo_sys::manager::PluginLibrary;
+use error::error::CuckooMinerError;
+use miner::verify::CuckooGraphType;
+
+/// The class of device a plugin mines on
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CuckooPluginDeviceType {
+	/// Mines on the CPU
+	Cpu,
+
+	/// Mines on an NVIDIA GPU via CUDA
+	Cuda,
+
+	/// Not reported, and not recognisable from the file name
+	Unknown,
+}
+
+impl CuckooPluginDeviceType {
+	fn from_name(name: &str) -> CuckooPluginDeviceType {
+		match name {
+			"cpu" => CuckooPluginDeviceType::Cpu,
+			"cuda" => CuckooPluginDeviceType::Cuda,
+			_ => CuckooPluginDeviceType::Unknown,
+		}
+	}
+}
+
+impl fmt::Display for CuckooPluginDeviceType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			CuckooPluginDeviceType::Cpu => write!(f, "cpu"),
+			CuckooPluginDeviceType::Cuda => write!(f, "cuda"),
+			CuckooPluginDeviceType::Unknown => write!(f, "unknown"),
+		}
+	}
+}
+
+/// Describes a plugin: what it is, which version it is, and which
+/// graphs it mines on which devices
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CuckooPluginMetadata {
+	/// The plugin's name, or its file name without the extension if it
+	/// doesn't report one
+	pub name: String,
+
+	/// The plugin's semantic version (e.g. `1.2.0`), if reported
+	pub version: Option<String>,
+
+	/// The version of the plugin interface the plugin implements, if
+	/// reported
+	pub abi_version: Option<u32>,
+
+	/// Whether the plugin mines cuckoo or cuckatoo graphs
+	pub graph_type: CuckooGraphType,
+
+	/// The number of edge bits in the graphs the plugin mines, if known
+	pub edge_bits: Option<u32>,
+
+	/// The class of device the plugin mines on
+	pub device_type: CuckooPluginDeviceType,
+
+	/// The host memory the plugin needs to mine, in bytes, if reported
+	pub memory_bytes: Option<u64>,
+
+	/// Whether the plugin reported this itself via `cuckoo_description`,
+	/// rather than it being inferred from the file name
+	pub reported: bool,
+}
+
+impl CuckooPluginMetadata {
+	/// #Description
+	///
+	/// Infers a plugin's metadata from its file name, following the
+	/// naming convention of the plugin build. Version information is
+	/// left empty, and edge bits are only filled in if the name ends in
+	/// a cuckoo size.
+	///
+	/// #Arguments
+	///
+	/// * `path` The plugin's file name or full path
+	///
+
+	pub fn from_file_name(path: &str) -> CuckooPluginMetadata {
+		let name = Path::new(path)
+			.file_stem()
+			.and_then(|s| s.to_str())
+			.unwrap_or(path);
+		let parts: Vec<&str> = name.split('_').collect();
+		let graph_type = match parts.first() {
+			Some(&"cuckatoo") => CuckooGraphType::Cuckatoo,
+			_ => CuckooGraphType::Cuckoo,
+		};
+		let edge_bits = parts
+			.last()
+			.and_then(|s| s.parse::<u32>().ok())
+			.map(|size| graph_type.edge_bits(size));
+		let device_type = parts
+			.iter()
+			.map(|p| CuckooPluginDeviceType::from_name(p))
+			.find(|d| *d != CuckooPluginDeviceType::Unknown)
+			.unwrap_or(CuckooPluginDeviceType::Unknown);
+		CuckooPluginMetadata {
+			name: String::from(name),
+			version: None,
+			abi_version: None,
+			graph_type: graph_type,
+			edge_bits: edge_bits,
+			device_type: device_type,
+			memory_bytes: None,
+			reported: false,
+		}
+	}
+}
+
+impl fmt::Display for CuckooPluginMetadata {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} ", self.name)?;
+		if let Some(ref v) = self.version {
+			write!(f, "v{} ", v)?;
+		}
+		write!(f, "({:?}", self.graph_type)?;
+		if let Some(e) = self.edge_bits {
+			write!(f, " {}", e)?;
+		}
+		write!(f, ", {})", self.device_type)
+	}
+}
+
+/// The JSON a plugin returns from `cuckoo_description`
+
+#[derive(Debug, Deserialize)]
+struct PluginDescription {
+	name: String,
+	version: String,
+	abi_version: u32,
+	algorithm: String,
+	edge_bits: u32,
+	device: String,
+	#[serde(default)]
+	memory_bytes: Option<u64>,
+}
+
+// Whether a version string is of the form major.minor.patch, optionally
+// followed by a pre-release or build suffix
+fn is_semantic_version(version: &str) -> bool {
+	let core = version.split(['-', '+']).next().unwrap_or("");
+	let parts: Vec<&str> = core.split('.').collect();
+	parts.len() == 3 && parts.iter().all(|p| !p.is_empty() && p.parse::<u64>().is_ok())
+}
+
+/// Parses the JSON returned by a plugin's `cuckoo_description` function
+
+pub fn parse_description(plugin: &str, json: &str) -> Result<CuckooPluginMetadata, CuckooMinerError> {
+	let d: PluginDescription = serde_json::from_str(json).map_err(|e| CuckooMinerError::JsonError {
+		context: format!("Error reading description of plugin {}", plugin),
+		source: e,
+	})?;
+	let graph_type = match &d.algorithm[..] {
+		"cuckoo" => CuckooGraphType::Cuckoo,
+		"cuckatoo" => CuckooGraphType::Cuckatoo,
+		a => {
+			return Err(CuckooMinerError::PluginIOError(format!(
+				"{} - Plugin reported unknown algorithm {}",
+				plugin, a
+			)))
+		}
+	};
+	if !is_semantic_version(&d.version) {
+		return Err(CuckooMinerError::PluginIOError(format!(
+			"{} - Plugin reported invalid version {}",
+			plugin, d.version
+		)));
+	}
+	let device_type = CuckooPluginDeviceType::from_name(&d.device);
+	if device_type == CuckooPluginDeviceType::Unknown {
+		warn!("{} - Plugin reported unknown device type {}", plugin, d.device);
+	}
+	Ok(CuckooPluginMetadata {
+		name: d.name,
+		version: Some(d.version),
+		abi_version: Some(d.abi_version),
+		graph_type: graph_type,
+		edge_bits: Some(d.edge_bits),
+		device_type: device_type,
+		memory_bytes: d.memory_bytes,
+		reported: true,
+	})
+}
+
+/// Reads a loaded plugin's metadata via its `cuckoo_description`
+/// function, or infers it from the file name if the plugin doesn't
+/// export one
+
+pub fn read_metadata(library: &PluginLibrary) -> Result<CuckooPluginMetadata, CuckooMinerError> {
+	match library.description()? {
+		Some(json) => parse_description(&library.lib_full_path, &json),
+		None => Ok(CuckooPluginMetadata::from_file_name(&library.lib_full_path)),
+	}
+}
@@ -18,9 +18,9 @@
 //! and return information about whether a particular plugin can be run
 //! on the host system.
 //!
-//! Although a plugin can only return its name and description at the moment,
-//! it will be extended in future to allow for other information such as
-//! version, and whether it can be run on a particular system.
+//! Plugins can describe their name, version, algorithm, edge bits and
+//! device type via an optional `cuckoo_description` function. For older
+//! plugins, this is inferred from the plugin's file name.
 //!
 
 #![deny(non_upper_case_globals)]
@@ -33,3 +33,7 @@ extern crate regex;
 extern crate glob;
 
 pub mod manager;
+pub mod host;
+pub mod memory;
+pub mod metadata;
+pub mod parameters;
@@ -0,0 +1,172 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Typed plugin parameters, checked against the names and ranges a
+//! plugin declares in its parameter list before the plugin is loaded
+//! for mining.
+//!
+//! #Example
+//! ```
+//! extern crate cuckoo_miner as cuckoo;
+//! use std::path::PathBuf;
+//! let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+//! d.push("target/debug/plugins/");
+//!
+//! let mut plugin_manager = cuckoo::CuckooPluginManager::new().unwrap();
+//! plugin_manager
+//! 	.load_plugin_dir(String::from(d.to_str().unwrap()))
+//! 	.expect("");
+//! let caps = plugin_manager.get_available_plugins("lean_cpu_16").unwrap();
+//!
+//! // Parameters that aren't set take the plugin's default value
+//! let config = cuckoo::PluginParameters::new(&caps[0])
+//! 	.set("NUM_THREADS", 4)
+//! 	.build()
+//! 	.unwrap();
+//! let miner = cuckoo::CuckooMiner::new(vec![config]).unwrap();
+//! ```
+
+use serde_json;
+
+use backend::backend::SolverBackend;
+use error::error::CuckooMinerError;
+use miner::miner::CuckooMinerConfig;
+use super::manager::{CuckooPluginCapabilities, CuckooPluginParameter};
+
+/// Reads and parses the list of parameters a plugin declares via its
+/// `cuckoo_parameter_list` function
+
+pub fn read_declared_parameters(
+	library: &dyn SolverBackend,
+) -> Result<Vec<CuckooPluginParameter>, CuckooMinerError> {
+	let param_list_json = library.parameter_list()?;
+	serde_json::from_str(&param_list_json).map_err(|e| CuckooMinerError::JsonError {
+		context: format!("Error reading parameter list of plugin {}", library.full_path()),
+		source: e,
+	})
+}
+
+/// Checks a list of (name, device_id, value) parameter settings against
+/// the parameters a plugin declares, returning a description of every
+/// setting with an unknown name or a value outside the declared range
+
+pub fn validate_parameters(
+	declared: &[CuckooPluginParameter],
+	values: &[(String, u32, u32)],
+) -> Vec<String> {
+	let mut violations = Vec::new();
+	for &(ref name, device_id, value) in values {
+		match declared.iter().find(|p| p.name == *name) {
+			None => violations.push(format!("{}: not a parameter of this plugin", name)),
+			Some(p) => if value < p.min_value || value > p.max_value {
+				violations.push(format!(
+					"{} (device {}): {} is outside the allowed range {} to {}",
+					name,
+					device_id,
+					value,
+					p.min_value,
+					p.max_value
+				));
+			},
+		}
+	}
+	violations
+}
+
+/// A builder for the parameters of a single plugin, which checks them
+/// against the plugin's
+/// [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html) and
+/// produces a [CuckooMinerConfig](struct.CuckooMinerConfig.html) for
+/// the plugin.
+
+#[derive(Debug, Clone)]
+pub struct PluginParameters {
+	/// The full path to the plugin
+	plugin_full_path: String,
+
+	/// The parameters the plugin declares
+	declared: Vec<CuckooPluginParameter>,
+
+	/// The values set, as (name, device_id, value)
+	values: Vec<(String, u32, u32)>,
+}
+
+impl PluginParameters {
+	/// Creates an empty set of parameters for the plugin with the
+	/// given capabilities
+
+	pub fn new(caps: &CuckooPluginCapabilities) -> PluginParameters {
+		PluginParameters {
+			plugin_full_path: caps.full_path.clone(),
+			declared: caps.parameters.clone(),
+			values: Vec::new(),
+		}
+	}
+
+	/// Sets a parameter on device 0, replacing any previous value
+
+	pub fn set(self, name: &str, value: u32) -> PluginParameters {
+		self.set_for_device(name, 0, value)
+	}
+
+	/// Sets a parameter on the given device, replacing any previous value
+
+	pub fn set_for_device(mut self, name: &str, device_id: u32, value: u32) -> PluginParameters {
+		self.values.retain(|v| !(v.0 == name && v.1 == device_id));
+		self.values.push((String::from(name), device_id, value));
+		self
+	}
+
+	/// Returns a description of every parameter that has been set with
+	/// a name the plugin doesn't declare, or a value outside the
+	/// plugin's declared range. Empty if all are valid.
+
+	pub fn violations(&self) -> Vec<String> {
+		validate_parameters(&self.declared, &self.values)
+	}
+
+	/// #Description
+	///
+	/// Validates the parameters, and creates a config for the plugin
+	/// which sets every parameter the plugin declares on device 0, using
+	/// the plugin's default value for any that haven't been set.
+	///
+	/// #Returns
+	///
+	/// * Ok([CuckooMinerConfig](struct.CuckooMinerConfig.html)) if all
+	/// parameters are valid
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) listing every
+	/// invalid parameter otherwise
+
+	pub fn build(self) -> Result<CuckooMinerConfig, CuckooMinerError> {
+		let violations = self.violations();
+		if !violations.is_empty() {
+			return Err(CuckooMinerError::ParameterError(format!(
+				"Invalid parameters for plugin {}: {}",
+				self.plugin_full_path,
+				violations.join("; ")
+			)));
+		}
+		let mut parameter_list = self.values;
+		for p in &self.declared {
+			if !parameter_list.iter().any(|v| v.0 == p.name && v.1 == 0) {
+				parameter_list.push((p.name.clone(), 0, p.default_value));
+			}
+		}
+		let mut config = CuckooMinerConfig::new();
+		config.plugin_full_path = self.plugin_full_path;
+		config.parameter_list = parameter_list;
+		Ok(config)
+	}
+}
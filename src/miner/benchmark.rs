@@ -0,0 +1,316 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks candidate plugins against each other, by mining the same
+//! fixed set of seeded headers with each in turn via
+//! [CuckooMiner::mine](../struct.CuckooMiner.html#method.mine), and
+//! ranking them by graphs per second.
+//!
+//! #Example
+//! ```
+//! extern crate cuckoo_miner as cuckoo;
+//! use std::path::PathBuf;
+//! let mut d = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+//! d.push("target/debug/plugins/");
+//!
+//! let mut plugin_manager = cuckoo::CuckooPluginManager::new().unwrap();
+//! plugin_manager
+//! 	.load_plugin_dir(String::from(d.to_str().unwrap()))
+//! 	.expect("");
+//! let caps = plugin_manager.get_available_plugins("cpu_16").unwrap();
+//!
+//! let mut benchmark = cuckoo::CuckooBenchmark::new().headers(2);
+//! for c in &caps {
+//! 	benchmark = benchmark.add_plugin(c);
+//! }
+//! let report = benchmark.run();
+//! println!("{}", report.to_json().unwrap());
+//! ```
+
+use std::cmp::Ordering;
+use std::path::Path;
+use std::time::Instant;
+
+use byteorder::{ByteOrder, LittleEndian};
+use blake2::blake2b::Blake2b;
+
+use serde_json;
+
+use super::miner::{CuckooMiner, CuckooMinerConfig, CuckooMinerSolution};
+use manager::manager::CuckooPluginCapabilities;
+use manager::parameters::PluginParameters;
+use error::error::CuckooMinerError;
+
+/// Number of headers mined by each plugin if not set
+const DEFAULT_HEADERS: usize = 5;
+
+/// The benchmark results of a single plugin
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CuckooBenchmarkResult {
+	/// The full path to the plugin
+	pub plugin_full_path: String,
+
+	/// The plugin's file name without the extension
+	pub plugin_name: String,
+
+	/// The cuckoo size the plugin reported mining at
+	pub cuckoo_size: u32,
+
+	/// The number of headers mined
+	pub graphs: usize,
+
+	/// The number of valid solutions found
+	pub solutions: usize,
+
+	/// The number of solutions found which failed verification
+	pub invalid_solutions: usize,
+
+	/// Total time spent mining, in seconds
+	pub total_time: f64,
+
+	/// Graphs searched per second
+	pub graphs_per_second: f64,
+
+	/// Why the plugin couldn't be benchmarked, if it couldn't. Failed
+	/// plugins are ranked last
+	pub error: Option<String>,
+}
+
+impl CuckooBenchmarkResult {
+	fn new(plugin_full_path: &str) -> CuckooBenchmarkResult {
+		CuckooBenchmarkResult {
+			plugin_full_path: String::from(plugin_full_path),
+			plugin_name: Path::new(plugin_full_path)
+				.file_stem()
+				.and_then(|s| s.to_str())
+				.map(String::from)
+				.unwrap_or_default(),
+			cuckoo_size: 0,
+			graphs: 0,
+			solutions: 0,
+			invalid_solutions: 0,
+			total_time: 0.0,
+			graphs_per_second: 0.0,
+			error: None,
+		}
+	}
+
+	fn failed(plugin_full_path: &str, error: String) -> CuckooBenchmarkResult {
+		let mut result = CuckooBenchmarkResult::new(plugin_full_path);
+		result.error = Some(error);
+		result
+	}
+}
+
+/// The results of a benchmark, best plugin first
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CuckooBenchmarkReport {
+	/// The seed the headers were generated from
+	pub seed: u64,
+
+	/// The number of headers each plugin mined
+	pub headers: usize,
+
+	/// The result for each plugin, ranked by graphs per second, with
+	/// any plugins that failed last
+	pub results: Vec<CuckooBenchmarkResult>,
+}
+
+impl CuckooBenchmarkReport {
+	/// The fastest plugin which didn't fail, if any
+	pub fn best(&self) -> Option<&CuckooBenchmarkResult> {
+		self.results.iter().find(|r| r.error.is_none())
+	}
+
+	/// The report as pretty-printed JSON
+	pub fn to_json(&self) -> Result<String, CuckooMinerError> {
+		serde_json::to_string_pretty(self).map_err(|e| CuckooMinerError::JsonError {
+			context: String::from("Error serialising benchmark report"),
+			source: e,
+		})
+	}
+}
+
+/// A plugin to benchmark, or the reason it can't be
+struct Candidate {
+	config: CuckooMinerConfig,
+	skip_reason: Option<String>,
+}
+
+/// Benchmarks a set of plugins. Each plugin is loaded on its own and
+/// mines the same headers, generated from a seed so that runs are
+/// repeatable.
+
+pub struct CuckooBenchmark {
+	candidates: Vec<Candidate>,
+	headers: usize,
+	seed: u64,
+}
+
+impl Default for CuckooBenchmark {
+	fn default() -> CuckooBenchmark {
+		CuckooBenchmark {
+			candidates: Vec::new(),
+			headers: DEFAULT_HEADERS,
+			seed: 0,
+		}
+	}
+}
+
+impl CuckooBenchmark {
+	/// A benchmark with no plugins, mining 5 headers from seed 0
+	pub fn new() -> CuckooBenchmark {
+		CuckooBenchmark::default()
+	}
+
+	/// Sets the number of headers each plugin mines
+	pub fn headers(mut self, headers: usize) -> CuckooBenchmark {
+		self.headers = headers;
+		self
+	}
+
+	/// Sets the seed the headers are generated from
+	pub fn seed(mut self, seed: u64) -> CuckooBenchmark {
+		self.seed = seed;
+		self
+	}
+
+	/// Adds a plugin to benchmark, with the given parameters
+	pub fn add_config(mut self, config: CuckooMinerConfig) -> CuckooBenchmark {
+		self.candidates.push(Candidate {
+			config: config,
+			skip_reason: None,
+		});
+		self
+	}
+
+	/// Adds a plugin to benchmark with its default parameters. Plugins
+	/// which can't run on this host are reported as failed without
+	/// being loaded.
+	pub fn add_plugin(mut self, caps: &CuckooPluginCapabilities) -> CuckooBenchmark {
+		let mut config = CuckooMinerConfig::new();
+		config.plugin_full_path = caps.full_path.clone();
+		let skip_reason = match caps.not_runnable_reason {
+			Some(ref r) => Some(r.clone()),
+			None => match PluginParameters::new(caps).build() {
+				Ok(c) => {
+					config = c;
+					None
+				}
+				Err(e) => Some(e.to_string()),
+			},
+		};
+		self.candidates.push(Candidate {
+			config: config,
+			skip_reason: skip_reason,
+		});
+		self
+	}
+
+	/// The headers each plugin mines, each the blake2b hash of the seed
+	/// and the header's index
+	pub fn benchmark_headers(&self) -> Vec<[u8; 32]> {
+		(0..self.headers)
+			.map(|i| {
+				let mut bytes = [0; 16];
+				LittleEndian::write_u64(&mut bytes[0..8], self.seed);
+				LittleEndian::write_u64(&mut bytes[8..16], i as u64);
+				let mut blake2b = Blake2b::new(32);
+				blake2b.update(&bytes);
+				let mut header = [0; 32];
+				header.copy_from_slice(blake2b.finalize().as_bytes());
+				header
+			})
+			.collect()
+	}
+
+	/// #Description
+	///
+	/// Runs the benchmark, loading and mining with each plugin in turn.
+	/// This blocks until every plugin has mined every header, which can
+	/// take some time for large cuckoo sizes.
+	///
+	/// #Returns
+	///
+	/// * A [CuckooBenchmarkReport](struct.CuckooBenchmarkReport.html)
+	/// ranking the plugins. A plugin that fails to load or mine is
+	/// ranked last, with the error recorded in its result.
+
+	pub fn run(&self) -> CuckooBenchmarkReport {
+		let headers = self.benchmark_headers();
+		let mut results: Vec<CuckooBenchmarkResult> = self.candidates
+			.iter()
+			.map(|c| {
+				let path = &c.config.plugin_full_path;
+				if let Some(ref r) = c.skip_reason {
+					return CuckooBenchmarkResult::failed(path, r.clone());
+				}
+				info!("Benchmarking plugin {}", path);
+				benchmark_plugin(&c.config, &headers)
+					.unwrap_or_else(|e| CuckooBenchmarkResult::failed(path, e.to_string()))
+			})
+			.collect();
+		// stable, so failed plugins keep the order they were added in
+		results.sort_by(|a, b| match (&a.error, &b.error) {
+			(&None, &None) => b.graphs_per_second
+				.partial_cmp(&a.graphs_per_second)
+				.unwrap_or(Ordering::Equal),
+			(&None, &Some(_)) => Ordering::Less,
+			(&Some(_), &None) => Ordering::Greater,
+			(&Some(_), &Some(_)) => Ordering::Equal,
+		});
+		CuckooBenchmarkReport {
+			seed: self.seed,
+			headers: self.headers,
+			results: results,
+		}
+	}
+}
+
+// Mines every header with a single plugin
+fn benchmark_plugin(
+	config: &CuckooMinerConfig,
+	headers: &[[u8; 32]],
+) -> Result<CuckooBenchmarkResult, CuckooMinerError> {
+	let miner = CuckooMiner::new(vec![config.clone()])?;
+	let mut result = CuckooBenchmarkResult::new(&config.plugin_full_path);
+	let start = Instant::now();
+	for header in headers {
+		let mut solution = CuckooMinerSolution::new();
+		let mut cuckoo_size = 0;
+		if miner.mine(header, &mut cuckoo_size, &mut solution, 0)? {
+			match solution.verify(header) {
+				Ok(()) => result.solutions += 1,
+				Err(e) => {
+					warn!("{} - Invalid solution: {}", config.plugin_full_path, e);
+					result.invalid_solutions += 1;
+				}
+			}
+		}
+		result.cuckoo_size = cuckoo_size;
+		result.graphs += 1;
+	}
+	let elapsed = start.elapsed();
+	result.total_time = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0;
+	if result.total_time > 0.0 {
+		result.graphs_per_second = result.graphs as f64 / result.total_time;
+	}
+	debug!(
+		"{} - {} graphs in {:.3}s, {} solutions",
+		config.plugin_full_path, result.graphs, result.total_time, result.solutions
+	);
+	Ok(result)
+}
@@ -17,7 +17,10 @@
 //!
 //!
 
-use std::sync::{Arc, RwLock};
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, PoisonError, RwLock};
+use std::collections::{HashSet, VecDeque};
 use std::{thread, time};
 use std::mem::transmute;
 
@@ -26,10 +29,14 @@ use byteorder::{ByteOrder, BigEndian};
 use blake2::blake2b::Blake2b;
 use env_logger;
 
-use cuckoo_sys::manager::PluginLibrary;
+use backend::backend::SolverBackend;
 use error::error::CuckooMinerError;
 use CuckooMinerJobHandle;
+use CuckooMinerJobStatus;
 use CuckooMinerSolution;
+use super::events::{CuckooMinerEvent, JobEvents};
+use super::miner::read_stats;
+use super::nonce::NonceStrategy;
 
 /// From grin
 /// The target is the 8-bytes hash block hashes must be lower than.
@@ -37,11 +44,88 @@ const MAX_TARGET: [u8; 8] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
 
 type JobSharedDataType = Arc<RwLock<JobSharedData>>;
 type JobControlDataType = Arc<RwLock<JobControlData>>;
-type PluginLibrariesDataType = Arc<RwLock<Vec<PluginLibrary>>>;
+type PluginLibrariesDataType = Arc<RwLock<Vec<Box<dyn SolverBackend>>>>;
+
+/// FIFO queue of solutions found by the job loop, which callers can
+/// block on until a solution arrives or the job stops
+
+pub struct SolutionQueue {
+	/// Queued solutions, and whether the job has stopped adding to them
+	queue: Mutex<(VecDeque<CuckooMinerSolution>, bool)>,
+
+	/// Signalled whenever a solution is pushed or the queue is closed
+	available: Condvar,
+}
+
+impl SolutionQueue {
+	pub fn new() -> SolutionQueue {
+		SolutionQueue {
+			queue: Mutex::new((VecDeque::new(), false)),
+			available: Condvar::new(),
+		}
+	}
+
+	/// Adds a solution to the back of the queue, waking any waiters
+	pub fn push(&self, solution: CuckooMinerSolution) {
+		let mut q = self.queue.lock().unwrap();
+		q.0.push_back(solution);
+		self.available.notify_all();
+	}
+
+	/// Removes the oldest solution, if there is one
+	pub fn pop(&self) -> Option<CuckooMinerSolution> {
+		self.queue.lock().unwrap().0.pop_front()
+	}
+
+	/// Removes the oldest solution, waiting up to `timeout` (or
+	/// indefinitely if `None`) for one to arrive. Returns `None` on
+	/// timeout, or once the queue is closed and empty
+	pub fn wait_pop(&self, timeout: Option<time::Duration>) -> Option<CuckooMinerSolution> {
+		let deadline = timeout.map(|t| time::Instant::now() + t);
+		let mut q = self.queue.lock().unwrap();
+		loop {
+			if let Some(s) = q.0.pop_front() {
+				return Some(s);
+			}
+			if q.1 {
+				return None;
+			}
+			q = match deadline {
+				None => self.available.wait(q).unwrap(),
+				Some(d) => {
+					let now = time::Instant::now();
+					if now >= d {
+						return None;
+					}
+					self.available.wait_timeout(q, d - now).unwrap().0
+				}
+			};
+		}
+	}
+
+	/// Marks that no more solutions will be pushed, waking any waiters
+	pub fn close(&self) {
+		let mut q = self.queue.lock().unwrap();
+		q.1 = true;
+		self.available.notify_all();
+	}
+}
+
+/// The message a panic was raised with, if it was a string
+
+fn panic_message(payload: &Box<dyn Any + Send>) -> String {
+	if let Some(s) = payload.downcast_ref::<&str>() {
+		String::from(*s)
+	} else if let Some(s) = payload.downcast_ref::<String>() {
+		s.clone()
+	} else {
+		String::from("unknown panic")
+	}
+}
 
 /// Data intended to be shared across threads
 pub struct JobSharedData {
-	/// ID of the current running job (not currently used)
+	/// ID of the current running job, which is attached to its solutions
 	pub job_id: u32,
 
 	/// The part of the header before the nonce, which this
@@ -55,8 +139,17 @@ pub struct JobSharedData {
 	/// target will be put into the output queue
 	pub difficulty: u64,
 
-	/// Output solutions
-	pub solutions: Vec<CuckooMinerSolution>,
+	/// Output solutions, oldest first
+	pub solutions: Arc<SolutionQueue>,
+
+	/// Number of solutions returned by each plugin that failed
+	/// verification, indexed by plugin
+	pub rejected_solutions: Vec<u64>,
+
+	/// Number of solutions returned by each plugin for a job which had
+	/// already been replaced, indexed by plugin. Solutions for jobs too
+	/// old to be remembered are counted without being verified
+	pub stale_solutions: Vec<u64>,
 }
 
 impl Default for JobSharedData {
@@ -66,19 +159,60 @@ impl Default for JobSharedData {
 			pre_nonce: String::from(""),
 			post_nonce: String::from(""),
 			difficulty: 0,
-			solutions: Vec::new(),
+			solutions: Arc::new(SolutionQueue::new()),
+			rejected_solutions: Vec::new(),
+			stale_solutions: Vec::new(),
 		}
 	}
 }
 
 impl JobSharedData {
-	pub fn new(job_id: u32, pre_nonce: &str, post_nonce: &str, difficulty: u64) -> JobSharedData {
+	pub fn new(job_id: u32, pre_nonce: &str, post_nonce: &str, difficulty: u64, num_plugins: usize) -> JobSharedData {
 		JobSharedData {
 			job_id: job_id,
 			pre_nonce: String::from(pre_nonce),
 			post_nonce: String::from(post_nonce),
 			difficulty: difficulty,
-			solutions: Vec::new(),
+			solutions: Arc::new(SolutionQueue::new()),
+			rejected_solutions: vec![0; num_plugins],
+			stale_solutions: vec![0; num_plugins],
+		}
+	}
+}
+
+/// The number of replaced jobs the job loop remembers, so solutions
+/// still coming out of the plugins for them can be identified
+
+const MAX_SUPERSEDED_JOBS: usize = 4;
+
+/// How often the job loop reads the plugins' stats while anyone is
+/// listening for events
+
+const STATS_EVENT_INTERVAL: time::Duration = time::Duration::from_secs(1);
+
+/// How long every plugin has to keep failing to start or to accept
+/// headers before the job loop gives up and fails the job
+
+const PLUGIN_FAILURE_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+/// A job's header template, as read by the job loop
+
+struct JobTemplate {
+	job_id: u32,
+	pre_nonce: String,
+	post_nonce: String,
+	difficulty: u64,
+	hash: [u8; 32],
+}
+
+impl JobTemplate {
+	fn from_shared_data(s: &JobSharedData) -> JobTemplate {
+		JobTemplate {
+			job_id: s.job_id,
+			pre_nonce: s.pre_nonce.clone(),
+			post_nonce: s.post_nonce.clone(),
+			difficulty: s.difficulty,
+			hash: CuckooMinerSolution::template_hash(&s.pre_nonce, &s.post_nonce),
 		}
 	}
 }
@@ -91,6 +225,18 @@ pub struct JobControlData {
 
 	/// Whether all plugins have stopped
 	pub has_stopped: bool,
+
+	/// Whether the job template in the shared data has been replaced,
+	/// and should be picked up by the job loop
+	pub job_updated: bool,
+
+	/// Plugins the job handle is restarting to change a parameter, which
+	/// the job loop leaves alone
+	pub paused_plugins: HashSet<usize>,
+
+	/// Plugins the job handle gave up waiting on to stop, which the job
+	/// loop restarts once they have
+	pub restart_plugins: HashSet<usize>,
 }
 
 impl Default for JobControlData {
@@ -98,6 +244,9 @@ impl Default for JobControlData {
 		JobControlData {
 			stop_flag: false,
 			has_stopped: false,
+			job_updated: false,
+			paused_plugins: HashSet::new(),
+			restart_plugins: HashSet::new(),
 		}
 	}
 }
@@ -115,21 +264,38 @@ pub struct Delegator {
 
 	/// Loaded Plugin Library
 	libraries: PluginLibrariesDataType,
+
+	/// Source of nonces to insert into the header
+	nonce_strategy: Mutex<Box<dyn NonceStrategy>>,
+
+	/// Where the job loop sends its events
+	events: Arc<JobEvents>,
 }
 
 impl Delegator {
 	/// Create a new job delegator
 
-	pub fn new(job_id: u32, pre_nonce: &str, post_nonce: &str, difficulty: u64, libraries:Vec<PluginLibrary>) -> Delegator {
+	pub fn new(
+		job_id: u32,
+		pre_nonce: &str,
+		post_nonce: &str,
+		difficulty: u64,
+		libraries: Vec<Box<dyn SolverBackend>>,
+		nonce_strategy: Box<dyn NonceStrategy>,
+		events: Arc<JobEvents>,
+	) -> Delegator {
 		Delegator {
 			shared_data: Arc::new(RwLock::new(JobSharedData::new(
 				job_id,
 				pre_nonce,
 				post_nonce,
 				difficulty,
+				libraries.len(),
 			))),
 			control_data: Arc::new(RwLock::new(JobControlData::default())),
 			libraries: Arc::new(RwLock::new(libraries)),
+			nonce_strategy: Mutex::new(nonce_strategy),
+			events: events,
 		}
 	}
 
@@ -143,17 +309,61 @@ impl Delegator {
 		let shared_data = self.shared_data.clone();
 		let control_data = self.control_data.clone();
 		let jh_library = self.libraries.clone();
-
-		thread::spawn(move || {
-			let result = self.job_loop(hash_header);
-			if let Err(e) = result {
-				error!("Error in job loop: {:?}", e);
-			}
-		});
+		let solutions = self.shared_data.read().unwrap().solutions.clone();
+		let jh_solutions = solutions.clone();
+		let events = self.events.clone();
+		let jh_events = events.clone();
+		let status = Arc::new(Mutex::new(CuckooMinerJobStatus::Running));
+		let jh_status = status.clone();
+		let loop_shared_data = shared_data.clone();
+		let loop_control_data = control_data.clone();
+		let loop_libraries = self.libraries.clone();
+
+		let thread = thread::Builder::new()
+			.name(String::from("cuckoo-miner-job"))
+			.spawn(move || {
+				let result = match panic::catch_unwind(AssertUnwindSafe(|| self.job_loop(hash_header))) {
+					Ok(r) => r,
+					Err(p) => {
+						// the plugins may still be processing
+						for l in loop_libraries.read().unwrap_or_else(PoisonError::into_inner).iter() {
+							l.stop_processing();
+						}
+						Err(CuckooMinerError::JobFailedError(format!(
+							"Job loop panicked: {}",
+							panic_message(&p)
+						)))
+					}
+				};
+				if let Err(ref e) = result {
+					error!("Error in job loop: {}", e);
+				}
+				*status.lock().unwrap_or_else(PoisonError::into_inner) = match result {
+					Ok(_) => CuckooMinerJobStatus::Stopped,
+					Err(ref e) => CuckooMinerJobStatus::Failed(e.to_string()),
+				};
+				loop_control_data.write().unwrap_or_else(PoisonError::into_inner).has_stopped = true;
+				events.send(CuckooMinerEvent::JobStopped {
+					job_id: loop_shared_data.read().unwrap_or_else(PoisonError::into_inner).job_id,
+					error: result.as_ref().err().map(|e| e.to_string()),
+				});
+				events.close();
+				// wake anyone still waiting on a solution
+				solutions.close();
+				result
+			})
+			.map_err(|e| CuckooMinerError::IoError {
+				context: String::from("Error starting job thread"),
+				source: e,
+			})?;
 		Ok(CuckooMinerJobHandle {
 			shared_data: shared_data,
 			control_data: control_data,
 			library: jh_library,
+			solutions: jh_solutions,
+			events: jh_events,
+			status: jh_status,
+			thread: Mutex::new(Some(thread)),
 		})
 	}
 
@@ -187,64 +397,167 @@ impl Delegator {
 
 		pre_vec
 	}
-	/// helper that generates a nonce and returns a header
 
-	fn get_next_header_data_hashed(&self, pre_nonce: &str, post_nonce: &str) -> (u64, Vec<u8>) {
-		// Generate new nonce
-		let nonce: u64 = rand::OsRng::new().unwrap().gen();
+	/// Returns the data sent to the plugin for the given nonce, hashing
+	/// the header if required
+	fn plugin_data(&self, pre_nonce: &str, post_nonce: &str, nonce: u64, hash_header: bool) -> Vec<u8> {
+		let header = self.header_data(pre_nonce, post_nonce, nonce);
+		if !hash_header {
+			return header;
+		}
 		let mut blake2b = Blake2b::new(32);
-		blake2b.update(&self.header_data(pre_nonce, post_nonce, nonce));
+		blake2b.update(&header);
 
 		let mut ret = [0; 32];
 		ret.copy_from_slice(blake2b.finalize().as_bytes());
-		(nonce, ret.to_vec())
+		ret.to_vec()
+	}
+
+	/// helper that generates a nonce and returns a header
+
+	fn get_next_header_data_hashed(&self, pre_nonce: &str, post_nonce: &str) -> (u64, Vec<u8>) {
+		// Generate new nonce
+		let nonce = self.nonce_strategy.lock().unwrap().next_nonce();
+		(nonce, self.plugin_data(pre_nonce, post_nonce, nonce, true))
 	}
 
 	/// as above, except doesn't hash the result
 	fn get_next_header_data(&self, pre_nonce: &str, post_nonce: &str) -> (u64, Vec<u8>) {
-		let nonce: u64 = rand:: OsRng::new().unwrap().gen();
-		(nonce, self.header_data(pre_nonce, post_nonce, nonce))
+		let nonce = self.nonce_strategy.lock().unwrap().next_nonce();
+		(nonce, self.plugin_data(pre_nonce, post_nonce, nonce, false))
 	}
 
 	/// Helper to determing whether a solution meets a target difficulty
 	/// based on same algorithm from grin
 
-	fn meets_difficulty(&self, in_difficulty: u64, sol: CuckooMinerSolution) -> bool {
+	fn meets_difficulty(&self, in_difficulty: u64, sol: &CuckooMinerSolution) -> bool {
 		let max_target = BigEndian::read_u64(&MAX_TARGET);
 		let num = BigEndian::read_u64(&sol.hash()[0..8]);
 		max_target / num >= in_difficulty
 	}
 
+	/// Sends a PluginFailed event, unless one has been sent since the
+	/// plugin last succeeded
+
+	fn plugin_failed(&self, failing: &mut [bool], plugin_index: usize, error: CuckooMinerError) {
+		warn!("Cuckoo-miner plugin[{}]: {}", plugin_index, error);
+		if !failing[plugin_index] {
+			failing[plugin_index] = true;
+			self.events.send(CuckooMinerEvent::PluginFailed {
+				plugin_index: plugin_index,
+				error: error.to_string(),
+			});
+		}
+	}
+
+	/// Reads every plugin's stats, sending them as events along with any
+	/// devices which have newly errored
+
+	fn send_stats(&self, failing: &mut [bool], errored: &mut [HashSet<String>]) {
+		for (plugin_index, l) in self.libraries.read().unwrap().iter().enumerate() {
+			let stats = match read_stats(l.as_ref()) {
+				Ok(s) => s,
+				Err(e) => {
+					self.plugin_failed(failing, plugin_index, e);
+					continue;
+				}
+			};
+			failing[plugin_index] = false;
+			for d in &stats {
+				if d.has_errored == 0 {
+					errored[plugin_index].remove(&d.device_id);
+				} else if errored[plugin_index].insert(d.device_id.clone()) {
+					self.events.send(CuckooMinerEvent::DeviceErrored {
+						plugin_index: plugin_index,
+						stats: d.clone(),
+					});
+				}
+			}
+			self.events.send(CuckooMinerEvent::StatsUpdated {
+				plugin_index: plugin_index,
+				stats: stats,
+			});
+		}
+	}
+
+	/// Resets the plugins the job handle gave up waiting on which have
+	/// since stopped, so they're started again with the others. Returns
+	/// the plugins to leave alone this time around: those being
+	/// restarted by the job handle, and those still stopping.
+	fn restart_stopped_plugins(&self, started: &mut [bool]) -> HashSet<usize> {
+		let libraries = self.libraries.read().unwrap();
+		let mut c = self.control_data.write().unwrap();
+		let mut skipped = c.paused_plugins.clone();
+		let stopping: Vec<usize> = c.restart_plugins.iter().cloned().collect();
+		for plugin_index in stopping {
+			let l = &libraries[plugin_index];
+			if l.has_processing_stopped() {
+				debug!("Cuckoo-miner plugin[{}]: Stopped, restarting", plugin_index);
+				l.reset_processing();
+				started[plugin_index] = false;
+				c.restart_plugins.remove(&plugin_index);
+			} else {
+				skipped.insert(plugin_index);
+			}
+		}
+		skipped
+	}
+
 	/// The main job loop. Pushes hashes to the plugin and reads solutions
 	/// from the queue, putting them into the job's output queue. Continues
-	/// until another thread sets the is_running flag to false
+	/// until another thread sets the is_running flag to false, or fails
+	/// once every plugin has been failing for PLUGIN_FAILURE_TIMEOUT
 
 	fn job_loop(self, hash_header: bool) -> Result<(), CuckooMinerError> {
-		// keep some unchanging data here, can move this out of shared
-		// object later if it's not needed anywhere else
-		let pre_nonce: String;
-		let post_nonce: String;
 		// generate an identifier to ensure we're only reading our
 		// jobs from the queue
-		let queue_id: u32 = rand::OsRng::new().unwrap().gen();
-		let difficulty;
-		{
-			let s = self.shared_data.read().unwrap();
-			pre_nonce = s.pre_nonce.clone();
-			post_nonce = s.post_nonce.clone();
-			difficulty = s.difficulty;
-		}
+		let mut queue_id: u32 = rand::OsRng::new().unwrap().gen();
+		let mut job = JobTemplate::from_shared_data(&self.shared_data.read().unwrap());
+		// replaced jobs by queue id, oldest first
+		let mut superseded_jobs: VecDeque<(u32, JobTemplate)> = VecDeque::new();
 		debug!(
 			"Cuckoo-miner: Searching for solution >= difficulty {}",
-			difficulty
+			job.difficulty
 		);
 	
-		for l in self.libraries.read().unwrap().iter() {
-			l.call_cuckoo_start_processing();
+		let num_plugins = self.libraries.read().unwrap().len();
+		// whether each plugin's last call failed, and its errored devices
+		let mut failing = vec![false; num_plugins];
+		let mut errored = vec![HashSet::new(); num_plugins];
+		let mut next_stats = time::Instant::now();
+		// whether each plugin has started processing, and since when its
+		// calls have been failing
+		let mut started = vec![false; num_plugins];
+		let mut failing_since: Vec<Option<time::Instant>> = vec![None; num_plugins];
+		let mut last_error = String::new();
+		let mut result = Ok(());
+
+		for (plugin_index, l) in self.libraries.read().unwrap().iter().enumerate() {
+			let code = l.start_processing();
+			if code != 0 {
+				let e = CuckooMinerError::plugin_call(
+					l.full_path(),
+					"cuckoo_start_processing",
+					code,
+					String::from("starting processing"),
+				);
+				failing_since[plugin_index] = Some(time::Instant::now());
+				last_error = e.to_string();
+				self.plugin_failed(&mut failing, plugin_index, e);
+			} else {
+				started[plugin_index] = true;
+			}
 		}
+		self.events.send(CuckooMinerEvent::JobStarted { job_id: job.job_id });
 
 		debug!("Cuckoo Miner Job loop processing");
-		let mut solution = CuckooMinerSolution::new();
+		// one solution buffer per plugin, sized to its proof size
+		let mut solutions: Vec<CuckooMinerSolution> = self.libraries
+			.read()
+			.unwrap()
+			.iter()
+			.map(|l| CuckooMinerSolution::with_proof_size(l.proof_size()))
+			.collect();
 
 		loop {
 			// Check if it's time to stop
@@ -254,45 +567,183 @@ impl Delegator {
 					break;
 				}
 			}
-			for l in self.libraries.read().unwrap().iter() {
-				while l.call_cuckoo_is_queue_under_limit() == 1 {
+			let skipped = self.restart_stopped_plugins(&mut started);
+			// Switch to a new job template if one has been provided,
+			// without stopping the plugins
+			let job_updated = {
+				let mut c = self.control_data.write().unwrap();
+				let updated = c.job_updated;
+				c.job_updated = false;
+				updated
+			};
+			if job_updated {
+				let new_job = JobTemplate::from_shared_data(&self.shared_data.read().unwrap());
+				superseded_jobs.push_back((queue_id, job));
+				if superseded_jobs.len() > MAX_SUPERSEDED_JOBS {
+					superseded_jobs.pop_front();
+				}
+				job = new_job;
+				// anything still queued was for the old template, and
+				// anything read later with the old id is stale
+				queue_id = queue_id.wrapping_add(1);
+				for (plugin_index, l) in self.libraries.read().unwrap().iter().enumerate() {
+					if !skipped.contains(&plugin_index) {
+						l.clear_queues();
+					}
+				}
+				debug!(
+					"Cuckoo-miner: Job updated, searching for solution >= difficulty {}",
+					job.difficulty
+				);
+				self.events.send(CuckooMinerEvent::JobStarted { job_id: job.job_id });
+			}
+			for (plugin_index, l) in self.libraries.read().unwrap().iter().enumerate() {
+				if skipped.contains(&plugin_index) {
+					continue;
+				}
+				// plugins which failed to start are retried each time around
+				if !started[plugin_index] {
+					let code = l.start_processing();
+					if code != 0 {
+						let e = CuckooMinerError::plugin_call(
+							l.full_path(),
+							"cuckoo_start_processing",
+							code,
+							String::from("starting processing"),
+						);
+						last_error = e.to_string();
+						self.plugin_failed(&mut failing, plugin_index, e);
+						continue;
+					}
+					started[plugin_index] = true;
+					failing_since[plugin_index] = None;
+					failing[plugin_index] = false;
+				}
+				while l.is_queue_under_limit() {
 					let (nonce, data) = match hash_header {
-						true => self.get_next_header_data_hashed(&pre_nonce, &post_nonce),
-						false => self.get_next_header_data(&pre_nonce, &post_nonce),
+						true => self.get_next_header_data_hashed(&job.pre_nonce, &job.post_nonce),
+						false => self.get_next_header_data(&job.pre_nonce, &job.post_nonce),
 					};
 					// TODO: make this a serialise operation instead
 					let nonce_bytes: [u8; 8] = unsafe { transmute(nonce.to_be()) };
-					l.call_cuckoo_push_to_input_queue(queue_id, &data, &nonce_bytes);
+					let code = l.push_to_input_queue(queue_id, &data, &nonce_bytes);
+					if code != 0 {
+						let e = CuckooMinerError::plugin_call(
+							l.full_path(),
+							"cuckoo_push_to_input_queue",
+							code,
+							String::from("queueing a header"),
+						);
+						failing_since[plugin_index].get_or_insert_with(time::Instant::now);
+						last_error = e.to_string();
+						self.plugin_failed(&mut failing, plugin_index, e);
+						break;
+					}
+					failing_since[plugin_index] = None;
+					failing[plugin_index] = false;
 				}
 			}
+			if num_plugins > 0 && failing_since
+				.iter()
+				.all(|f| f.is_some_and(|t| t.elapsed() >= PLUGIN_FAILURE_TIMEOUT))
+			{
+				result = Err(CuckooMinerError::JobFailedError(format!(
+					"Every plugin has been failing for {}s, last error: {}",
+					PLUGIN_FAILURE_TIMEOUT.as_secs(),
+					last_error
+				)));
+				break;
+			}
 
-			let mut plugin_index=0;
-			for l in self.libraries.read().unwrap().iter() {
+			for (plugin_index, l) in self.libraries.read().unwrap().iter().enumerate() {
+				if skipped.contains(&plugin_index) {
+					continue;
+				}
+				let solution = &mut solutions[plugin_index];
 				let mut qid:u32 = 0;
-				while l.call_cuckoo_read_from_output_queue(
+				while l.read_from_output_queue(
 					&mut qid,
 					&mut solution.solution_nonces,
 					&mut solution.cuckoo_size,
 					&mut solution.nonce,
-				) != 0
-				{
+				) {
 					// TODO: make this a serialise operation instead
 					let nonce = unsafe { transmute::<[u8; 8], u64>(solution.nonce) }.to_be();
 
-					if self.meets_difficulty(difficulty, solution) && qid == queue_id {
+					let is_stale = qid != queue_id;
+					let solution_job = if !is_stale {
+						&job
+					} else {
+						match superseded_jobs.iter().find(|j| j.0 == qid) {
+							Some(j) => &j.1,
+							None => {
+								// too old to check against its template
+								debug!(
+									"Cuckoo-miner plugin[{}]: Discarding stale solution for a forgotten job, Nonce:({})",
+									plugin_index,
+									nonce
+								);
+								let mut s = self.shared_data.write().unwrap();
+								s.stale_solutions[plugin_index] += 1;
+								continue;
+							}
+						}
+					};
+					solution.graph_type = l.metadata().graph_type;
+					solution.job_id = solution_job.job_id;
+					solution.job_template_hash = solution_job.hash;
+					// Check the cycle against the data it was found for before
+					// trusting anything else about it
+					let data = self.plugin_data(
+						&solution_job.pre_nonce,
+						&solution_job.post_nonce,
+						nonce,
+						hash_header,
+					);
+					if let Err(e) = solution.verify(&data) {
+						error!(
+							"Cuckoo-miner plugin[{}]: Rejected solution for Nonce:({}), {:?} - {:?}",
+							plugin_index,
+							nonce,
+							solution,
+							e
+						);
+						let mut s = self.shared_data.write().unwrap();
+						s.rejected_solutions[plugin_index] += 1;
+						continue;
+					}
+					if is_stale {
+						debug!(
+							"Cuckoo-miner plugin[{}]: Discarding stale solution for Job: {}, Nonce:({})",
+							plugin_index,
+							solution.job_id,
+							nonce
+						);
+						let mut s = self.shared_data.write().unwrap();
+						s.stale_solutions[plugin_index] += 1;
+						continue;
+					}
+					if self.meets_difficulty(job.difficulty, solution) {
 						debug!(
 							"Cuckoo-miner plugin[{}]: Solution Found for Nonce:({}), {:?}",
 							plugin_index,
 							nonce,
 							solution
 						);
-						let mut s = self.shared_data.write().unwrap();
+						let s = self.shared_data.read().unwrap();
 						s.solutions.push(solution.clone());
-						plugin_index+=1;
+						self.events.send(CuckooMinerEvent::SolutionFound {
+							plugin_index: plugin_index,
+							solution: solution.clone(),
+						});
 					}
 
 				}
 			}
+			if self.events.has_subscribers() && time::Instant::now() >= next_stats {
+				self.send_stats(&mut failing, &mut errored);
+				next_stats = time::Instant::now() + STATS_EVENT_INTERVAL;
+			}
 			//avoid busy wait 
 			let sleep_dur = time::Duration::from_millis(100);
 			thread::sleep(sleep_dur);
@@ -300,17 +751,17 @@ impl Delegator {
 
 		// Do any cleanup
 		for l in self.libraries.read().unwrap().iter() {
-			l.call_cuckoo_stop_processing();
+			l.stop_processing();
 		}
 		for l in self.libraries.read().unwrap().iter() {
 			//wait for internal processing to finish
-			while l.call_cuckoo_has_processing_stopped()==0{
+			while !l.has_processing_stopped() {
 				thread::sleep(time::Duration::from_millis(1));
 			};
-			l.call_cuckoo_reset_processing();
+			l.reset_processing();
 		}
 		let mut s = self.control_data.write().unwrap();
 		s.has_stopped=true;
-		Ok(())
+		result
 	}
 }
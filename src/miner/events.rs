@@ -0,0 +1,147 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Events sent by a running job, so callers can react to solutions,
+//! failures and stats as they happen instead of polling the job handle.
+//!
+//! #Example
+//! ```
+//! extern crate cuckoo_miner as cuckoo;
+//! use std::time::Duration;
+//! use cuckoo::{CuckooMiner, CuckooMinerEvent, MockBackend, SequentialNonce};
+//!
+//! let miner = CuckooMiner::with_backends(vec![Box::new(MockBackend::new("mock_cpu_16"))]).unwrap();
+//! let events = miner.events();
+//! let job_handle = miner
+//! 	.notify(1, "52", "", 0, false, Box::new(SequentialNonce::new(0)))
+//! 	.unwrap();
+//! match events.recv_timeout(Duration::from_secs(10)).unwrap() {
+//! 	CuckooMinerEvent::JobStarted { job_id } => assert_eq!(job_id, 1),
+//! 	e => panic!("Unexpected event {:?}", e),
+//! }
+//! job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+//! ```
+
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use super::miner::{CuckooMinerDeviceStats, CuckooMinerSolution};
+
+/// Something that happened to a running job. Sent to every receiver
+/// returned by [CuckooMiner::events](struct.CuckooMiner.html#method.events)
+/// or [CuckooMinerJobHandle::events](struct.CuckooMinerJobHandle.html#method.events)
+
+#[derive(Debug, Clone)]
+pub enum CuckooMinerEvent {
+	/// The job loop has started processing a job, either the job given to
+	/// [notify](struct.CuckooMiner.html#method.notify) or one replacing it
+	/// via [update_job](struct.CuckooMinerJobHandle.html#method.update_job)
+	JobStarted {
+		/// The id of the job
+		job_id: u32,
+	},
+
+	/// The job loop has stopped, and the plugins have stopped processing
+	JobStopped {
+		/// The id of the job being processed when the loop stopped
+		job_id: u32,
+
+		/// Why the loop stopped, if it wasn't stopped by the caller
+		error: Option<String>,
+	},
+
+	/// A verified solution meeting the job's difficulty has been added
+	/// to the solution queue
+	SolutionFound {
+		/// The index of the plugin which found it
+		plugin_index: usize,
+
+		/// The solution
+		solution: CuckooMinerSolution,
+	},
+
+	/// A plugin's device stats have been read, which happens about once a
+	/// second while anyone is listening for events
+	StatsUpdated {
+		/// The index of the plugin
+		plugin_index: usize,
+
+		/// The stats of each of the plugin's devices
+		stats: Vec<CuckooMinerDeviceStats>,
+	},
+
+	/// A device has started reporting that it has errored
+	DeviceErrored {
+		/// The index of the plugin the device belongs to
+		plugin_index: usize,
+
+		/// The device's stats, including its id and name
+		stats: CuckooMinerDeviceStats,
+	},
+
+	/// A call to a plugin failed. Sent once until a later call to the
+	/// plugin succeeds
+	PluginFailed {
+		/// The index of the plugin
+		plugin_index: usize,
+
+		/// Why the call failed
+		error: String,
+	},
+}
+
+/// The senders of a job's event channels, one for each receiver handed
+/// out, or None once the job has stopped. Receivers which have been
+/// dropped are forgotten on the next send
+
+pub struct JobEvents {
+	senders: Mutex<Option<Vec<Sender<CuckooMinerEvent>>>>,
+}
+
+impl Default for JobEvents {
+	fn default() -> JobEvents {
+		JobEvents {
+			senders: Mutex::new(Some(Vec::new())),
+		}
+	}
+}
+
+impl JobEvents {
+	/// Returns a new receiver, which gets every event sent from now on.
+	/// Once closed, the receiver is already disconnected
+	pub fn subscribe(&self) -> Receiver<CuckooMinerEvent> {
+		let (sender, receiver) = channel();
+		if let Some(ref mut s) = *self.senders.lock().unwrap() {
+			s.push(sender);
+		}
+		receiver
+	}
+
+	/// Whether any receivers are still listening
+	pub fn has_subscribers(&self) -> bool {
+		self.senders.lock().unwrap().as_ref().is_some_and(|s| !s.is_empty())
+	}
+
+	/// Sends an event to every receiver
+	pub fn send(&self, event: CuckooMinerEvent) {
+		if let Some(ref mut s) = *self.senders.lock().unwrap() {
+			s.retain(|s| s.send(event.clone()).is_ok());
+		}
+	}
+
+	/// Disconnects every receiver, once the last event has been sent
+	pub fn close(&self) {
+		*self.senders.lock().unwrap() = None;
+	}
+}
@@ -16,8 +16,11 @@
 //! to load a mining plugin, send it a Cuckoo Cycle POW problem, and
 //! return any resulting solutions.
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::sync::mpsc::Receiver;
 use std::{thread, time};
+use std::thread::JoinHandle;
+use std::time::Duration;
 use std::{fmt, cmp};
 use std::path::Path;
 
@@ -26,62 +29,108 @@ use blake2::blake2b::Blake2b;
 
 use serde_json;
 
-use super::delegator:: {JobSharedData, JobControlData, Delegator};
-use cuckoo_sys::manager::PluginLibrary;
+use super::delegator:: {JobSharedData, JobControlData, Delegator, SolutionQueue};
+use super::events::{CuckooMinerEvent, JobEvents};
+use super::verify::{self, CuckooGraphType};
+use super::nonce::NonceStrategy;
+use cuckoo_sys::manager::{PluginLibrary, DEFAULT_PROOF_SIZE};
+use backend::backend::SolverBackend;
+use backend::isolated::IsolatedPlugin;
+use backend::lean::{is_builtin_solver, LeanSolver};
+use manager::parameters::{read_declared_parameters, validate_parameters};
+use manager::memory::{estimate_plugin_memory, format_mib, num_threads};
+use manager::host::HostCapabilities;
 use error::error::CuckooMinerError;
 
-// Hardcoded assumption for now that the solution size will be 42 will be
-// maintained, to avoid having to allocate memory within the called C functions
+/// A simple struct to hold a cuckoo miner solution. The `solution_nonces`
+/// member is allocated here at the proof size reported by the plugin,
+/// and will be filled in by the plugin upon finding a solution, so
+/// no memory needs to be allocated within the called C functions.
 
-const CUCKOO_SOLUTION_SIZE: usize = 42;
-
-/// A simple struct to hold a cuckoo miner solution. Currently,
-/// it's assumed that a solution will be 42 bytes. The `solution_nonces`
-/// member is statically allocated here, and will be filled in
-/// by a plugin upon finding a solution.
-
-#[derive(Copy)]
+#[derive(Clone)]
 pub struct CuckooMinerSolution {
 	/// Cuckoo size
 	pub cuckoo_size: u32,
-	/// An array allocated in rust that will be filled
+	/// A vector allocated in rust that will be filled
 	/// by the called plugin upon successfully finding
-	/// a solution
-	pub solution_nonces: [u32; CUCKOO_SOLUTION_SIZE],
+	/// a solution. Its length is the proof size.
+	pub solution_nonces: Vec<u32>,
 
 	/// The nonce that was used to generate the
 	/// hash for which a solution was found
 	pub nonce: [u8; 8],
+
+	/// The type of graph the solution was found in, as
+	/// determined by the plugin that found it
+	pub graph_type: CuckooGraphType,
+
+	/// The id of the job the solution was found for, as given to
+	/// `notify` or `update_job`. Always 0 in synchronous mode.
+	pub job_id: u32,
+
+	/// A hash of the header template of the job the solution was
+	/// found for. See
+	/// [template_hash](struct.CuckooMinerSolution.html#method.template_hash)
+	pub job_template_hash: [u8; 32],
 }
 
 impl Default for CuckooMinerSolution {
 	fn default() -> CuckooMinerSolution {
 		CuckooMinerSolution {
 			cuckoo_size: 30,
-			solution_nonces: [0; CUCKOO_SOLUTION_SIZE],
+			solution_nonces: vec![0; DEFAULT_PROOF_SIZE],
 			nonce: [0; 8],
+			graph_type: CuckooGraphType::Cuckoo,
+			job_id: 0,
+			job_template_hash: [0; 32],
 		}
 	}
 }
 
-impl Clone for CuckooMinerSolution {
-	fn clone(&self) -> CuckooMinerSolution {
-		*self
-	}
-}
-
 impl CuckooMinerSolution {
 	/// Creates a new cuckoo miner solution
-	/// with nonces set to a u32 array of size
-	/// 42 filled with zeroes.
+	/// with nonces set to a u32 array of the
+	/// default proof size (42) filled with zeroes.
 
 	pub fn new() -> CuckooMinerSolution {
 		CuckooMinerSolution::default()
 	}
 
-	/// Sets the solution, mostly for testing
-	pub fn set_solution(&mut self, nonces: [u32; CUCKOO_SOLUTION_SIZE]) {
-		self.solution_nonces = nonces;
+	/// Creates a new cuckoo miner solution with nonces
+	/// set to a zero-filled array of the given proof size
+
+	pub fn with_proof_size(proof_size: usize) -> CuckooMinerSolution {
+		CuckooMinerSolution {
+			solution_nonces: vec![0; proof_size],
+			..CuckooMinerSolution::default()
+		}
+	}
+
+	/// Returns the hash identifying a job's header template, which is
+	/// the blake2b hash of the length of `pre_nonce`, then `pre_nonce`
+	/// and `post_nonce` as given to `notify` or `update_job`
+
+	pub fn template_hash(pre_nonce: &str, post_nonce: &str) -> [u8; 32] {
+		let mut len_bytes = [0; 8];
+		BigEndian::write_u64(&mut len_bytes, pre_nonce.len() as u64);
+		let mut blake2b = Blake2b::new(32);
+		blake2b.update(&len_bytes);
+		blake2b.update(pre_nonce.as_bytes());
+		blake2b.update(post_nonce.as_bytes());
+		let mut ret = [0; 32];
+		ret.copy_from_slice(blake2b.finalize().as_bytes());
+		ret
+	}
+
+	/// Sets the solution, mostly for testing. The proof size
+	/// becomes the length of the given nonces
+	pub fn set_solution(&mut self, nonces: &[u32]) {
+		self.solution_nonces = nonces.to_vec();
+	}
+
+	/// The proof size (cycle length) of this solution
+	pub fn proof_size(&self) -> usize {
+		self.solution_nonces.len()
 	}
 
 	/// return the nonce as a u64, for convenience
@@ -91,7 +140,7 @@ impl CuckooMinerSolution {
 
 	/// Converts the proof to a vector of u64s
 	pub fn to_u64s(&self) -> Vec<u64> {
-		let mut nonces = Vec::with_capacity(CUCKOO_SOLUTION_SIZE);
+		let mut nonces = Vec::with_capacity(self.proof_size());
 		for n in self.solution_nonces.iter() {
 			nonces.push(*n as u64);
 		}
@@ -112,6 +161,34 @@ impl CuckooMinerSolution {
 		ret.copy_from_slice(blake2b.finalize().as_bytes());
 		ret
 	}
+
+	/// #Description
+	///
+	/// Verifies the solution against the header it was found for,
+	/// recomputing the siphash edges for each of the solution nonces at
+	/// the recorded `cuckoo_size` and checking they are sorted, in range
+	/// and form a single cycle.
+	///
+	/// #Arguments
+	///
+	/// * `header` (IN) The exact bytes that were passed to the plugin
+	/// when the solution was found
+	///
+	/// #Returns
+	///
+	/// * `Ok()` if the solution is a valid proof for the header
+	/// * Otherwise a
+	/// [CuckooMinerError::InvalidSolutionError](enum.CuckooMinerError.html)
+	/// with the reason the proof was rejected
+
+	pub fn verify(&self, header: &[u8]) -> Result<(), CuckooMinerError> {
+		verify::verify(
+			header,
+			&self.solution_nonces,
+			self.graph_type.edge_bits(self.cuckoo_size),
+			self.graph_type,
+		)
+	}
 }
 
 impl fmt::Display for CuckooMinerSolution {
@@ -137,12 +214,7 @@ impl fmt::Debug for CuckooMinerSolution {
 
 impl cmp::PartialEq for CuckooMinerSolution {
 	fn eq(&self, other: &CuckooMinerSolution) -> bool {
-		for i in 0..CUCKOO_SOLUTION_SIZE {
-			if self.solution_nonces[i] != other.solution_nonces[i] {
-				return false;
-			}
-		}
-		return true;
+		self.solution_nonces == other.solution_nonces
 	}
 }
 
@@ -152,12 +224,20 @@ impl cmp::PartialEq for CuckooMinerSolution {
 pub struct CuckooMinerConfig {
 	/// The full path to the plugin to load and use to find a solution
 	/// to a POW problem. Defaults to empty string, so must be filled
-	/// before use.
+	/// before use. Paths starting with `builtin:` select the
+	/// [built-in solver](struct.LeanSolver.html) instead of a plugin
+	/// file, e.g. `builtin:lean_rust_cpu_16`
 	pub plugin_full_path: String,
 
 	/// A parameter list, which differs depending on which
 	/// plugin is being called
 	pub parameter_list: Vec<(String, u32, u32)>,
+
+	/// Whether to load the plugin in a separate
+	/// [host process](struct.IsolatedPlugin.html), so that the plugin
+	/// crashing doesn't take down the caller. Ignored for built-in
+	/// solvers. Defaults to false
+	pub isolate_plugin: bool,
 }
 
 impl Default for CuckooMinerConfig {
@@ -165,6 +245,7 @@ impl Default for CuckooMinerConfig {
 		CuckooMinerConfig {
 			plugin_full_path: String::from(""),
 			parameter_list: Vec::new(),
+			isolate_plugin: false,
 		}
 	}
 }
@@ -215,6 +296,105 @@ pub struct CuckooMinerDeviceStats {
 	pub iterations_completed: u32,
 }
 
+/// The current value of a plugin parameter on one device, as read back
+/// from the plugin by
+/// [dump_parameters](struct.CuckooMiner.html#method.dump_parameters)
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CuckooMinerParameterValue {
+	/// The index of the plugin, in the order the configs were provided
+	pub plugin_index: usize,
+
+	/// The device the value applies to
+	pub device_id: u32,
+
+	/// The parameter name
+	pub name: String,
+
+	/// The parameter's current value
+	pub value: u32,
+}
+
+/// Reads the current value of a parameter from a loaded plugin
+
+fn read_parameter(library: &dyn SolverBackend, name: &str, device_id: u32) -> Result<u32, CuckooMinerError> {
+	let mut value = 0;
+	let return_code = library.get_parameter(name, device_id, &mut value);
+	if return_code != 0 {
+		return Err(CuckooMinerError::plugin_call(
+			library.full_path(),
+			"cuckoo_get_parameter",
+			return_code,
+			format!("getting {} on device {}", name, device_id),
+		));
+	}
+	Ok(value)
+}
+
+/// Reads and parses the device stats of a loaded plugin, which are
+/// empty if the plugin doesn't report stats
+
+pub fn read_stats(library: &dyn SolverBackend) -> Result<Vec<CuckooMinerDeviceStats>, CuckooMinerError> {
+	if !library.exports("cuckoo_get_stats") {
+		return Ok(Vec::new());
+	}
+	let stats_json = library.stats()?;
+	serde_json::from_str(&stats_json).map_err(|e| CuckooMinerError::JsonError {
+		context: format!("Error reading stats from plugin {}", library.full_path()),
+		source: e,
+	})
+}
+
+/// Reads every parameter a loaded plugin declares on each of the given
+/// devices, or just device 0 if the devices aren't known
+
+fn read_all_parameters(
+	plugin_index: usize,
+	library: &dyn SolverBackend,
+	stats: Result<Vec<CuckooMinerDeviceStats>, CuckooMinerError>,
+) -> Result<Vec<CuckooMinerParameterValue>, CuckooMinerError> {
+	let mut device_ids: Vec<u32> = match stats {
+		Ok(s) => s.iter().filter_map(|d| d.device_id.parse().ok()).collect(),
+		Err(_) => Vec::new(),
+	};
+	device_ids.sort();
+	device_ids.dedup();
+	if device_ids.is_empty() {
+		device_ids.push(0);
+	}
+	let mut values = Vec::new();
+	for p in read_declared_parameters(library)? {
+		for &device_id in &device_ids {
+			values.push(CuckooMinerParameterValue {
+				plugin_index: plugin_index,
+				device_id: device_id,
+				name: p.name.clone(),
+				value: read_parameter(library, &p.name, device_id)?,
+			});
+		}
+	}
+	Ok(values)
+}
+
+fn no_plugin_error(plugin_index: usize) -> CuckooMinerError {
+	CuckooMinerError::PluginNotLoadedError(format!("No plugin loaded at index {}", plugin_index))
+}
+
+/// Whether a job's loop is still running, as returned by
+/// [CuckooMinerJobHandle::status](struct.CuckooMinerJobHandle.html#method.status)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CuckooMinerJobStatus {
+	/// The job loop is running, or still stopping
+	Running,
+
+	/// The job loop has been stopped by the caller
+	Stopped,
+
+	/// The job loop stopped by itself because of an error, or panicked
+	Failed(String),
+}
+
 /// Handle to the miner's running job, used to read solutions
 /// or to control the job. Internal members are not exposed
 /// and all interactions should be via public functions
@@ -229,68 +409,458 @@ pub struct CuckooMinerJobHandle {
 	pub control_data: Arc<RwLock<JobControlData>>,
 
 	/// The loaded plugin
-	pub library: Arc<RwLock<Vec<PluginLibrary>>>,
+	pub library: Arc<RwLock<Vec<Box<dyn SolverBackend>>>>,
+
+	/// The job's solution queue, shared with the job loop so
+	/// callers can wait on it without locking the shared data
+	pub solutions: Arc<SolutionQueue>,
+
+	/// Where the job loop sends its events
+	pub events: Arc<JobEvents>,
+
+	/// Set by the job loop's thread when it finishes
+	pub status: Arc<Mutex<CuckooMinerJobStatus>>,
+
+	/// The job loop's thread, until it has been joined
+	pub thread: Mutex<Option<JoinHandle<Result<(), CuckooMinerError>>>>,
+}
+
+/// A blocking iterator over the solutions found by a running job,
+/// returned by [`CuckooMinerJobHandle::solutions`](struct.CuckooMinerJobHandle.html#method.solutions).
+/// Each call to `next` waits until a solution is found, and the
+/// iterator ends once the job has stopped and all found solutions
+/// have been returned.
+
+pub struct CuckooMinerSolutions<'a> {
+	queue: &'a SolutionQueue,
+}
+
+impl<'a> Iterator for CuckooMinerSolutions<'a> {
+	type Item = CuckooMinerSolution;
+
+	fn next(&mut self) -> Option<CuckooMinerSolution> {
+		self.queue.wait_pop(None)
+	}
 }
 
 impl CuckooMinerJobHandle {
 
 	/// #Description
 	///
-	/// Returns a solution if one is currently waiting.
+	/// Returns a solution if one is currently waiting, without blocking.
+	/// Solutions are returned in the order they were found.
 	///
 	/// #Returns
 	///
-	/// If a solution was found and is waiting in the plugin's input queue,
+	/// If a solution was found and is waiting in the job's output queue,
 	/// returns
 	/// * Ok([CuckooMinerSolution](struct.CuckooMinerSolution.html)) if a
 	/// solution is waiting in the queue. 
 	/// * None if no solution is waiting
 
 	pub fn get_solution(&self) -> Option<CuckooMinerSolution> {
-		// just to prevent endless needless locking of this
-		// when using fast test miners, in real cuckoo30 terms
-		// this shouldn't be an issue
-		// TODO: Make this less blocky
-		thread::sleep(time::Duration::from_millis(10));
-		// let time_pre_lock=Instant::now();
-		let mut s = self.shared_data.write().unwrap();
-		// let time_elapsed=Instant::now()-time_pre_lock;
-		// println!("Get_solution Time spent waiting for lock: {}",
-		// time_elapsed.as_secs()*1000 +(time_elapsed.subsec_nanos()/1_000_000)as u64);
-		if s.solutions.len() > 0 {
-			let sol = s.solutions.pop().unwrap();
-			return Some(sol);
+		self.solutions.pop()
+	}
+
+	/// #Description
+	///
+	/// Waits for a solution to be found, returning as soon as one is
+	/// available rather than polling. Solutions are returned in the order
+	/// they were found.
+	///
+	/// #Arguments
+	///
+	/// * `timeout` The maximum amount of time to wait
+	///
+	/// #Returns
+	///
+	/// * Some([CuckooMinerSolution](struct.CuckooMinerSolution.html)) if a
+	/// solution was waiting or was found before the timeout.
+	/// * None if the timeout expired, or the job has stopped and no
+	/// solutions remain
+
+	pub fn wait_for_solution(&self, timeout: Duration) -> Option<CuckooMinerSolution> {
+		self.solutions.wait_pop(Some(timeout))
+	}
+
+	/// #Description
+	///
+	/// Returns a blocking iterator over the job's solutions, in the order
+	/// they were found. The iterator waits for each solution and ends once
+	/// the job has been stopped (from another thread) and all remaining
+	/// solutions have been read.
+	///
+	/// #Returns
+	///
+	/// A [CuckooMinerSolutions](struct.CuckooMinerSolutions.html) iterator
+
+	pub fn solutions(&self) -> CuckooMinerSolutions<'_> {
+		CuckooMinerSolutions { queue: &self.solutions }
+	}
+
+	/// #Description
+	///
+	/// Returns a channel receiving the job's
+	/// [events](enum.CuckooMinerEvent.html) from now on: solutions found,
+	/// failing plugins and devices, stats, and the job starting and
+	/// stopping. Each call returns a separate receiver getting every
+	/// event, and stats are only read while at least one is listening.
+	/// Use [CuckooMiner::events](struct.CuckooMiner.html#method.events)
+	/// to also receive the events sent as the job starts.
+	///
+	/// #Returns
+	///
+	/// A receiver for [CuckooMinerEvents](enum.CuckooMinerEvent.html),
+	/// which disconnects once the job has stopped
+
+	pub fn events(&self) -> Receiver<CuckooMinerEvent> {
+		self.events.subscribe()
+	}
+
+	/// #Description
+	///
+	/// Returns the number of solutions returned by a plugin which failed
+	/// verification against the header they were found for, and were
+	/// therefore discarded rather than placed in the solution queue.
+	/// A non-zero count indicates a faulty plugin or device.
+	///
+	/// #Arguments
+	///
+	/// * `plugin_index` The index of the plugin, in the order the configs
+	/// were provided to the miner
+	///
+	/// #Returns
+	///
+	/// * Ok(count) if successful
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if there is no
+	/// plugin at the given index
+
+	pub fn get_rejected_solutions(&self, plugin_index: usize) -> Result<u64, CuckooMinerError> {
+		let s = self.shared_data.read().unwrap();
+		match s.rejected_solutions.get(plugin_index) {
+			Some(c) => Ok(*c),
+			None => Err(CuckooMinerError::PluginNotLoadedError(
+				format!("No plugin loaded at index {}", plugin_index),
+			)),
+		}
+	}
+
+	/// #Description
+	///
+	/// Returns the number of valid solutions returned by a plugin for a
+	/// job that had already been replaced by a call to
+	/// [update_job](#method.update_job), which were discarded rather than
+	/// placed in the solution queue. Solutions for jobs replaced so long
+	/// ago that their templates are no longer kept can't be verified, and
+	/// are counted as well.
+	///
+	/// #Arguments
+	///
+	/// * `plugin_index` The index of the plugin, in the order the configs
+	/// were provided to the miner
+	///
+	/// #Returns
+	///
+	/// * Ok(count) if successful
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if there is no
+	/// plugin at the given index
+
+	pub fn get_stale_solutions(&self, plugin_index: usize) -> Result<u64, CuckooMinerError> {
+		let s = self.shared_data.read().unwrap();
+		match s.stale_solutions.get(plugin_index) {
+			Some(c) => Ok(*c),
+			None => Err(CuckooMinerError::PluginNotLoadedError(
+				format!("No plugin loaded at index {}", plugin_index),
+			)),
+		}
+	}
+
+	/// #Description
+	///
+	/// Returns whether a solution was found for the job currently being
+	/// mined, rather than one since replaced by
+	/// [update_job](#method.update_job). Solutions found before an update
+	/// may still be waiting in the solution queue after it.
+	///
+	/// #Arguments
+	///
+	/// * `solution` A solution returned by this job handle
+	///
+	/// #Returns
+	///
+	/// * true if the solution's job id and template hash match the
+	/// current job
+
+	pub fn is_current(&self, solution: &CuckooMinerSolution) -> bool {
+		let s = self.shared_data.read().unwrap();
+		solution.job_id == s.job_id &&
+			solution.job_template_hash ==
+				CuckooMinerSolution::template_hash(&s.pre_nonce, &s.post_nonce)
+	}
+
+	/// #Description
+	///
+	/// Replaces the header template of the running job, without stopping
+	/// or resetting the loaded plugins. Any headers for the previous job
+	/// still waiting in the plugins' queues are cleared, and the plugins
+	/// continue processing with headers built from the new template.
+	///
+	/// #Arguments
+	///
+	/// * `job_id` An identifier for the new job
+	///
+	/// * `pre_nonce` The new hex-encoded header data before the nonce
+	///
+	/// * `post_nonce` The new hex-encoded header data after the nonce
+	///
+	/// * `difficulty` The target difficulty, as in
+	/// [notify](struct.CuckooMiner.html#method.notify)
+	///
+	/// #Returns
+	///
+	/// * Ok() if the job was updated
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if the job has
+	/// already been stopped
+
+	pub fn update_job(
+		&self,
+		job_id: u32,
+		pre_nonce: &str,
+		post_nonce: &str,
+		difficulty: u64,
+	) -> Result<(), CuckooMinerError> {
+		if self.control_data.read().unwrap().stop_flag {
+			return Err(CuckooMinerError::PluginProcessingError(
+				String::from("Can't update a job which has been stopped"),
+			));
 		}
-		None
+		{
+			let mut s = self.shared_data.write().unwrap();
+			s.job_id = job_id;
+			s.pre_nonce = String::from(pre_nonce);
+			s.post_nonce = String::from(post_nonce);
+			s.difficulty = difficulty;
+		}
+		let mut c = self.control_data.write().unwrap();
+		c.job_updated = true;
+		debug!("Job updated to {}", job_id);
+		Ok(())
+	}
+
+	/// #Description
+	///
+	/// Returns whether the job loop is still running, has been stopped,
+	/// or has failed. A job loop which fails stops processing, so this
+	/// should be checked if solutions stop arriving.
+	///
+	/// #Returns
+	///
+	/// The job's [CuckooMinerJobStatus](enum.CuckooMinerJobStatus.html)
+
+	pub fn status(&self) -> CuckooMinerJobStatus {
+		self.status.lock().unwrap_or_else(PoisonError::into_inner).clone()
 	}
 
 	/// #Description
 	///
 	/// Stops the current job, and signals for the loaded plugin to stop
 	/// processing and perform any cleanup it needs to do. Blocks until
-	/// the jobs have completed
+	/// the jobs have completed, or the timeout expires. Can be called
+	/// again after a timeout to carry on waiting.
+	///
+	/// #Arguments
+	///
+	/// * `timeout` The maximum amount of time to wait for the plugins to
+	/// stop processing
 	///
 	/// #Returns
 	///
-	/// Nothing
-
-	pub fn stop_jobs(&self) {
+	/// * Ok() if the job has stopped
+	/// * A [CuckooMinerError::JobStopTimeoutError](enum.CuckooMinerError.html)
+	/// if it's still stopping when the timeout expires
+	/// * The error the job loop failed with, if it failed before it was
+	/// stopped, or a
+	/// [CuckooMinerError::JobFailedError](enum.CuckooMinerError.html)
+	/// describing it if it has already been returned
+
+	pub fn stop_jobs(&self, timeout: Duration) -> Result<(), CuckooMinerError> {
 		debug!("Stop jobs called");
-		{ 
-			let mut r = self.control_data.write().unwrap();
-			r.stop_flag = true;
-		}
+		self.control_data.write().unwrap_or_else(PoisonError::into_inner).stop_flag = true;
 		debug!("Stop jobs flag set");
-		loop {
-			{
-				let r = self.control_data.read().unwrap();
-				if r.has_stopped {
-					break;
+		let deadline = time::Instant::now() + timeout;
+		let mut thread = self.thread.lock().unwrap_or_else(PoisonError::into_inner);
+		if let Some(t) = thread.take() {
+			while !t.is_finished() {
+				if time::Instant::now() >= deadline {
+					*thread = Some(t);
+					return Err(CuckooMinerError::JobStopTimeoutError(timeout));
 				}
+				thread::sleep(time::Duration::from_millis(5));
 			}
-			thread::sleep(time::Duration::from_millis(5));
+			debug!("All jobs have stopped");
+			return match t.join() {
+				Ok(r) => r,
+				Err(_) => Err(CuckooMinerError::JobFailedError(String::from("Job thread panicked"))),
+			};
+		}
+		match self.status() {
+			CuckooMinerJobStatus::Failed(e) => Err(CuckooMinerError::JobFailedError(e)),
+			_ => Ok(()),
+		}
+	}
+
+	/// #Description
+	///
+	/// Changes a parameter of one of the running job's plugins. As plugins
+	/// generally only read their parameters when processing starts, the
+	/// plugin's processing is stopped, the parameter set, and processing
+	/// restarted, while the job carries on with the other plugins. The
+	/// change is checked against the parameters the plugin declares first,
+	/// so an invalid change doesn't interrupt processing.
+	///
+	/// #Arguments
+	///
+	/// * `name` The name of the parameter to set
+	///
+	/// * `device_id` The device the parameter applies to
+	///
+	/// * `value` The value to set the parameter to
+	///
+	/// * `plugin_index` The index of the plugin, in the order the configs
+	/// were provided to the miner
+	///
+	/// * `timeout` The maximum amount of time to wait for the plugin to
+	/// stop processing before the parameter is set
+	///
+	/// #Returns
+	///
+	/// * Ok() if the parameter has been set and processing restarted
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if there is no
+	/// such plugin, the parameter is invalid, the plugin refused it, or
+	/// the plugin didn't stop or restart processing. A plugin which
+	/// doesn't stop within the timeout, or doesn't restart, is left
+	/// unchanged and restarted by the job once it has stopped.
+
+	pub fn set_parameter(
+		&self,
+		name: &str,
+		device_id: u32,
+		value: u32,
+		plugin_index: usize,
+		timeout: Duration,
+	) -> Result<(), CuckooMinerError> {
+		let libraries = self.library.read().unwrap();
+		let library = match libraries.get(plugin_index) {
+			Some(l) => l,
+			None => return Err(no_plugin_error(plugin_index)),
+		};
+		let declared = read_declared_parameters(library.as_ref())?;
+		let violations = validate_parameters(&declared, &[(String::from(name), device_id, value)]);
+		if !violations.is_empty() {
+			return Err(CuckooMinerError::ParameterError(violations.join("; ")));
+		}
+		if self.control_data.read().unwrap().has_stopped {
+			return CuckooMiner::set_parameter(String::from(name), device_id, value, library.as_ref());
+		}
+
+		// the job loop leaves the plugin alone until it's restarted
+		if !self.control_data.write().unwrap().paused_plugins.insert(plugin_index) {
+			return Err(CuckooMinerError::PluginProcessingError(format!(
+				"{} is already being restarted",
+				library.full_path()
+			)));
+		}
+		debug!("Pausing plugin {} to set {} to {}", plugin_index, name, value);
+		library.stop_processing();
+		let deadline = time::Instant::now() + timeout;
+		while !library.has_processing_stopped() {
+			if time::Instant::now() >= deadline {
+				self.restart_in_job_loop(plugin_index);
+				return Err(CuckooMinerError::PluginProcessingError(format!(
+					"{} didn't stop processing within {}ms",
+					library.full_path(),
+					timeout.as_millis()
+				)));
+			}
+			thread::sleep(time::Duration::from_millis(1));
+		}
+		library.reset_processing();
+		let result = CuckooMiner::set_parameter(String::from(name), device_id, value, library.as_ref());
+		// restart whether or not the change was accepted
+		let code = library.start_processing();
+		if code != 0 {
+			// the job loop keeps retrying, as for a plugin which failed
+			// to start with the job
+			self.restart_in_job_loop(plugin_index);
+			return Err(CuckooMinerError::plugin_call(
+				library.full_path(),
+				"cuckoo_start_processing",
+				code,
+				String::from("restarting processing"),
+			));
+		}
+		self.control_data.write().unwrap().paused_plugins.remove(&plugin_index);
+		result
+	}
+
+	// Hands a paused plugin back to the job loop, to restart once it has
+	// stopped processing
+	fn restart_in_job_loop(&self, plugin_index: usize) {
+		let mut c = self.control_data.write().unwrap();
+		c.paused_plugins.remove(&plugin_index);
+		c.restart_plugins.insert(plugin_index);
+	}
+
+	/// #Description
+	///
+	/// Reads the current value of a parameter from a plugin of the
+	/// running job, as the plugin reports it.
+	///
+	/// #Arguments
+	///
+	/// * `name` The name of the parameter
+	///
+	/// * `device_id` The device to read the value for
+	///
+	/// * `plugin_index` The index of the plugin, in the order the configs
+	/// were provided to the miner
+	///
+	/// #Returns
+	///
+	/// * Ok(value) if successful
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if there is no
+	/// such plugin or parameter
+
+	pub fn get_parameter(&self, name: &str, device_id: u32, plugin_index: usize) -> Result<u32, CuckooMinerError> {
+		let libraries = self.library.read().unwrap();
+		match libraries.get(plugin_index) {
+			Some(l) => read_parameter(l.as_ref(), name, device_id),
+			None => Err(no_plugin_error(plugin_index)),
+		}
+	}
+
+	/// #Description
+	///
+	/// Reads back the current value of every parameter declared by every
+	/// plugin of the running job, on each device the plugin reports in its
+	/// stats (or device 0 if it reports none).
+	///
+	/// #Returns
+	///
+	/// * Ok(Vec<[CuckooMinerParameterValue](struct.CuckooMinerParameterValue.html)>)
+	/// if successful
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if any value
+	/// couldn't be read
+
+	pub fn dump_parameters(&self) -> Result<Vec<CuckooMinerParameterValue>, CuckooMinerError> {
+		let num_plugins = self.library.read().unwrap().len();
+		let mut values = Vec::new();
+		for i in 0..num_plugins {
+			let stats = self.get_stats(i);
+			let libraries = self.library.read().unwrap();
+			values.extend(read_all_parameters(i, libraries[i].as_ref(), stats)?);
 		}
-		debug!("All jobs have stopped");
+		Ok(values)
 	}
 
 	/// #Description
@@ -308,30 +878,13 @@ impl CuckooMinerJobHandle {
 	/// error occurred
 
 	pub fn get_stats(&self, plugin_index:usize) -> Result<Vec<CuckooMinerDeviceStats>, CuckooMinerError> {
-		let mut stats_bytes: [u8; 4096] = [0; 4096];
-		let mut stats_bytes_len = stats_bytes.len() as u32;
-		// get a list of parameters
-		self.library.read().unwrap()[plugin_index].call_cuckoo_get_stats(
-			&mut stats_bytes,
-			&mut stats_bytes_len,
-		);
-		let mut stats_vec: Vec<u8> = Vec::new();
-		// result contains null zero
-		for i in 0..stats_bytes_len {
-			stats_vec.push(stats_bytes[i as usize].clone());
-		}
-		let stats_json = String::from_utf8(stats_vec)?;
-		//println!("Stats_json: {}", stats_json);
-
-		let result = serde_json::from_str(&stats_json);
-		if let Err(e) = result {
-			return Err(CuckooMinerError::StatsError(
-				String::from(format!("Error retrieving stats from plugin: {:?}", e)),
-			));
-		}
-
-		let mut result:Vec<CuckooMinerDeviceStats> = result.unwrap();
-		let lib_full_path = &self.library.read().unwrap()[plugin_index].lib_full_path;
+		let libraries = self.library.read().unwrap();
+		let library = match libraries.get(plugin_index) {
+			Some(l) => l,
+			None => return Err(no_plugin_error(plugin_index)),
+		};
+		let mut result = read_stats(library.as_ref())?;
+		let lib_full_path = library.full_path();
 		let path_str = Path::new(lib_full_path).file_name().unwrap();
 		let path = Path::new(path_str).file_stem().unwrap();
 		
@@ -354,7 +907,10 @@ pub struct CuckooMiner {
 	delegator: Option<Delegator>,
 
 	/// Loaded plugin
-	libraries: Vec<PluginLibrary>,
+	libraries: Vec<Box<dyn SolverBackend>>,
+
+	/// Where the job loop will send its events, once started
+	events: Arc<JobEvents>,
 }
 
 impl CuckooMiner {
@@ -369,11 +925,16 @@ impl CuckooMiner {
 	/// that is to be loaded and run, and each of which contains
 	/// the full path name of a valid mining plugin. Each config struct may
 	/// also contain values in its `parameter_list` field, which will be automatically set
-	/// in the specified plugin. 
+	/// in the specified plugin. See
+	/// [PluginParameters](struct.PluginParameters.html) for a checked way to
+	/// build these.
 	///
 	/// #Returns
 	///
 	/// * `Ok()` if successful, and the specified plugin has been loaded internally.
+	/// * A [CuckooMinerError::ParameterError](enum.CuckooMinerError.html)
+	/// listing every parameter with an unknown name or a value outside the
+	/// range the plugin declares, if any
 	/// * Otherwise a [CuckooMinerError](enum.CuckooMinerError.html)
 	/// with specific detail
 
@@ -381,22 +942,106 @@ impl CuckooMiner {
 		CuckooMiner::init(configs)
 	}
 
+	/// #Description
+	///
+	/// Creates a new instance of a CuckooMiner which mines with solvers
+	/// that have already been created, such as a
+	/// [MockBackend](struct.MockBackend.html). Any parameters should be set
+	/// on the solvers beforehand, and no memory check is made.
+	///
+	/// #Arguments
+	///
+	/// * `backends` The solvers to mine with, in plugin index order
+	///
+	/// #Returns
+	///
+	/// * `Ok()` if there is at least one solver
+	/// * A [CuckooMinerError::PluginNotLoadedError](enum.CuckooMinerError.html)
+	/// if there are none
+
+	pub fn with_backends(backends: Vec<Box<dyn SolverBackend>>) -> Result<CuckooMiner, CuckooMinerError> {
+		if backends.is_empty() {
+			return Err(CuckooMinerError::PluginNotLoadedError(String::from("No solvers given")));
+		}
+		let configs = backends
+			.iter()
+			.map(|b| {
+				let mut c = CuckooMinerConfig::new();
+				c.plugin_full_path = String::from(b.full_path());
+				c
+			})
+			.collect();
+		Ok(CuckooMiner {
+			configs: configs,
+			delegator: None,
+			libraries: backends,
+			events: Arc::new(JobEvents::default()),
+		})
+	}
+
 	/// Internal function to perform tha actual library loading
 
 	fn init(configs: Vec<CuckooMinerConfig>) -> Result<CuckooMiner, CuckooMinerError> {
 		let mut lib_vec=Vec::new();
+		let mut memory = Vec::new();
 		for c in &configs {
-			let lib=PluginLibrary::new(&c.plugin_full_path)?;
-			for elem in c.parameter_list.clone() {
-				CuckooMiner::set_parameter(elem.0.clone(), elem.1.clone(), elem.2.clone(), &lib)?;
+			let lib: Box<dyn SolverBackend> = if is_builtin_solver(&c.plugin_full_path) {
+				Box::new(LeanSolver::new(&c.plugin_full_path)?)
+			} else if c.isolate_plugin {
+				Box::new(IsolatedPlugin::new(&c.plugin_full_path)?)
+			} else {
+				Box::new(PluginLibrary::new(&c.plugin_full_path)?)
+			};
+			// check all parameters up front, so every problem is reported
+			let declared = read_declared_parameters(lib.as_ref())?;
+			let violations = validate_parameters(&declared, &c.parameter_list);
+			if !violations.is_empty() {
+				return Err(CuckooMinerError::ParameterError(format!(
+					"Invalid parameters for plugin {}: {}",
+					c.plugin_full_path,
+					violations.join("; ")
+				)));
 			}
+			let file_name = Path::new(&c.plugin_full_path)
+				.file_name()
+				.and_then(|n| n.to_str())
+				.unwrap_or("");
+			let threads = num_threads(&c.parameter_list, &declared);
+			memory.push((file_name.to_owned(), estimate_plugin_memory(file_name, lib.metadata(), threads)));
 			lib_vec.push(lib);
 		}
 
+		// refuse to start plugins that would be killed for lack of memory
+		let required: u64 = memory.iter().filter_map(|m| m.1).sum();
+		if let Some(available) = HostCapabilities::detect().available_memory {
+			if required > available {
+				let detail = memory
+					.iter()
+					.map(|m| match m.1 {
+						Some(b) => format!("{}: {}", m.0, format_mib(b)),
+						None => format!("{}: unknown", m.0),
+					})
+					.collect::<Vec<_>>()
+					.join(", ");
+				return Err(CuckooMinerError::InsufficientMemoryError {
+					required: required,
+					available: available,
+					detail: detail,
+				});
+			}
+		}
+
+		for (c, lib) in configs.iter().zip(lib_vec.iter()) {
+			for elem in c.parameter_list.clone() {
+				CuckooMiner::set_parameter(elem.0.clone(), elem.1, elem.2, lib.as_ref())?;
+			}
+		}
+
 		let ret_val=CuckooMiner {
 			configs : configs.clone(),
 			delegator : None,
 			libraries : lib_vec,
+			events : Arc::new(JobEvents::default()),
 		};
 
 		Ok(ret_val)
@@ -420,27 +1065,15 @@ impl CuckooMiner {
 	/// with specific detail is returned.
 	///
 
-	pub fn set_parameter(name: String, device_id: u32, value: u32, library:&PluginLibrary) -> Result<(), CuckooMinerError> {
-		let return_code = library.call_cuckoo_set_parameter(
-			name.as_bytes(),
-			device_id,
-			value,
-		);
+	pub fn set_parameter(name: String, device_id: u32, value: u32, library: &dyn SolverBackend) -> Result<(), CuckooMinerError> {
+		let return_code = library.set_parameter(&name, device_id, value);
 		if return_code != 0 {
-
-			let reason = match return_code {
-				1 => "Property doesn't exist for this plugin",
-				2 => "Property outside allowed range",
-				5 => "Device doesn't exist",
-				_ => "Unknown Error",
-			};
-
-			return Err(CuckooMinerError::ParameterError(String::from(format!(
-				"Error setting parameter: {} to {} - {}",
-				name,
-				value,
-				reason
-			))));
+			return Err(CuckooMinerError::plugin_call(
+				library.full_path(),
+				"cuckoo_set_parameter",
+				return_code,
+				format!("setting {} to {} on device {}", name, value, device_id),
+			));
 		}
 		Ok(())
 	}
@@ -449,8 +1082,8 @@ impl CuckooMiner {
 	///
 	/// Synchronous call to the cuckoo_call function of the currently loaded
 	/// plugin, which will perform
-	/// a Cuckoo Cycle on the given seed, filling the first solution (a length
-	/// 42 cycle) that is found in the provided
+	/// a Cuckoo Cycle on the given seed, filling the first solution (a cycle
+	/// of the plugin's proof size) that is found in the provided
 	/// [CuckooMinerSolution](struct.CuckooMinerSolution.html) structure.
 	/// The implementation details are dependent on the particular loaded plugin.
 	/// Values provided
@@ -469,11 +1102,13 @@ impl CuckooMiner {
 	/// * `solution` (OUT) An empty
 	/// [CuckooMinerSolution](struct.CuckooMinerSolution.html).
 	/// If a solution is found, this structure will contain a list of
-	/// solution nonces, otherwise, it will remain untouched.
+	/// solution nonces, otherwise, it will remain untouched. If its
+	/// nonces don't match the plugin's proof size, they are first
+	/// replaced by a zeroed array of the correct size.
 	///
 	/// #Returns
 	///
-	/// * Ok(true) if a solution is found, with the 42 solution nonces
+	/// * Ok(true) if a solution is found, with the solution nonces
 	/// contained within
 	/// the provided [CuckooMinerSolution](struct.CuckooMinerSolution.html).
 	/// * Ok(false) if no solution is found and `solution` remains untouched.
@@ -487,7 +1122,11 @@ impl CuckooMiner {
 		solution: &mut CuckooMinerSolution,
 		plugin_index: usize
 	) -> Result<bool, CuckooMinerError> {
-		let result = self.libraries[plugin_index].call_cuckoo(
+		let proof_size = self.libraries[plugin_index].proof_size();
+		if solution.proof_size() != proof_size {
+			solution.solution_nonces = vec![0; proof_size];
+		}
+		let result = self.libraries[plugin_index].solve(
 			header,
 			cuckoo_size,
 			&mut solution.solution_nonces,
@@ -495,13 +1134,68 @@ impl CuckooMiner {
 		match result {
 			1 => {
 				debug!("Solution found.");
+				solution.cuckoo_size = *cuckoo_size;
+				solution.graph_type = self.libraries[plugin_index].metadata().graph_type;
 				Ok(true)
 			}
 			0 => Ok(false),
-			_ => Err(CuckooMinerError::UnexpectedResultError(result)),
+			_ => Err(CuckooMinerError::plugin_call(
+				self.libraries[plugin_index].full_path(),
+				"cuckoo_call",
+				result,
+				String::from("searching for a solution"),
+			)),
+		}
+	}
+
+	/// #Description
+	///
+	/// Reads the current value of a parameter from a loaded plugin, as
+	/// the plugin reports it.
+	///
+	/// #Arguments
+	///
+	/// * `name` The name of the parameter
+	///
+	/// * `device_id` The device to read the value for
+	///
+	/// * `plugin_index` The index of the plugin, in the order the configs
+	/// were provided
+	///
+	/// #Returns
+	///
+	/// * Ok(value) if successful
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if there is no
+	/// such plugin or parameter
+
+	pub fn get_parameter(&self, name: &str, device_id: u32, plugin_index: usize) -> Result<u32, CuckooMinerError> {
+		match self.libraries.get(plugin_index) {
+			Some(l) => read_parameter(l.as_ref(), name, device_id),
+			None => Err(no_plugin_error(plugin_index)),
 		}
 	}
 
+	/// #Description
+	///
+	/// Reads back the current value of every parameter declared by every
+	/// loaded plugin, on each device the plugin reports in its stats (or
+	/// device 0 if it reports none).
+	///
+	/// #Returns
+	///
+	/// * Ok(Vec<[CuckooMinerParameterValue](struct.CuckooMinerParameterValue.html)>)
+	/// if successful
+	/// * A [CuckooMinerError](enum.CuckooMinerError.html) if any value
+	/// couldn't be read
+
+	pub fn dump_parameters(&self) -> Result<Vec<CuckooMinerParameterValue>, CuckooMinerError> {
+		let mut values = Vec::new();
+		for (i, l) in self.libraries.iter().enumerate() {
+			values.extend(read_all_parameters(i, l.as_ref(), self.get_stats(i))?);
+		}
+		Ok(values)
+	}
+
 	/// #Description
 	///
 	/// Returns an vector of [CuckooMinerDeviceStats](struct.CuckooMinerDeviceStats.html)
@@ -516,28 +1210,26 @@ impl CuckooMiner {
 	/// error occurred
 
 	pub fn get_stats(&self, plugin_index:usize) -> Result<Vec<CuckooMinerDeviceStats>, CuckooMinerError> {
-		let mut stats_bytes: [u8; 2048] = [0; 2048];
-		let mut stats_bytes_len = stats_bytes.len() as u32;
-		// get a list of parameters
-		self.libraries[plugin_index].call_cuckoo_get_stats(
-			&mut stats_bytes,
-			&mut stats_bytes_len,
-		);
-		let mut stats_vec: Vec<u8> = Vec::new();
-		// result contains null zero
-		for i in 0..stats_bytes_len {
-			stats_vec.push(stats_bytes[i as usize].clone());
+		match self.libraries.get(plugin_index) {
+			Some(l) => read_stats(l.as_ref()),
+			None => Err(no_plugin_error(plugin_index)),
 		}
-		let stats_json = String::from_utf8(stats_vec)?;
-		//println!("Stats_json: {}", stats_json);
+	}
 
-		let result = serde_json::from_str(&stats_json);
-		if let Err(e) = result {
-			return Err(CuckooMinerError::StatsError(
-				String::from(format!("Error retrieving stats from plugin: {:?}", e)),
-			));
-		}
-		Ok(result.unwrap())
+	/// #Description
+	///
+	/// Returns a channel receiving the events of the job started by
+	/// [notify](#method.notify), so none are missed between starting the
+	/// job and subscribing through its
+	/// [job handle](struct.CuckooMinerJobHandle.html#method.events).
+	///
+	/// #Returns
+	///
+	/// A receiver for [CuckooMinerEvents](enum.CuckooMinerEvent.html)
+	///
+
+	pub fn events(&self) -> Receiver<CuckooMinerEvent> {
+		self.events.subscribe()
 	}
 
 	/// #Description
@@ -561,7 +1253,9 @@ impl CuckooMiner {
 	///
 	/// #Arguments
 	///
-	/// * `job_id` (IN) A job ID, for later reference (not currently used).
+	/// * `job_id` (IN) A job ID, carried by each solution found for the
+	///   job, so solutions for a job which has since been replaced can be
+	///   told apart and discarded as stale.
 	///
 	/// * `pre_nonce` (IN) The part of the header which comes before the nonce,
 	///   as a hex string slice.
@@ -574,14 +1268,23 @@ impl CuckooMiner {
 	/// * `difficulty` (IN) The miner will only put solutions greater than or
 	///   equal to this difficulty in its output queue.
 	///
+	/// * `hash_header` (IN) Whether to hash the header before sending it
+	///   to the plugin (true for testnet2 and earlier).
+	///
+	/// * `nonce_strategy` (IN) The [NonceStrategy](trait.NonceStrategy.html)
+	///   used to generate the nonces inserted into the header, e.g.
+	///   [RandomNonce](struct.RandomNonce.html), or a
+	///   [PartitionedNonce](struct.PartitionedNonce.html) to search a range
+	///   disjoint from other rigs.
+	///
 	/// #Returns
 	///
 	/// * Ok([CuckooMinerJobHandle](struct.CuckooMinerJobHandle.html)) if the
 	/// job
 	/// is successfully started.
 	/// * A [CuckooMinerError](enum.CuckooMinerError.html)
-	/// if there is no plugin loaded, or if there is an error calling the
-	/// function.
+	/// if there is no plugin loaded, if there is an error calling the
+	/// function, or if the job's thread can't be started.
 
 	pub fn notify(
 		mut self,
@@ -591,10 +1294,19 @@ impl CuckooMiner {
 		difficulty: u64, /* The target difficulty, only sols greater than this difficulty will
 		                  * be returned. */
 		hash_header: bool, // (Temporary) Whether to hash the header before sending (true for testnet2 and earlier)
+		nonce_strategy: Box<dyn NonceStrategy>, // Source of the nonces inserted into the header
 	) -> Result<CuckooMinerJobHandle, CuckooMinerError> {
 
 		//Note this gives up the plugin to the job thread
-		self.delegator = Some(Delegator::new(job_id, pre_nonce, post_nonce, difficulty, self.libraries));
-		Ok(self.delegator.unwrap().start_job_loop(hash_header).unwrap())
+		self.delegator = Some(Delegator::new(
+			job_id,
+			pre_nonce,
+			post_nonce,
+			difficulty,
+			self.libraries,
+			nonce_strategy,
+			self.events,
+		));
+		self.delegator.unwrap().start_job_loop(hash_header)
 	}
 }
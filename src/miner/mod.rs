@@ -21,5 +21,9 @@
 #![deny(unused_mut)]
 #![warn(missing_docs)]
 
+pub mod benchmark;
 mod delegator;
+pub mod events;
 pub mod miner;
+pub mod verify;
+pub mod nonce;
@@ -0,0 +1,131 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Nonce generation strategies used by the job delegator when mutating
+//! a header in asynchronous mode. A strategy is handed to
+//! [`notify`](struct.CuckooMiner.html#method.notify), and is then asked
+//! for a new nonce each time a header is pushed to a plugin's queue.
+
+use rand::{Rng, SeedableRng, StdRng};
+
+use error::error::CuckooMinerError;
+
+/// A source of nonces for the job delegator. Implementations must be
+/// `Send`, as they're moved into the job loop's thread.
+
+pub trait NonceStrategy: Send {
+	/// Returns the next nonce to insert into the header
+	fn next_nonce(&mut self) -> u64;
+}
+
+/// Returns consecutive nonces counting up from a given start,
+/// wrapping around at `u64::MAX`. Useful for exactly reproducing
+/// a run.
+
+pub struct SequentialNonce {
+	next: u64,
+}
+
+impl SequentialNonce {
+	/// Creates a strategy whose first nonce is `start`
+	pub fn new(start: u64) -> SequentialNonce {
+		SequentialNonce { next: start }
+	}
+}
+
+impl NonceStrategy for SequentialNonce {
+	fn next_nonce(&mut self) -> u64 {
+		let nonce = self.next;
+		self.next = self.next.wrapping_add(1);
+		nonce
+	}
+}
+
+/// Returns random nonces. The generator is seeded once, either from
+/// the OS or from a caller-provided seed, rather than on every call.
+
+pub struct RandomNonce {
+	rng: StdRng,
+}
+
+impl RandomNonce {
+	/// Creates a strategy seeded from the OS random number generator
+	pub fn new() -> RandomNonce {
+		RandomNonce { rng: StdRng::new().unwrap() }
+	}
+
+	/// Creates a strategy with a fixed seed, which will always return
+	/// the same sequence of nonces
+	pub fn from_seed(seed: u64) -> RandomNonce {
+		let seed = [(seed >> 32) as usize, seed as u32 as usize];
+		RandomNonce { rng: StdRng::from_seed(&seed[..]) }
+	}
+}
+
+impl Default for RandomNonce {
+	fn default() -> RandomNonce {
+		RandomNonce::new()
+	}
+}
+
+impl NonceStrategy for RandomNonce {
+	fn next_nonce(&mut self) -> u64 {
+		self.rng.gen()
+	}
+}
+
+/// Returns nonces from a partitioned range: `start`, `start + stride`,
+/// `start + 2 * stride`, and so on, wrapping around at `u64::MAX`.
+/// Giving each of N workers the same stride N and a distinct start
+/// below N ensures no two workers search the same nonce until their
+/// sequences wrap around. Unless N is a power of two, the sequences
+/// shift after wrapping, and may then overlap.
+
+pub struct PartitionedNonce {
+	next: u64,
+	stride: u64,
+}
+
+impl PartitionedNonce {
+	/// Creates a strategy returning `start`, then every `stride`th nonce
+	/// after it. A stride of 0 is treated as 1.
+	pub fn new(start: u64, stride: u64) -> PartitionedNonce {
+		PartitionedNonce {
+			next: start,
+			stride: if stride == 0 { 1 } else { stride },
+		}
+	}
+
+	/// Creates the partition for worker `worker_index` of `num_workers`,
+	/// each of which will search a disjoint set of nonces. Returns an
+	/// error if `worker_index` isn't below `num_workers`, as its nonces
+	/// would overlap with another worker's
+	pub fn for_worker(worker_index: u64, num_workers: u64) -> Result<PartitionedNonce, CuckooMinerError> {
+		if worker_index >= num_workers {
+			return Err(CuckooMinerError::ParameterError(format!(
+				"Worker index {} is out of range for {} workers",
+				worker_index, num_workers
+			)));
+		}
+		Ok(PartitionedNonce::new(worker_index, num_workers))
+	}
+}
+
+impl NonceStrategy for PartitionedNonce {
+	fn next_nonce(&mut self) -> u64 {
+		let nonce = self.next;
+		self.next = self.next.wrapping_add(self.stride);
+		nonce
+	}
+}
@@ -0,0 +1,222 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pure-Rust verification of the proofs returned by plugins. Recomputes
+//! the siphash edges of a solution from the header it was found for and
+//! checks that they form a single cycle of the expected length, following
+//! the reference `verify` functions in John Tromp's cuckoo and cuckatoo
+//! implementations.
+
+use blake2::blake2b::blake2b;
+use byteorder::{ByteOrder, LittleEndian};
+
+use error::error::CuckooMinerError;
+
+/// The type of graph a plugin searches for cycles. Both variants are built
+/// by the plugin CMake scripts, and differ in how edges map onto nodes.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CuckooGraphType {
+	/// Original Cuckoo Cycle, a bipartite graph with 2^(cuckoo_size-1)
+	/// edges. Plugins named `cuckoo_*`
+	Cuckoo,
+
+	/// Cuckatoo Cycle, with 2^cuckoo_size edges, where each node is
+	/// linked to its partner differing only in the lowest bit. Plugins
+	/// named `cuckatoo_*`
+	Cuckatoo,
+}
+
+impl CuckooGraphType {
+	/// Number of edge bits in a graph of the given cuckoo size
+	pub fn edge_bits(&self, cuckoo_size: u32) -> u32 {
+		match *self {
+			CuckooGraphType::Cuckoo => cuckoo_size.saturating_sub(1),
+			CuckooGraphType::Cuckatoo => cuckoo_size,
+		}
+	}
+}
+
+/// Siphash keys derived from a header
+
+pub struct SipHashKeys {
+	k0: u64,
+	k1: u64,
+	k2: u64,
+	k3: u64,
+}
+
+impl SipHashKeys {
+	/// Derives keys from the blake2b hash of the header, as each plugin
+	/// does before searching a graph
+	pub fn from_header(header: &[u8]) -> SipHashKeys {
+		let hash = blake2b(32, &[], header);
+		let bytes = hash.as_bytes();
+		SipHashKeys {
+			k0: LittleEndian::read_u64(&bytes[0..8]),
+			k1: LittleEndian::read_u64(&bytes[8..16]),
+			k2: LittleEndian::read_u64(&bytes[16..24]),
+			k3: LittleEndian::read_u64(&bytes[24..32]),
+		}
+	}
+
+	/// Siphash-2-4 of a single u64, as used to generate graph edges
+	pub fn siphash24(&self, nonce: u64) -> u64 {
+		let mut v0 = self.k0;
+		let mut v1 = self.k1;
+		let mut v2 = self.k2;
+		let mut v3 = self.k3 ^ nonce;
+
+		sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+		sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+		v0 ^= nonce;
+		v2 ^= 0xff;
+		for _ in 0..4 {
+			sip_round(&mut v0, &mut v1, &mut v2, &mut v3);
+		}
+		v0 ^ v1 ^ v2 ^ v3
+	}
+
+	/// Returns the endpoint of `edge` on side `uorv` (0 or 1) for the
+	/// given graph type
+	pub fn sipnode(&self, graph_type: CuckooGraphType, edge_mask: u64, edge: u64, uorv: u64) -> u64 {
+		let node = self.siphash24(2 * edge + uorv) & edge_mask;
+		match graph_type {
+			CuckooGraphType::Cuckoo => node << 1 | uorv,
+			CuckooGraphType::Cuckatoo => node,
+		}
+	}
+}
+
+fn sip_round(v0: &mut u64, v1: &mut u64, v2: &mut u64, v3: &mut u64) {
+	*v0 = v0.wrapping_add(*v1);
+	*v2 = v2.wrapping_add(*v3);
+	*v1 = v1.rotate_left(13);
+	*v3 = v3.rotate_left(16);
+	*v1 ^= *v0;
+	*v3 ^= *v2;
+	*v0 = v0.rotate_left(32);
+	*v2 = v2.wrapping_add(*v1);
+	*v0 = v0.wrapping_add(*v3);
+	*v1 = v1.rotate_left(17);
+	*v3 = v3.rotate_left(21);
+	*v1 ^= *v2;
+	*v3 ^= *v0;
+	*v2 = v2.rotate_left(32);
+}
+
+fn invalid(reason: &str) -> Result<(), CuckooMinerError> {
+	Err(CuckooMinerError::InvalidSolutionError(String::from(reason)))
+}
+
+/// #Description
+///
+/// Verifies that the given edges form a single cycle covering every edge
+/// in the graph generated from `header`.
+///
+/// #Arguments
+///
+/// * `header` (IN) The exact bytes the plugin was given to search
+///
+/// * `edges` (IN) The solution nonces returned by the plugin
+///
+/// * `edge_bits` (IN) The number of edge bits in the graph
+///
+/// * `graph_type` (IN) Whether the graph is cuckoo or cuckatoo
+///
+/// #Returns
+///
+/// * `Ok()` if the edges form a valid cycle
+/// * Otherwise a
+/// [CuckooMinerError::InvalidSolutionError](enum.CuckooMinerError.html)
+/// describing why the proof was rejected
+
+pub fn verify(
+	header: &[u8],
+	edges: &[u32],
+	edge_bits: u32,
+	graph_type: CuckooGraphType,
+) -> Result<(), CuckooMinerError> {
+	let proof_size = edges.len();
+	if proof_size == 0 || !proof_size.is_multiple_of(2) {
+		return invalid("proof size must be even and non-zero");
+	}
+	if edge_bits == 0 || edge_bits > 32 {
+		return invalid("edge bits out of range");
+	}
+	let edge_mask = (1u64 << edge_bits) - 1;
+	let keys = SipHashKeys::from_header(header);
+
+	let mut uvs = vec![0u64; 2 * proof_size];
+	let (mut xor0, mut xor1) = match graph_type {
+		CuckooGraphType::Cuckoo => (0, 0),
+		CuckooGraphType::Cuckatoo => {
+			let x = (proof_size as u64 / 2) & 1;
+			(x, x)
+		}
+	};
+
+	for n in 0..proof_size {
+		let edge = edges[n] as u64;
+		if edge > edge_mask {
+			return invalid("edge index too big");
+		}
+		if n > 0 && edges[n] <= edges[n - 1] {
+			return invalid("edges not in ascending order");
+		}
+		uvs[2 * n] = keys.sipnode(graph_type, edge_mask, edge, 0);
+		uvs[2 * n + 1] = keys.sipnode(graph_type, edge_mask, edge, 1);
+		xor0 ^= uvs[2 * n];
+		xor1 ^= uvs[2 * n + 1];
+	}
+	if xor0 | xor1 != 0 {
+		return invalid("endpoints don't match up");
+	}
+
+	// follow the cycle from the first edge
+	let mut n = 0;
+	let mut i = 0;
+	loop {
+		let mut j = i;
+		let mut k = i;
+		loop {
+			k = (k + 2) % (2 * proof_size);
+			if k == i {
+				break;
+			}
+			let matches = match graph_type {
+				CuckooGraphType::Cuckoo => uvs[k] == uvs[i],
+				CuckooGraphType::Cuckatoo => uvs[k] >> 1 == uvs[i] >> 1,
+			};
+			if matches {
+				if j != i {
+					return invalid("branch in cycle");
+				}
+				j = k;
+			}
+		}
+		if j == i || (graph_type == CuckooGraphType::Cuckatoo && uvs[j] == uvs[i]) {
+			return invalid("cycle dead ends");
+		}
+		i = j ^ 1;
+		n += 1;
+		if i == 0 {
+			break;
+		}
+	}
+	if n != proof_size {
+		return invalid("cycle too short");
+	}
+	Ok(())
+}
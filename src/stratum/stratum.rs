@@ -0,0 +1,637 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Stratum client, which speaks the newline-delimited JSON-RPC protocol
+//! used by Grin pools. The client can be driven by hand, polling for
+//! jobs and submitting solutions, or left to mine with a
+//! [CuckooMiner](../struct.CuckooMiner.html) via
+//! [run](struct.StratumClient.html#method.run).
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use std::mem;
+
+use serde_json::{self, Value};
+
+use miner::miner::{CuckooMiner, CuckooMinerJobHandle, CuckooMinerJobStatus, CuckooMinerSolution};
+use miner::nonce::NonceStrategy;
+use error::error::CuckooMinerError;
+
+/// How long to wait for the server to answer a login request
+
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the job loop in `run` checks for new jobs and solutions
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long `run` waits for the plugins to stop once mining stops
+
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A mining job sent by the pool
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StratumJob {
+	/// The pool's identifier for the job, which is attached to
+	/// any solutions found for it
+	pub job_id: u32,
+
+	/// The height of the block being mined
+	pub height: u64,
+
+	/// The share difficulty set by the pool
+	pub difficulty: u64,
+
+	/// Hex-encoded header data before the nonce. Nothing follows
+	/// the nonce in a Grin header
+	pub pre_pow: String,
+}
+
+/// Counts of the shares submitted to the pool, and the pool's
+/// responses to them
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StratumShareStats {
+	/// Shares sent to the pool
+	pub submitted: u64,
+
+	/// Shares the pool has accepted
+	pub accepted: u64,
+
+	/// Shares the pool has rejected
+	pub rejected: u64,
+}
+
+/// Something the pool has told the client
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StratumEvent {
+	/// A new job, which replaces the previous one
+	Job(StratumJob),
+
+	/// The pool accepted a submitted share
+	ShareAccepted,
+
+	/// The pool rejected a submitted share, with the reason given
+	ShareRejected(String),
+}
+
+#[derive(Serialize, Debug)]
+struct RpcRequest {
+	id: String,
+	jsonrpc: String,
+	method: String,
+	params: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RpcError {
+	code: i32,
+	message: String,
+}
+
+/// Any message from the server, either a response to one of our
+/// requests or a notification
+
+#[derive(Deserialize, Debug)]
+struct RpcMessage {
+	id: Option<Value>,
+	method: Option<String>,
+	params: Option<Value>,
+	result: Option<Value>,
+	error: Option<RpcError>,
+}
+
+#[derive(Serialize, Debug)]
+struct LoginParams {
+	login: String,
+	pass: String,
+	agent: String,
+}
+
+#[derive(Serialize, Debug)]
+struct SubmitParams {
+	edge_bits: u32,
+	height: u64,
+	job_id: u32,
+	nonce: u64,
+	pow: Vec<u64>,
+}
+
+fn stratum_error(context: &str, error: io::Error) -> CuckooMinerError {
+	CuckooMinerError::IoError {
+		context: String::from(context),
+		source: error,
+	}
+}
+
+fn id_string(id: &Value) -> String {
+	match *id {
+		Value::String(ref s) => s.clone(),
+		ref v => v.to_string(),
+	}
+}
+
+fn parse_job(job: Value) -> Result<StratumJob, CuckooMinerError> {
+	serde_json::from_value(job).map_err(|e| {
+		CuckooMinerError::StratumError(format!("Invalid job from server: {}", e))
+	})
+}
+
+/// A connection to a stratum pool
+
+pub struct StratumClient {
+	/// Stream for writing requests
+	stream: TcpStream,
+
+	/// Buffered reader over a clone of the stream
+	reader: BufReader<TcpStream>,
+
+	/// Partial line read before a read timed out
+	line: String,
+
+	/// Id of the next request
+	next_id: u64,
+
+	/// Methods of the requests awaiting a response, by id
+	pending: HashMap<String, String>,
+
+	/// Events received while waiting for a specific response
+	events: VecDeque<StratumEvent>,
+
+	/// The most recent job sent by the pool
+	current_job: Option<StratumJob>,
+
+	/// Share counts
+	stats: StratumShareStats,
+}
+
+impl StratumClient {
+	/// #Description
+	///
+	/// Connects to a stratum server.
+	///
+	/// #Arguments
+	///
+	/// * `address` The server's address, e.g. `pool.example.com:3416`
+	///
+	/// #Returns
+	///
+	/// * Ok([StratumClient](struct.StratumClient.html)) if connected
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the
+	/// connection failed
+
+	pub fn connect(address: &str) -> Result<StratumClient, CuckooMinerError> {
+		let stream = TcpStream::connect(address)
+			.map_err(|e| stratum_error(&format!("Error connecting to {}", address), e))?;
+		let reader = stream
+			.try_clone()
+			.map_err(|e| stratum_error("Error cloning stream", e))?;
+		Ok(StratumClient {
+			stream: stream,
+			reader: BufReader::new(reader),
+			line: String::new(),
+			next_id: 0,
+			pending: HashMap::new(),
+			events: VecDeque::new(),
+			current_job: None,
+			stats: StratumShareStats::default(),
+		})
+	}
+
+	/// #Description
+	///
+	/// Logs in to the pool, blocking until the server responds. Any jobs
+	/// sent by the server in the meantime are returned by subsequent
+	/// calls to [poll](#method.poll).
+	///
+	/// #Arguments
+	///
+	/// * `login` The login, usually a wallet address or username
+	///
+	/// * `password` The password, if the pool requires one
+	///
+	/// * `agent` A description of this miner
+	///
+	/// #Returns
+	///
+	/// * Ok() if the server accepted the login
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the login
+	/// was rejected, or the server didn't respond in time
+
+	pub fn login(&mut self, login: &str, password: &str, agent: &str) -> Result<(), CuckooMinerError> {
+		let params = LoginParams {
+			login: String::from(login),
+			pass: String::from(password),
+			agent: String::from(agent),
+		};
+		let id = self.send("login", serde_json::to_value(params).ok())?;
+		let response = self.wait_for_response(&id, LOGIN_TIMEOUT)?;
+		if let Some(e) = response.error {
+			return Err(CuckooMinerError::StratumError(
+				format!("Login rejected: {} ({})", e.message, e.code),
+			));
+		}
+		debug!("Stratum: logged in as {}", login);
+		Ok(())
+	}
+
+	/// #Description
+	///
+	/// Asks the server for the current job. The job is returned by
+	/// a subsequent call to [poll](#method.poll).
+
+	pub fn request_job(&mut self) -> Result<(), CuckooMinerError> {
+		self.send("getjobtemplate", None)?;
+		Ok(())
+	}
+
+	/// #Description
+	///
+	/// Submits a solution to the pool as a share. The solution must be
+	/// for the pool's current job. The pool's response is returned by
+	/// a subsequent call to [poll](#method.poll).
+	///
+	/// #Arguments
+	///
+	/// * `solution` A solution found for the current job
+	///
+	/// #Returns
+	///
+	/// * Ok() if the share was sent
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the
+	/// solution isn't for the current job, or couldn't be sent
+
+	pub fn submit(&mut self, solution: &CuckooMinerSolution) -> Result<(), CuckooMinerError> {
+		let height = match self.current_job {
+			Some(ref j) if j.job_id == solution.job_id &&
+				CuckooMinerSolution::template_hash(&j.pre_pow, "") == solution.job_template_hash => j.height,
+			_ => {
+				return Err(CuckooMinerError::StratumError(format!(
+					"Solution for job {} is not for the pool's current job",
+					solution.job_id
+				)))
+			}
+		};
+		let params = SubmitParams {
+			edge_bits: solution.graph_type.edge_bits(solution.cuckoo_size),
+			height: height,
+			job_id: solution.job_id,
+			nonce: solution.get_nonce_as_u64(),
+			pow: solution.to_u64s(),
+		};
+		self.send("submit", serde_json::to_value(params).ok())?;
+		self.stats.submitted += 1;
+		Ok(())
+	}
+
+	/// #Description
+	///
+	/// Waits for the next event from the pool, such as a new job or the
+	/// response to a submitted share.
+	///
+	/// #Arguments
+	///
+	/// * `timeout` The maximum amount of time to wait
+	///
+	/// #Returns
+	///
+	/// * Ok(Some([StratumEvent](enum.StratumEvent.html))) if an event
+	/// was received
+	/// * Ok(None) if nothing was received before the timeout
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the
+	/// connection failed, or the server sent something invalid
+
+	pub fn poll(&mut self, timeout: Duration) -> Result<Option<StratumEvent>, CuckooMinerError> {
+		if let Some(e) = self.events.pop_front() {
+			return Ok(Some(e));
+		}
+		let deadline = Instant::now() + timeout;
+		loop {
+			if let Some(msg) = self.read_message(deadline)? {
+				if let Some(e) = self.handle_message(msg)? {
+					return Ok(Some(e));
+				}
+			}
+			if Instant::now() >= deadline {
+				return Ok(None);
+			}
+		}
+	}
+
+	/// The most recent job sent by the pool, if any
+
+	pub fn current_job(&self) -> Option<&StratumJob> {
+		self.current_job.as_ref()
+	}
+
+	/// Counts of shares submitted, accepted and rejected so far
+
+	pub fn stats(&self) -> StratumShareStats {
+		self.stats
+	}
+
+	/// #Description
+	///
+	/// Mines for the pool until `stop` is set. Waits for a job from the
+	/// pool, starts the miner on it, then switches the running job with
+	/// [update_job](../struct.CuckooMinerJobHandle.html#method.update_job)
+	/// whenever the pool sends a new one. Solutions for the current job
+	/// are submitted as shares. The job is stopped before returning.
+	///
+	/// #Arguments
+	///
+	/// * `miner` A miner with its plugins loaded, which is consumed as
+	/// in [notify](../struct.CuckooMiner.html#method.notify)
+	///
+	/// * `hash_header` Whether to hash the header before passing it to
+	/// the plugins, as in `notify`
+	///
+	/// * `nonce_strategy` The source of nonces, as in `notify`
+	///
+	/// * `stop` Set from another thread to stop mining
+	///
+	/// #Returns
+	///
+	/// * Ok([StratumShareStats](struct.StratumShareStats.html)) once
+	/// stopped
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the
+	/// miner failed to start or stop, the job loop failed, or the
+	/// connection to the pool failed
+
+	pub fn run(
+		&mut self,
+		miner: CuckooMiner,
+		hash_header: bool,
+		nonce_strategy: Box<dyn NonceStrategy>,
+		stop: &AtomicBool,
+	) -> Result<StratumShareStats, CuckooMinerError> {
+		self.run_with_callback(miner, hash_header, nonce_strategy, stop, |_, _| {})
+	}
+
+	/// #Description
+	///
+	/// As [run](#method.run), but calls `callback` with the running job's
+	/// handle and the share counts on each iteration of the mining loop,
+	/// e.g. to report stats.
+	///
+	/// #Arguments
+	///
+	/// * `miner`, `hash_header`, `nonce_strategy`, `stop` As in `run`
+	///
+	/// * `callback` Called about every 100ms while mining
+	///
+	/// #Returns
+	///
+	/// As `run`
+
+	pub fn run_with_callback<F>(
+		&mut self,
+		miner: CuckooMiner,
+		hash_header: bool,
+		nonce_strategy: Box<dyn NonceStrategy>,
+		stop: &AtomicBool,
+		mut callback: F,
+	) -> Result<StratumShareStats, CuckooMinerError>
+	where
+		F: FnMut(&CuckooMinerJobHandle, StratumShareStats),
+	{
+		let job = match self.wait_for_job(stop)? {
+			Some(j) => j,
+			None => return Ok(self.stats),
+		};
+		let job_handle = miner.notify(
+			job.job_id,
+			&job.pre_pow,
+			"",
+			job.difficulty,
+			hash_header,
+			nonce_strategy,
+		)?;
+		let mut result = Ok(());
+		while result.is_ok()
+			&& !stop.load(Ordering::SeqCst)
+			&& job_handle.status() == CuckooMinerJobStatus::Running
+		{
+			result = self.process(&job_handle, POLL_INTERVAL).map(|_| ());
+			callback(&job_handle, self.stats);
+		}
+		// a failed job loop is reported by stopping it
+		let stopped = job_handle.stop_jobs(STOP_TIMEOUT);
+		result.and(stopped).map(|_| self.stats)
+	}
+
+	/// #Description
+	///
+	/// Returns the pool's current job, requesting one and waiting for it
+	/// if none has been received yet.
+	///
+	/// #Arguments
+	///
+	/// * `stop` Set from another thread to stop waiting
+	///
+	/// #Returns
+	///
+	/// * Ok(Some([StratumJob](struct.StratumJob.html))) once a job has
+	/// been received
+	/// * Ok(None) if stopped before a job was received
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the
+	/// connection failed
+
+	pub fn wait_for_job(&mut self, stop: &AtomicBool) -> Result<Option<StratumJob>, CuckooMinerError> {
+		if let Some(ref j) = self.current_job {
+			return Ok(Some(j.clone()));
+		}
+		self.request_job()?;
+		while !stop.load(Ordering::SeqCst) {
+			if let Some(StratumEvent::Job(j)) = self.poll(POLL_INTERVAL)? {
+				return Ok(Some(j));
+			}
+		}
+		Ok(None)
+	}
+
+	/// #Description
+	///
+	/// Performs one iteration of the mining loop used by
+	/// [run](#method.run), for callers who want to drive the loop
+	/// themselves. Submits any solutions for the current job waiting in
+	/// the job handle, then waits for an event from the pool, switching
+	/// the running job if the pool sends a new one.
+	///
+	/// #Arguments
+	///
+	/// * `job_handle` The handle of a job started on a job from
+	/// this pool
+	///
+	/// * `timeout` The maximum amount of time to wait for an event
+	///
+	/// #Returns
+	///
+	/// * Ok(Some([StratumEvent](enum.StratumEvent.html))) if an event
+	/// was received and handled
+	/// * Ok(None) if nothing was received before the timeout
+	/// * A [CuckooMinerError](../enum.CuckooMinerError.html) if the
+	/// connection failed, or the job couldn't be updated
+
+	pub fn process(
+		&mut self,
+		job_handle: &CuckooMinerJobHandle,
+		timeout: Duration,
+	) -> Result<Option<StratumEvent>, CuckooMinerError> {
+		while let Some(s) = job_handle.get_solution() {
+			if job_handle.is_current(&s) {
+				self.submit(&s)?;
+			} else {
+				debug!("Stratum: Not submitting solution for old job {}", s.job_id);
+			}
+		}
+		let event = self.poll(timeout)?;
+		match event {
+			Some(StratumEvent::Job(ref j)) => {
+				debug!("Stratum: New job {} at height {}", j.job_id, j.height);
+				job_handle.update_job(j.job_id, &j.pre_pow, "", j.difficulty)?;
+			}
+			Some(StratumEvent::ShareAccepted) => debug!("Stratum: Share accepted"),
+			Some(StratumEvent::ShareRejected(ref m)) => warn!("Stratum: Share rejected: {}", m),
+			None => {}
+		}
+		Ok(event)
+	}
+
+	/// Sends a request, returning its id
+
+	fn send(&mut self, method: &str, params: Option<Value>) -> Result<String, CuckooMinerError> {
+		let id = self.next_id.to_string();
+		self.next_id += 1;
+		let request = RpcRequest {
+			id: id.clone(),
+			jsonrpc: String::from("2.0"),
+			method: String::from(method),
+			params: params,
+		};
+		let mut msg = serde_json::to_string(&request).map_err(|e| {
+			CuckooMinerError::StratumError(format!("Error serialising request: {}", e))
+		})?;
+		msg.push('\n');
+		self.stream
+			.write_all(msg.as_bytes())
+			.map_err(|e| stratum_error("Error sending request", e))?;
+		self.pending.insert(id.clone(), String::from(method));
+		Ok(id)
+	}
+
+	/// Reads messages until the response to the given request arrives,
+	/// queueing any events received in the meantime
+
+	fn wait_for_response(&mut self, id: &str, timeout: Duration) -> Result<RpcMessage, CuckooMinerError> {
+		let deadline = Instant::now() + timeout;
+		while Instant::now() < deadline {
+			if let Some(msg) = self.read_message(deadline)? {
+				if msg.id.as_ref().map(id_string) == Some(String::from(id)) {
+					self.pending.remove(id);
+					return Ok(msg);
+				}
+				if let Some(e) = self.handle_message(msg)? {
+					self.events.push_back(e);
+				}
+			}
+		}
+		Err(CuckooMinerError::StratumError(
+			String::from("Timed out waiting for a response from the server"),
+		))
+	}
+
+	/// Reads a single message, waiting until the deadline at most
+
+	fn read_message(&mut self, deadline: Instant) -> Result<Option<RpcMessage>, CuckooMinerError> {
+		let now = Instant::now();
+		// a zero timeout isn't allowed
+		let timeout = if deadline > now {
+			deadline - now
+		} else {
+			Duration::from_millis(1)
+		};
+		self.reader
+			.get_ref()
+			.set_read_timeout(Some(timeout))
+			.map_err(|e| stratum_error("Error setting read timeout", e))?;
+		match self.reader.read_line(&mut self.line) {
+			Ok(0) => Err(CuckooMinerError::StratumError(
+				String::from("Connection closed by server"),
+			)),
+			Ok(_) => {
+				let line = mem::take(&mut self.line);
+				let line = line.trim();
+				if line.is_empty() {
+					return Ok(None);
+				}
+				trace!("Stratum: received {}", line);
+				serde_json::from_str(line).map(Some).map_err(|e| {
+					CuckooMinerError::StratumError(format!("Invalid message from server: {}", e))
+				})
+			}
+			// anything read so far stays in self.line until the rest arrives
+			Err(ref e) if e.kind() == io::ErrorKind::WouldBlock ||
+				e.kind() == io::ErrorKind::TimedOut => Ok(None),
+			Err(e) => Err(stratum_error("Error reading from server", e)),
+		}
+	}
+
+	/// Updates the client's state from a message, returning the
+	/// resulting event, if any
+
+	fn handle_message(&mut self, msg: RpcMessage) -> Result<Option<StratumEvent>, CuckooMinerError> {
+		let request_method = msg.id
+			.as_ref()
+			.map(id_string)
+			.and_then(|id| self.pending.remove(&id));
+		let job = match (request_method, msg.method) {
+			(Some(ref m), _) if m == "submit" => {
+				return Ok(Some(match msg.error {
+					Some(e) => {
+						self.stats.rejected += 1;
+						StratumEvent::ShareRejected(e.message)
+					}
+					None => {
+						self.stats.accepted += 1;
+						StratumEvent::ShareAccepted
+					}
+				}));
+			}
+			(Some(ref m), _) if m == "getjobtemplate" => {
+				if let Some(e) = msg.error {
+					warn!("Stratum: Error getting job: {}", e.message);
+					return Ok(None);
+				}
+				msg.result
+			}
+			(None, Some(ref m)) if m == "job" => msg.params,
+			(_, m) => {
+				debug!("Stratum: Ignoring message {:?}", m);
+				return Ok(None);
+			}
+		};
+		let job = match job {
+			Some(j) => parse_job(j)?,
+			None => return Ok(None),
+		};
+		self.current_job = Some(job.clone());
+		Ok(Some(StratumEvent::Job(job)))
+	}
+}
@@ -15,8 +15,14 @@
 
 //! Tests for async mode.. should be run with RUST_TEST_THREADS=1
 
+extern crate cuckoo_miner as cuckoo;
+
 pub mod common;
 
+use std::time::{Duration, Instant};
+
+use cuckoo::{CuckooMiner, CuckooMinerConfig, SequentialNonce};
+
 //mines for a bit on each available plugin, one after the other
 #[test]
 fn on_commit_mine_single_plugin_async() {
@@ -81,3 +87,60 @@ fn on_commit_mine_plugins_async() {
 	}
 	common::mine_async_for_duration(plugin_path_vec, 15, None);
 }
+
+//switches the job template while mining, without stopping the plugin
+#[test]
+fn on_commit_update_job_async() {
+	let caps = common::get_plugin_vec("mean_cpu_16");
+	for c in &caps {
+		let mut config = CuckooMinerConfig::new();
+		config.plugin_full_path = c.full_path.clone();
+		let miner = CuckooMiner::new(vec![config]).expect("");
+		let job_handle = miner.notify(1, common::SAMPLE_GRIN_PRE_HEADER_1,
+			common::SAMPLE_GRIN_POST_HEADER_1, 0, false, Box::new(SequentialNonce::new(0))).unwrap();
+		std::thread::sleep(Duration::from_secs(2));
+
+		// same header with a different first byte
+		let new_pre_header = format!("01{}", &common::SAMPLE_GRIN_PRE_HEADER_1[2..]);
+		job_handle.update_job(2, &new_pre_header, common::SAMPLE_GRIN_POST_HEADER_1, 0).unwrap();
+		let deadline = Instant::now() + Duration::from_secs(60);
+		let mut found_updated = false;
+		while !found_updated && Instant::now() < deadline {
+			if let Some(s) = job_handle.wait_for_solution(Duration::from_secs(5)) {
+				// solutions found before the update can still be waiting
+				found_updated = s.job_id == 2;
+				assert_eq!(job_handle.is_current(&s), found_updated);
+			}
+		}
+		assert!(found_updated);
+		assert!(job_handle.get_rejected_solutions(0).unwrap() == 0);
+		job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+		assert!(job_handle.update_job(3, &new_pre_header, common::SAMPLE_GRIN_POST_HEADER_1, 0).is_err());
+	}
+}
+
+//changes a parameter while mining
+#[test]
+fn on_commit_set_parameter_async() {
+	let caps = common::get_plugin_vec("mean_cpu_16");
+	for c in &caps {
+		let mut config = CuckooMinerConfig::new();
+		config.plugin_full_path = c.full_path.clone();
+		config.parameter_list = vec![(String::from("NUM_THREADS"), 0, 1)];
+		let miner = CuckooMiner::new(vec![config]).expect("");
+		let job_handle = miner.notify(1, common::SAMPLE_GRIN_PRE_HEADER_1,
+			common::SAMPLE_GRIN_POST_HEADER_1, 0, false, Box::new(SequentialNonce::new(0))).unwrap();
+		std::thread::sleep(Duration::from_secs(2));
+
+		job_handle.set_parameter("NUM_THREADS", 0, 2, 0, Duration::from_secs(10)).unwrap();
+		assert_eq!(job_handle.get_parameter("NUM_THREADS", 0, 0).unwrap(), 2);
+		assert!(job_handle.set_parameter("NUM_SANDWICHES", 0, 2, 0, Duration::from_secs(10)).is_err());
+		assert!(job_handle.set_parameter("NUM_THREADS", 0, 2, 1, Duration::from_secs(10)).is_err());
+
+		// still mining after the change
+		let solution = job_handle.wait_for_solution(Duration::from_secs(10));
+		assert!(solution.is_some());
+		assert!(job_handle.get_rejected_solutions(0).unwrap() == 0);
+		job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	}
+}
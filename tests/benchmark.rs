@@ -0,0 +1,55 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the benchmark report with plugins that can't be run, which
+//! don't require any plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+extern crate serde_json;
+
+use cuckoo::{CuckooBenchmark, CuckooBenchmarkReport, CuckooMinerConfig, CuckooPluginCapabilities};
+
+#[test]
+fn on_commit_benchmark_failed_plugins() {
+	let mut config = CuckooMinerConfig::new();
+	config.plugin_full_path = String::from("/no/such/dir/lean_cpu_16.cuckooplugin");
+	let caps = CuckooPluginCapabilities {
+		full_path: String::from("/plugins/cuckatoo_mean_cpu_29.cuckooplugin"),
+		runnable: false,
+		not_runnable_reason: Some(String::from("Requires a CPU with AVX2")),
+		..Default::default()
+	};
+
+	let benchmark = CuckooBenchmark::new().headers(3).seed(7).add_config(config).add_plugin(&caps);
+	// headers are repeatable, and differ by seed
+	let headers = benchmark.benchmark_headers();
+	assert_eq!(headers.len(), 3);
+	assert_eq!(headers, benchmark.benchmark_headers());
+	assert!(headers[0] != headers[1]);
+	assert!(headers[0] != CuckooBenchmark::new().seed(8).benchmark_headers()[0]);
+
+	let report = benchmark.run();
+	assert_eq!(report.seed, 7);
+	assert_eq!(report.headers, 3);
+	assert!(report.best().is_none());
+	assert_eq!(report.results.len(), 2);
+	assert_eq!(report.results[0].plugin_name, "lean_cpu_16");
+	assert!(report.results[0].error.as_ref().unwrap().contains("lean_cpu_16"));
+	assert_eq!(report.results[1].plugin_name, "cuckatoo_mean_cpu_29");
+	assert_eq!(report.results[1].error, caps.not_runnable_reason);
+
+	let json = report.to_json().unwrap();
+	let parsed: CuckooBenchmarkReport = serde_json::from_str(&json).unwrap();
+	assert_eq!(parsed, report);
+}
@@ -0,0 +1,99 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the standalone binary's config handling, which don't
+//! require any plugins to be built
+
+#![cfg(feature = "binary")]
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+// A temp directory holding a config file, deleted when dropped
+struct ConfigDir(PathBuf);
+
+impl ConfigDir {
+	fn config_path(&self) -> PathBuf {
+		self.0.join("cuckoo-miner.toml")
+	}
+}
+
+impl Drop for ConfigDir {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.0);
+	}
+}
+
+// Writes a config file to a fresh temp directory
+fn write_config(test_name: &str, contents: &str) -> ConfigDir {
+	let mut dir = env::temp_dir();
+	dir.push(format!("cuckoo-miner-{}-{}", test_name, std::process::id()));
+	fs::create_dir_all(dir.join("plugins")).unwrap();
+	let dir = ConfigDir(dir);
+	File::create(dir.config_path())
+		.unwrap()
+		.write_all(contents.as_bytes())
+		.unwrap();
+	dir
+}
+
+// Runs the binary, returning whether it succeeded and its stderr
+fn run_miner(config_path: &PathBuf) -> (bool, String) {
+	let output = Command::new(env!("CARGO_BIN_EXE_cuckoo-miner"))
+		.arg(config_path)
+		.current_dir(config_path.parent().unwrap())
+		.output()
+		.unwrap();
+	(output.status.success(), String::from_utf8_lossy(&output.stderr).into_owned())
+}
+
+#[test]
+fn on_commit_binary_missing_config() {
+	let path = env::temp_dir().join("cuckoo-miner-no-such-config.toml");
+	let (success, stderr) = run_miner(&path);
+	assert!(!success);
+	assert!(stderr.contains("Error reading config file"));
+}
+
+#[test]
+fn on_commit_binary_invalid_config() {
+	// no pool given
+	let dir = write_config("invalid", "[[plugin]]\nname = \"lean_cpu_16\"\n");
+	let (success, stderr) = run_miner(&dir.config_path());
+	assert!(!success);
+	assert!(stderr.contains("missing field `pool`"));
+
+	// no plugins given
+	let dir = write_config("no-plugins", "plugin = []\n[pool]\naddress = \"127.0.0.1:3416\"\n");
+	let (success, stderr) = run_miner(&dir.config_path());
+	assert!(!success);
+	assert!(stderr.contains("No plugins given"));
+}
+
+#[test]
+fn on_commit_binary_empty_plugin_dir() {
+	let dir = write_config(
+		"empty-plugin-dir",
+		"plugin_dir = \"plugins\"\n\
+		 [pool]\naddress = \"127.0.0.1:3416\"\n\
+		 [[plugin]]\nname = \"lean_cpu_16\"\n\
+		 parameters = [{ name = \"NUM_THREADS\", value = 1 }]\n",
+	);
+	let (success, stderr) = run_miner(&dir.config_path());
+	assert!(!success);
+	assert!(stderr.contains("Error loading plugins"));
+}
@@ -30,7 +30,8 @@ use self::cuckoo::{CuckooPluginManager,
 	CuckooPluginCapabilities,
 	CuckooMinerSolution,
 	CuckooMinerConfig,
-	CuckooMiner};
+	CuckooMiner,
+	RandomNonce};
 
 // Encode the provided bytes into a hex string
 pub fn to_hex(bytes: Vec<u8>) -> String {
@@ -224,10 +225,11 @@ pub fn mine_async_for_duration(full_paths: Vec<&str>, duration_in_seconds: i64,
 
 		// these always get consumed after a notify
 		let miner = CuckooMiner::new(config_vec.clone()).expect("");
-		let job_handle = miner.notify(1, SAMPLE_GRIN_PRE_HEADER_1, SAMPLE_GRIN_POST_HEADER_1, 0, false).unwrap();
+		let job_handle = miner.notify(1, SAMPLE_GRIN_PRE_HEADER_1, SAMPLE_GRIN_POST_HEADER_1, 0, false,
+			Box::new(RandomNonce::new())).unwrap();
 
 		loop {
-			if let Some(s) = job_handle.get_solution() {
+			if let Some(s) = job_handle.wait_for_solution(std::time::Duration::from_millis(100)) {
 				println!("Sol found: {}, {:?}", s.get_nonce_as_u64(), s);
 				// up to you to read it and check difficulty
 				continue;
@@ -239,6 +241,8 @@ pub fn mine_async_for_duration(full_paths: Vec<&str>, duration_in_seconds: i64,
 					if let Err(e) = stats_vec {
 						panic!("Error getting stats: {:?}", e);
 					}
+					//plugins should never return invalid cycles
+					assert!(job_handle.get_rejected_solutions(index).unwrap() == 0);
 					for s in stats_vec.unwrap().into_iter() {
 						if s.in_use == 0 {continue;}
 						let status = match s.has_errored {
@@ -270,17 +274,13 @@ pub fn mine_async_for_duration(full_paths: Vec<&str>, duration_in_seconds: i64,
 					println!("More time needed");
 				} else {
 					println!("Stopping jobs and waiting for cleanup");
-					job_handle.stop_jobs();
+					job_handle.stop_jobs(std::time::Duration::from_secs(30)).unwrap();
 					break;
 				}
 			}
 			if stats_updated && extra_time {
 				break;
 			}
-			//avoid busy wait 
-			let sleep_dur = std::time::Duration::from_millis(100);
-			std::thread::sleep(sleep_dur);
-
 		}
 		if stats_updated && extra_time {
 			break;
@@ -0,0 +1,119 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the error type, which don't require any plugins to be built.
+//! Loading a plugin which reports too long a proof requires a C compiler
+
+extern crate cuckoo_miner as cuckoo;
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::process::Command;
+
+use cuckoo::{CuckooMinerError, PluginLibrary, PluginReturnCode, MAX_PROOF_SIZE, PLUGIN_ABI_VERSION};
+
+#[test]
+fn on_commit_error_return_codes() {
+	for code in 1..7 {
+		assert_eq!(PluginReturnCode::from_code(code).code(), code);
+	}
+	assert_eq!(PluginReturnCode::from_code(2), PluginReturnCode::OutOfRange);
+	assert_eq!(PluginReturnCode::from_code(6), PluginReturnCode::Unknown(6));
+
+	let e = CuckooMinerError::plugin_call(
+		"/plugins/lean_cpu_16.cuckooplugin",
+		"cuckoo_set_parameter",
+		2,
+		String::from("setting NUM_THREADS to 0 on device 0"),
+	);
+	match e {
+		CuckooMinerError::PluginCallError { ref code, .. } => {
+			assert_eq!(*code, PluginReturnCode::OutOfRange)
+		}
+		ref e => panic!("Unexpected error {:?}", e),
+	}
+	let message = e.to_string();
+	assert!(message.contains("lean_cpu_16"));
+	assert!(message.contains("cuckoo_set_parameter"));
+	assert!(message.contains("NUM_THREADS"));
+	assert!(message.contains("code 2"));
+	assert!(e.source().is_none());
+}
+
+#[test]
+fn on_commit_error_plugin_not_found() {
+	let path = "/no/such/dir/lean_cpu_16.cuckooplugin";
+	match PluginLibrary::new(path) {
+		Err(e) => {
+			match e {
+				CuckooMinerError::PluginNotFoundError { ref plugin, .. } => assert_eq!(plugin, path),
+				ref e => panic!("Unexpected error {:?}", e),
+			}
+			assert!(e.to_string().contains(path));
+			// the loader's error is kept
+			assert!(e.source().is_some());
+		}
+		Ok(_) => panic!("Loaded a plugin that doesn't exist"),
+	}
+}
+
+#[test]
+fn on_commit_error_abi_version() {
+	let e = CuckooMinerError::PluginAbiError {
+		plugin: String::from("/plugins/cuckatoo_lean_cpu_29.cuckooplugin"),
+		expected: PLUGIN_ABI_VERSION,
+		found: PLUGIN_ABI_VERSION + 1,
+	};
+	// both versions are named
+	let message = e.to_string();
+	assert!(message.contains("cuckatoo_lean_cpu_29"));
+	assert!(message.contains(&format!("version {}", PLUGIN_ABI_VERSION)));
+	assert!(message.contains(&format!("version {}", PLUGIN_ABI_VERSION + 1)));
+}
+
+#[test]
+fn on_commit_error_proof_size() {
+	// a plugin reporting too long a proof is refused before anything
+	// else it exports is looked at
+	let mut dir = env::temp_dir();
+	dir.push(format!("cuckoo-miner-proof-size-{}", std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	let source = dir.join("plugin.c");
+	File::create(&source)
+		.unwrap()
+		.write_all(format!("unsigned int cuckoo_proof_size() {{ return {}; }}\n", MAX_PROOF_SIZE + 2).as_bytes())
+		.unwrap();
+	let path = dir.join("lean_cpu_16.cuckooplugin");
+	let status = Command::new("cc")
+		.arg("-shared")
+		.arg("-fPIC")
+		.arg("-o")
+		.arg(&path)
+		.arg(&source)
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	let result = PluginLibrary::new(path.to_str().unwrap());
+	fs::remove_dir_all(&dir).unwrap();
+	match result {
+		Err(CuckooMinerError::PluginIOError(e)) => {
+			assert!(e.contains(&format!("proof size {}", MAX_PROOF_SIZE + 2)))
+		}
+		r => panic!("Unexpected result {:?}", r.map(|_| ())),
+	}
+}
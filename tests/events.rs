@@ -0,0 +1,149 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the events sent by a running job, using the mock backend,
+//! which don't require any plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+use cuckoo::{CuckooMiner, CuckooMinerEvent, MockBackend, SequentialNonce};
+
+// Known 42-cycle in the cuckoo 16 graph for a header of [82, 0, 0 ...]
+const KNOWN_CUCKOO_16_SOLUTION: [u32; 42] = [
+	1098, 1266, 1304, 1898, 2944, 3929, 4024, 4174, 4706, 5257, 5602, 5684, 5719, 5864,
+	6445, 7886, 7972, 10610, 11190, 13554, 14697, 16794, 17162, 18144, 18400, 19177, 20566,
+	22940, 23504, 24408, 26107, 26388, 27638, 28084, 29463, 29628, 29946, 30242, 32381,
+	32450, 32578, 32584,
+];
+
+// The header the known solution is for, split around a zero nonce
+const PRE_NONCE: &str = "52";
+const POST_NONCE: &str = "0000000000000000000000000000000000000000000000";
+
+fn header() -> [u8; 32] {
+	let mut header = [0; 32];
+	header[0] = 82;
+	header
+}
+
+// Receives events until one matches, returning those received
+fn wait_for<F>(events: &Receiver<CuckooMinerEvent>, mut matches: F) -> Vec<CuckooMinerEvent>
+where
+	F: FnMut(&CuckooMinerEvent) -> bool,
+{
+	let mut received = Vec::new();
+	loop {
+		let e = events.recv_timeout(Duration::from_secs(10)).unwrap();
+		let done = matches(&e);
+		received.push(e);
+		if done {
+			return received;
+		}
+	}
+}
+
+#[test]
+fn on_commit_events_job_lifecycle() {
+	let backend = MockBackend::new("mock_cpu_16").solution_for(&header(), &KNOWN_CUCKOO_16_SOLUTION);
+	let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+	let events = miner.events();
+	let job_handle = miner
+		.notify(7, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+	let later_events = job_handle.events();
+
+	match events.recv_timeout(Duration::from_secs(10)).unwrap() {
+		CuckooMinerEvent::JobStarted { job_id } => assert_eq!(job_id, 7),
+		e => panic!("Unexpected event {:?}", e),
+	}
+	let received = wait_for(&events, |e| matches!(*e, CuckooMinerEvent::SolutionFound { .. }));
+	match received[received.len() - 1] {
+		CuckooMinerEvent::SolutionFound { plugin_index, ref solution } => {
+			assert_eq!(plugin_index, 0);
+			assert_eq!(solution.job_id, 7);
+			assert!(solution.verify(&header()).is_ok());
+		}
+		_ => unreachable!(),
+	}
+	wait_for(&events, |e| match *e {
+		CuckooMinerEvent::StatsUpdated { plugin_index, ref stats } => {
+			plugin_index == 0 && stats[0].iterations_completed > 0
+		}
+		_ => false,
+	});
+
+	job_handle.update_job(8, PRE_NONCE, "", 0).unwrap();
+	wait_for(&events, |e| match *e {
+		CuckooMinerEvent::JobStarted { job_id } => job_id == 8,
+		_ => false,
+	});
+
+	// the last event says why the job stopped, then the channel closes
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	for rx in &[events, later_events] {
+		let received: Vec<CuckooMinerEvent> = rx.iter().collect();
+		match received[received.len() - 1] {
+			CuckooMinerEvent::JobStopped { job_id, ref error } => {
+				assert_eq!(job_id, 8);
+				assert!(error.is_none());
+			}
+			ref e => panic!("Unexpected event {:?}", e),
+		}
+	}
+	assert!(job_handle.events().recv().is_err());
+}
+
+#[test]
+fn on_commit_events_failures() {
+	let backend = MockBackend::new("mock_cpu_16").device_errored().start_error(5);
+	let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+	let events = miner.events();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+
+	match events.recv_timeout(Duration::from_secs(10)).unwrap() {
+		CuckooMinerEvent::PluginFailed { plugin_index, ref error } => {
+			assert_eq!(plugin_index, 0);
+			assert!(error.contains("cuckoo_start_processing"));
+		}
+		e => panic!("Unexpected event {:?}", e),
+	}
+
+	// the errored device is reported once, however often stats are read
+	let mut stats_updates = 0;
+	let received = wait_for(&events, |e| match *e {
+		CuckooMinerEvent::StatsUpdated { .. } => {
+			stats_updates += 1;
+			stats_updates == 2
+		}
+		_ => false,
+	});
+	let errored: Vec<&CuckooMinerEvent> = received
+		.iter()
+		.filter(|e| matches!(**e, CuckooMinerEvent::DeviceErrored { .. }))
+		.collect();
+	assert_eq!(errored.len(), 1);
+	match *errored[0] {
+		CuckooMinerEvent::DeviceErrored { plugin_index, ref stats } => {
+			assert_eq!(plugin_index, 0);
+			assert_eq!(stats.has_errored, 1);
+		}
+		_ => unreachable!(),
+	}
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+}
@@ -0,0 +1,206 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for plugins loaded in a separate host process. Only the
+//! mining test requires plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+pub mod common;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use cuckoo::{CuckooMiner, CuckooMinerConfig, CuckooMinerError, CuckooMinerSolution, IsolatedPlugin,
+	PluginReturnCode, SolverBackend};
+
+fn host_path() -> &'static Path {
+	Path::new(env!("CARGO_BIN_EXE_cuckoo-plugin-host"))
+}
+
+// A fake host which loads any plugin and succeeds at its first request,
+// crashes on its second, then returns the given code for everything
+// once restarted. Each host records the requests it receives in the
+// test's directory
+#[cfg(unix)]
+fn crashing_host(test_name: &str, restarted_code: u32) -> PathBuf {
+	fake_host(
+		test_name,
+		&format!(
+			r#"if [ ! -e {{dir}}/started ]; then
+	touch {{dir}}/started
+	read line
+	echo "$line" >> {{dir}}/first-host
+	echo '{{"Code":0}}'
+	read line
+	echo "$line" >> {{dir}}/first-host
+	exit 1
+fi
+while read line; do
+	echo "$line" >> {{dir}}/second-host
+	echo '{{"Code":{}}}'
+done
+"#,
+			restarted_code
+		),
+	)
+}
+
+// A fake host which loads any plugin, then runs the given script, with
+// `{dir}` replaced by the test's directory
+#[cfg(unix)]
+fn fake_host(test_name: &str, requests: &str) -> PathBuf {
+	use std::os::unix::fs::PermissionsExt;
+
+	let mut dir = env::temp_dir();
+	dir.push(format!("cuckoo-miner-{}-{}", test_name, std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("host.sh");
+	let script = format!(
+		r#"#!/bin/sh
+read line
+echo '{{"Loaded":{{"proof_size":42,"metadata":{{"name":"fake","version":null,"abi_version":null,"graph_type":"Cuckoo","edge_bits":null,"device_type":"Cpu","memory_bytes":null,"reported":false}},"exports":[]}}}}'
+{}"#,
+		requests.replace("{dir}", &dir.display().to_string())
+	);
+	File::create(&path)
+		.unwrap()
+		.write_all(script.as_bytes())
+		.unwrap();
+	fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+	path
+}
+
+#[test]
+fn on_commit_isolated_load_errors() {
+	// the host starts, but can't load the plugin
+	match IsolatedPlugin::with_host(host_path(), "/nonexistent/lean_cpu_16.cuckooplugin") {
+		Err(CuckooMinerError::PluginIOError(e)) => assert!(e.contains("lean_cpu_16")),
+		r => panic!("Unexpected result {:?}", r.map(|_| ())),
+	}
+
+	// the host exits without answering
+	match IsolatedPlugin::with_host(Path::new("/bin/true"), "lean_cpu_16.cuckooplugin") {
+		Err(CuckooMinerError::IoError { context, .. }) => assert!(context.contains("exited")),
+		r => panic!("Unexpected result {:?}", r.map(|_| ())),
+	}
+
+	// the host doesn't exist
+	match IsolatedPlugin::with_host(Path::new("/nonexistent/host"), "lean_cpu_16.cuckooplugin") {
+		Err(CuckooMinerError::IoError { context, .. }) => assert!(context.contains("starting")),
+		r => panic!("Unexpected result {:?}", r.map(|_| ())),
+	}
+
+	let mut config = CuckooMinerConfig::new();
+	config.plugin_full_path = String::from("/nonexistent/lean_cpu_16.cuckooplugin");
+	config.isolate_plugin = true;
+	assert!(CuckooMiner::new(vec![config]).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn on_commit_isolated_restart() {
+	let host = crashing_host("isolated-restart", 0);
+	let plugin = IsolatedPlugin::with_host(&host, "fake.cuckooplugin").unwrap();
+	assert_eq!(plugin.proof_size(), 42);
+	assert!(!plugin.exports("cuckoo_get_stats"));
+	let first_host = plugin.host_id().unwrap();
+	assert_eq!(plugin.set_parameter("NUM_THREADS", 0, 2), 0);
+
+	// the call that crashed the host fails, and a new host is started
+	// with the parameter set again
+	assert_eq!(plugin.start_processing(), PluginReturnCode::HostUnavailable.code());
+	let second_host = plugin.host_id().unwrap();
+	assert!(first_host != second_host);
+	assert_eq!(plugin.start_processing(), 0);
+	assert_eq!(plugin.host_id(), Some(second_host));
+	drop(plugin);
+
+	let dir = host.parent().unwrap();
+	let requests = |file: &str| -> Vec<String> {
+		fs::read_to_string(dir.join(file)).unwrap().lines().map(String::from).collect()
+	};
+	let first = requests("first-host");
+	let second = requests("second-host");
+	assert_eq!(first.len(), 2);
+	assert_eq!(second.len(), 2);
+	for r in &[&first[0], &second[0]] {
+		assert!(r.contains("SetParameter") && r.contains("NUM_THREADS") && r.contains("\"value\":2"));
+	}
+	assert!(first[1].contains("StartProcessing"));
+	assert!(second[1].contains("StartProcessing"));
+	fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn on_commit_isolated_restart_failed() {
+	// the restarted host refuses the parameter set again, so isn't used
+	let host = crashing_host("isolated-restart-failed", 2);
+	let plugin = IsolatedPlugin::with_host(&host, "fake.cuckooplugin").unwrap();
+	assert_eq!(plugin.set_parameter("NUM_THREADS", 0, 2), 0);
+	assert_eq!(plugin.start_processing(), PluginReturnCode::HostUnavailable.code());
+	assert_eq!(plugin.host_id(), None);
+	drop(plugin);
+	fs::remove_dir_all(host.parent().unwrap()).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn on_commit_isolated_request_timeout() {
+	// a host which hangs is killed, and a new one started
+	let host = fake_host("isolated-request-timeout", "read line\nexec sleep 60\n");
+	let plugin = IsolatedPlugin::with_host(&host, "fake.cuckooplugin").unwrap();
+	plugin.set_request_timeout(Duration::from_millis(200));
+	let first_host = plugin.host_id().unwrap();
+	let start = Instant::now();
+	assert_eq!(plugin.start_processing(), PluginReturnCode::HostUnavailable.code());
+	assert!(start.elapsed() < Duration::from_secs(10));
+	assert!(plugin.host_id().is_some_and(|h| h != first_host));
+	drop(plugin);
+	fs::remove_dir_all(host.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn on_commit_isolated_mine() {
+	let caps = common::get_plugin_vec("lean_cpu_16");
+	for c in &caps {
+		let plugin = IsolatedPlugin::with_host(host_path(), &c.full_path).unwrap();
+		assert_eq!(plugin.metadata().name, c.metadata.name);
+		assert!(plugin.parameter_list().unwrap().contains("NUM_THREADS"));
+		assert!(plugin.host_id().is_some());
+
+		let mut config = CuckooMinerConfig::new();
+		config.plugin_full_path = c.full_path.clone();
+		config.isolate_plugin = true;
+		let miner = CuckooMiner::new(vec![config]).unwrap();
+		let mut cuckoo_size = 0;
+		let mut found = false;
+		for _ in 0..20 {
+			let header = common::get_random_hash();
+			let mut solution = CuckooMinerSolution::new();
+			if miner.mine(&header, &mut cuckoo_size, &mut solution, 0).unwrap() {
+				solution.verify(&header).unwrap();
+				found = true;
+				break;
+			}
+		}
+		assert!(found);
+		assert!(!miner.get_stats(0).unwrap().is_empty());
+	}
+}
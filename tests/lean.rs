@@ -0,0 +1,167 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the built-in pure-Rust lean solver, which don't require any
+//! plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use std::thread;
+use std::time::Duration;
+
+use cuckoo::{CuckooGraphType, CuckooMiner, CuckooMinerConfig, CuckooMinerError, CuckooMinerEvent,
+	CuckooMinerSolution, CuckooPluginFilter, CuckooPluginManager, LeanSolver, SequentialNonce, SolverBackend};
+
+// Known 42-cycle in the cuckoo 16 graph for a header of [82, 0, 0 ...]
+const KNOWN_CUCKOO_16_SOLUTION: [u32; 42] = [
+	1098, 1266, 1304, 1898, 2944, 3929, 4024, 4174, 4706, 5257, 5602, 5684, 5719, 5864,
+	6445, 7886, 7972, 10610, 11190, 13554, 14697, 16794, 17162, 18144, 18400, 19177, 20566,
+	22940, 23504, 24408, 26107, 26388, 27638, 28084, 29463, 29628, 29946, 30242, 32381,
+	32450, 32578, 32584,
+];
+
+// Known 42-cycle in the cuckatoo 19 graph for a header of [173, 0, 0 ...]
+const KNOWN_CUCKATOO_19_SOLUTION: [u32; 42] = [
+	19497, 51968, 58590, 67351, 82810, 84585, 117561, 122625, 127842, 139560, 141701,
+	144740, 146394, 149872, 153869, 166212, 176324, 178263, 210143, 231322, 256752, 262663,
+	267332, 273421, 305377, 320805, 349850, 359284, 375290, 387176, 398365, 405738, 409739,
+	451446, 452140, 461520, 462310, 463232, 480261, 485965, 502754, 515928,
+];
+
+// Known 12-cycle in the cuckoo 16 graph for a header of [7, 0, 0 ...]
+const KNOWN_CUCKOO_16_12_CYCLE: [u32; 12] = [
+	2607, 10941, 12879, 13928, 14074, 14811, 15994, 20416, 20593, 20841, 22457, 30878,
+];
+
+fn header(first: u8) -> [u8; 32] {
+	let mut header = [0; 32];
+	header[0] = first;
+	header
+}
+
+#[test]
+fn on_commit_lean_known_cycles() {
+	let solver = LeanSolver::new("builtin:lean_rust_cpu_16").unwrap();
+	assert_eq!(solver.metadata().graph_type, CuckooGraphType::Cuckoo);
+	assert_eq!(solver.metadata().edge_bits, Some(15));
+	assert!(solver.find_cycles(&header(82)).contains(&KNOWN_CUCKOO_16_SOLUTION.to_vec()));
+
+	let solver = LeanSolver::new("builtin:cuckatoo_lean_rust_cpu_19").unwrap();
+	assert_eq!(solver.metadata().graph_type, CuckooGraphType::Cuckatoo);
+	assert_eq!(solver.metadata().edge_bits, Some(19));
+	assert!(solver.find_cycles(&header(173)).contains(&KNOWN_CUCKATOO_19_SOLUTION.to_vec()));
+
+	let solver = LeanSolver::new("builtin:lean_rust_cpu_16").unwrap().proof_size(12);
+	assert!(solver.find_cycles(&header(7)).contains(&KNOWN_CUCKOO_16_12_CYCLE.to_vec()));
+
+	for path in &["lean_rust_cpu_16", "builtin:lean_rust_cpu", "builtin:lean_rust_cpu_30"] {
+		match LeanSolver::new(path) {
+			Err(CuckooMinerError::PluginNotLoadedError(_)) => {}
+			r => panic!("Unexpected result for {}: {:?}", path, r.map(|_| ())),
+		}
+	}
+}
+
+#[test]
+fn on_commit_lean_manager() {
+	let mut manager = CuckooPluginManager::new().unwrap();
+	manager.load_builtin_solvers().unwrap();
+	let caps = manager.get_available_plugins("lean_rust").unwrap();
+	assert_eq!(caps.len(), 2);
+	assert!(caps.iter().all(|c| c.runnable && c.memory_estimate.is_some()));
+	assert!(caps[0].parameters.iter().any(|p| p.name == "NUM_THREADS"));
+
+	let filter = CuckooPluginFilter::new().graph_type(CuckooGraphType::Cuckatoo).edge_bits(19);
+	let caps = manager.find_plugins(&filter).unwrap();
+	assert_eq!(caps[0].full_path, "builtin:cuckatoo_lean_rust_cpu_19");
+	assert_eq!(manager.recommend_plugin(15).unwrap().full_path, "builtin:lean_rust_cpu_16");
+
+	// loading twice doesn't list them twice
+	manager.load_builtin_solvers().unwrap();
+	assert_eq!(manager.get_available_plugins("").unwrap().len(), 2);
+}
+
+#[test]
+fn on_commit_lean_mine_sync() {
+	let mut config = CuckooMinerConfig::new();
+	config.plugin_full_path = String::from("builtin:cuckatoo_lean_rust_cpu_19");
+	let miner = CuckooMiner::new(vec![config]).unwrap();
+	let mut cuckoo_size = 0;
+	let mut solution = CuckooMinerSolution::new();
+	assert!(miner.mine(&header(173), &mut cuckoo_size, &mut solution, 0).unwrap());
+	assert_eq!(cuckoo_size, 19);
+	assert!(solution.verify(&header(173)).is_ok());
+	assert_eq!(miner.get_stats(0).unwrap()[0].iterations_completed, 1);
+
+	let mut config = CuckooMinerConfig::new();
+	config.plugin_full_path = String::from("builtin:lean_rust_cpu_16");
+	config.parameter_list = vec![(String::from("NUM_THREADS"), 0, 100)];
+	assert!(CuckooMiner::new(vec![config]).is_err());
+}
+
+#[test]
+fn on_commit_lean_mine_async() {
+	let mut config = CuckooMinerConfig::new();
+	config.plugin_full_path = String::from("builtin:lean_rust_cpu_16");
+	config.parameter_list = vec![(String::from("NUM_THREADS"), 0, 2)];
+	let miner = CuckooMiner::new(vec![config]).unwrap();
+	let job_handle = miner
+		.notify(3, "52", "", 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+
+	// about one in five cuckoo 16 graphs has a 42-cycle
+	let solution = job_handle.wait_for_solution(Duration::from_secs(120)).unwrap();
+	assert_eq!(solution.job_id, 3);
+	assert_eq!(solution.cuckoo_size, 16);
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	assert_eq!(job_handle.get_rejected_solutions(0).unwrap(), 0);
+	assert!(job_handle.get_stats(0).unwrap()[0].iterations_completed > 0);
+}
+
+#[test]
+fn on_commit_lean_solutions_in_order() {
+	// a single thread solves the headers in the order they're queued
+	let mut config = CuckooMinerConfig::new();
+	config.plugin_full_path = String::from("builtin:lean_rust_cpu_16");
+	let miner = CuckooMiner::new(vec![config]).unwrap();
+	let events = miner.events();
+	let job_handle = miner
+		.notify(1, "52", "", 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+
+	let job_handle = &job_handle;
+	let (found, solutions) = thread::scope(|s| {
+		let stopper = s.spawn(move || {
+			let mut found = Vec::new();
+			while found.len() < 3 {
+				if let CuckooMinerEvent::SolutionFound { solution, .. } =
+					events.recv_timeout(Duration::from_secs(120)).unwrap()
+				{
+					found.push(solution.get_nonce_as_u64());
+				}
+			}
+			job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+			found
+		});
+		// ends once the job has stopped and the queue is empty
+		let solutions: Vec<u64> = job_handle.solutions().map(|s| s.get_nonce_as_u64()).collect();
+		(stopper.join().unwrap(), solutions)
+	});
+
+	// solutions come out oldest first
+	assert!(solutions.len() >= 3);
+	assert_eq!(&solutions[..3], &found[..]);
+	assert!(solutions.windows(2).all(|w| w[0] < w[1]));
+	assert!(job_handle.solutions().next().is_none());
+}
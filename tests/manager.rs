@@ -0,0 +1,196 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for plugin discovery with missing or broken plugins, which
+//! don't require any plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use cuckoo::{CuckooMinerError, CuckooPluginDeviceType, CuckooPluginFilter, CuckooPluginManager,
+             HostCapabilities};
+
+// A temp directory, deleted when dropped so it's cleaned up even when
+// the test fails
+struct TempDir(PathBuf);
+
+impl TempDir {
+	fn path(&self) -> String {
+		String::from(self.0.to_str().unwrap())
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.0);
+	}
+}
+
+// Creates a fresh temp directory containing the given files
+fn plugin_dir(test_name: &str, files: &[(&str, &[u8])]) -> TempDir {
+	let mut dir = env::temp_dir();
+	dir.push(format!("cuckoo-miner-{}-{}", test_name, std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	fs::create_dir_all(&dir).unwrap();
+	for &(name, contents) in files {
+		File::create(dir.join(name))
+			.unwrap()
+			.write_all(contents)
+			.unwrap();
+	}
+	TempDir(dir)
+}
+
+#[test]
+fn on_commit_manager_not_loaded() {
+	let mut plugin_manager = CuckooPluginManager::new().unwrap();
+	match plugin_manager.get_available_plugins("") {
+		Err(CuckooMinerError::PluginNotLoadedError(_)) => {}
+		r => panic!("Unexpected result {:?}", r),
+	}
+	match plugin_manager.get_available_plugins("lean_cpu_(") {
+		Err(CuckooMinerError::PluginFilterError { ref filter, .. }) => assert_eq!(filter, "lean_cpu_("),
+		r => panic!("Unexpected result {:?}", r),
+	}
+}
+
+#[test]
+fn on_commit_manager_broken_plugins() {
+	let dir = plugin_dir(
+		"broken-plugins",
+		&[
+			("lean_cpu_16.cuckooplugin", b"not a shared library"),
+			("mean_cpu_16.cuckooplugin", b""),
+			("README.txt", b"not a plugin"),
+		],
+	);
+	let mut plugin_manager = CuckooPluginManager::new().unwrap();
+	match plugin_manager.load_plugin_dir(dir.path()) {
+		Err(CuckooMinerError::NoPluginsFoundError(e)) => assert!(e.contains("2 skipped")),
+		r => panic!("Unexpected result {:?}", r),
+	}
+
+	let load_errors = plugin_manager.get_load_errors();
+	assert_eq!(load_errors.len(), 2);
+	assert!(load_errors[0].full_path.ends_with("lean_cpu_16.cuckooplugin"));
+	assert!(load_errors[1].full_path.ends_with("mean_cpu_16.cuckooplugin"));
+	for e in load_errors {
+		match e.error {
+			CuckooMinerError::PluginNotFoundError { .. } => {}
+			ref e => panic!("Unexpected error {:?}", e),
+		}
+	}
+
+	// nothing was loaded, so there's nothing to return
+	assert!(plugin_manager.get_available_plugins("").is_err());
+}
+
+#[test]
+fn on_commit_manager_unrunnable_plugins() {
+	// neither plugin is a real library, but they're never loaded
+	let dir = plugin_dir(
+		"unrunnable-plugins",
+		&[
+			("cuckatoo_mean_cpu_29.cuckooplugin", b"not a shared library"),
+			("cuckatoo_cuda_29.cuckooplugin", b"not a shared library"),
+		],
+	);
+	let mut plugin_manager = CuckooPluginManager::new().unwrap();
+	let mut host = plugin_manager.get_host_capabilities().clone();
+	host.avx2 = false;
+	host.cuda = false;
+	plugin_manager.set_host_capabilities(host.clone());
+	plugin_manager
+		.load_plugin_dir(dir.path())
+		.unwrap();
+	assert!(plugin_manager.get_load_errors().is_empty());
+
+	let caps = plugin_manager
+		.find_plugins(&CuckooPluginFilter::new().runnable(false))
+		.unwrap();
+	assert_eq!(caps.len(), 2);
+	for c in &caps {
+		assert!(!c.runnable);
+		assert!(c.parameters.is_empty());
+		let reason = c.not_runnable_reason.as_ref().unwrap();
+		if c.metadata.device_type == CuckooPluginDeviceType::Cuda {
+			assert!(reason.contains("NVIDIA"));
+		} else {
+			assert!(reason.contains("AVX2"));
+		}
+	}
+	assert!(plugin_manager.recommend_plugin(29).is_err());
+
+	// with AVX2, the mean_cpu plugin is loaded, and found to be broken
+	host.avx2 = true;
+	plugin_manager.set_host_capabilities(host);
+	plugin_manager
+		.load_plugin_dir(dir.path())
+		.unwrap();
+	assert_eq!(plugin_manager.get_load_errors().len(), 1);
+	assert!(plugin_manager.get_load_errors()[0].full_path.contains("mean_cpu"));
+}
+
+#[test]
+fn on_commit_manager_insufficient_memory() {
+	// lean_cpu_29 needs about 192 MiB, so it's never loaded
+	let dir = plugin_dir(
+		"insufficient-memory",
+		&[("cuckatoo_lean_cpu_29.cuckooplugin", b"not a shared library")],
+	);
+	let mut plugin_manager = CuckooPluginManager::new().unwrap();
+	let mut host = plugin_manager.get_host_capabilities().clone();
+	host.available_memory = Some(64 << 20);
+	plugin_manager.set_host_capabilities(host);
+	plugin_manager
+		.load_plugin_dir(dir.path())
+		.unwrap();
+	assert!(plugin_manager.get_load_errors().is_empty());
+
+	let caps = plugin_manager.get_available_plugins("").unwrap();
+	assert_eq!(caps.len(), 1);
+	assert!(!caps[0].runnable);
+	assert!(caps[0].not_runnable_reason.as_ref().unwrap().contains("memory"));
+	assert!(plugin_manager.recommend_plugin(29).is_err());
+
+	// the built-in solvers fit, so are still recommended
+	plugin_manager.load_builtin_solvers().unwrap();
+	assert!(plugin_manager.recommend_plugin(15).unwrap().runnable);
+}
+
+#[test]
+fn on_commit_manager_cpu_cores() {
+	let mut plugin_manager = CuckooPluginManager::new().unwrap();
+	let host = plugin_manager.get_host_capabilities().clone();
+	assert!(host.cpu_cores >= 1);
+	#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+	assert_eq!(host.sse4_1, is_x86_feature_detected!("sse4.1"));
+	// every CPU with AVX2 also has SSE4.1
+	assert!(!host.avx2 || host.sse4_1);
+
+	// the built-in solvers take a thread per core, up to their maximum
+	plugin_manager.load_builtin_solvers().unwrap();
+	let caps = plugin_manager.recommend_plugin(15).unwrap();
+	for &(cores, threads) in &[(1, 1), (8, 8), (1000, 64)] {
+		plugin_manager.set_host_capabilities(HostCapabilities {
+			cpu_cores: cores,
+			..host.clone()
+		});
+		assert_eq!(plugin_manager.recommend_num_threads(&caps), Some(threads));
+	}
+}
@@ -0,0 +1,67 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for plugin memory estimates, which don't require any plugins
+//! to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use cuckoo::{estimate_plugin_memory, CuckooMinerError, CuckooPluginMetadata};
+
+const MIB: u64 = 1 << 20;
+
+fn estimate(file_name: &str, num_threads: u32) -> Option<u64> {
+	estimate_plugin_memory(file_name, &CuckooPluginMetadata::from_file_name(file_name), num_threads)
+}
+
+#[test]
+fn on_commit_memory_estimates() {
+	// lean needs 3 bits per edge
+	assert_eq!(estimate("cuckatoo_lean_cpu_29.cuckooplugin", 1), Some(192 * MIB));
+	assert_eq!(estimate("cuckatoo_lean_cpu_29.cuckooplugin", 8), Some(192 * MIB));
+	// cuckoo 30 has 29 edge bits
+	assert_eq!(estimate("cuckoo_lean_cpu_30.cuckooplugin", 1), Some(192 * MIB));
+
+	// mean needs gigabytes, and more with more threads
+	let mean_1 = estimate("cuckatoo_mean_cpu_29.cuckooplugin", 1).unwrap();
+	let mean_8 = estimate("cuckatoo_mean_compat_cpu_29.cuckooplugin", 8).unwrap();
+	assert_eq!(mean_1, 2816 * MIB + 8 * MIB);
+	assert_eq!(mean_8, 2816 * MIB + 64 * MIB);
+	assert!(estimate("cuckatoo_mean_cpu_31.cuckooplugin", 1).unwrap() > 4 * mean_1 - MIB);
+
+	// GPU memory isn't counted, and unknown plugins can't be estimated
+	assert_eq!(estimate("cuckatoo_cuda_29.cuckooplugin", 1), None);
+	assert_eq!(estimate("my_plugin.cuckooplugin", 1), None);
+
+	// a plugin's own figure is used if it reports one
+	let mut metadata = CuckooPluginMetadata::from_file_name("cuckatoo_mean_cpu_29.cuckooplugin");
+	metadata.memory_bytes = Some(1000 * MIB);
+	assert_eq!(
+		estimate_plugin_memory("cuckatoo_mean_cpu_29.cuckooplugin", &metadata, 4),
+		Some(1000 * MIB)
+	);
+}
+
+#[test]
+fn on_commit_memory_error() {
+	let e = CuckooMinerError::InsufficientMemoryError {
+		required: 5632 * MIB,
+		available: 4096 * MIB,
+		detail: String::from("cuckatoo_mean_cpu_29.cuckooplugin: 2816 MiB"),
+	};
+	let message = e.to_string();
+	assert!(message.contains("5632 MiB"));
+	assert!(message.contains("4096 MiB"));
+	assert!(message.contains("cuckatoo_mean_cpu_29"));
+}
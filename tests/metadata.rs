@@ -0,0 +1,93 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for plugin metadata inferred from file names, and filtering
+//! on it, which don't require any plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use cuckoo::{CuckooGraphType, CuckooPluginCapabilities, CuckooPluginDeviceType, CuckooPluginFilter,
+	CuckooPluginMetadata};
+
+fn caps(path: &str) -> CuckooPluginCapabilities {
+	CuckooPluginCapabilities {
+		full_path: String::from(path),
+		metadata: CuckooPluginMetadata::from_file_name(path),
+		..Default::default()
+	}
+}
+
+#[test]
+fn on_commit_metadata_from_file_name() {
+	let m = CuckooPluginMetadata::from_file_name("/plugins/cuckatoo_lean_cpu_29.cuckooplugin");
+	assert_eq!(m.name, "cuckatoo_lean_cpu_29");
+	assert_eq!(m.graph_type, CuckooGraphType::Cuckatoo);
+	assert_eq!(m.edge_bits, Some(29));
+	assert_eq!(m.device_type, CuckooPluginDeviceType::Cpu);
+	assert_eq!(m.version, None);
+	assert!(!m.reported);
+
+	// cuckoo plugins are named by cuckoo size, one more than edge bits
+	let m = CuckooPluginMetadata::from_file_name("cuckoo_cuda_30.cuckooplugin");
+	assert_eq!(m.graph_type, CuckooGraphType::Cuckoo);
+	assert_eq!(m.edge_bits, Some(29));
+	assert_eq!(m.device_type, CuckooPluginDeviceType::Cuda);
+
+	// older plugins don't name the algorithm
+	let m = CuckooPluginMetadata::from_file_name("mean_compat_cpu_16.cuckooplugin");
+	assert_eq!(m.graph_type, CuckooGraphType::Cuckoo);
+	assert_eq!(m.edge_bits, Some(15));
+	assert_eq!(m.device_type, CuckooPluginDeviceType::Cpu);
+
+	let m = CuckooPluginMetadata::from_file_name("my_plugin.cuckooplugin");
+	assert_eq!(m.name, "my_plugin");
+	assert_eq!(m.edge_bits, None);
+	assert_eq!(m.device_type, CuckooPluginDeviceType::Unknown);
+}
+
+#[test]
+fn on_commit_metadata_filter() {
+	let plugins = [
+		caps("/plugins/cuckatoo_lean_cpu_29.cuckooplugin"),
+		caps("/plugins/cuckatoo_mean_cpu_29.cuckooplugin"),
+		caps("/plugins/cuckatoo_cuda_29.cuckooplugin"),
+		caps("/plugins/cuckoo_lean_cpu_30.cuckooplugin"),
+	];
+	let matching = |filter: CuckooPluginFilter| {
+		plugins
+			.iter()
+			.filter(|c| filter.matches(c))
+			.map(|c| c.metadata.name.clone())
+			.collect::<Vec<_>>()
+	};
+
+	assert_eq!(matching(CuckooPluginFilter::new()).len(), 4);
+	assert_eq!(
+		matching(
+			CuckooPluginFilter::new()
+				.graph_type(CuckooGraphType::Cuckatoo)
+				.device_type(CuckooPluginDeviceType::Cpu)
+		),
+		vec!["cuckatoo_lean_cpu_29", "cuckatoo_mean_cpu_29"]
+	);
+	assert_eq!(
+		matching(CuckooPluginFilter::new().edge_bits(29).device_type(CuckooPluginDeviceType::Cuda)),
+		vec!["cuckatoo_cuda_29"]
+	);
+	assert_eq!(
+		matching(CuckooPluginFilter::new().edge_bits(29).name("lean")),
+		vec!["cuckatoo_lean_cpu_29", "cuckoo_lean_cpu_30"]
+	);
+	assert!(matching(CuckooPluginFilter::new().edge_bits(31)).is_empty());
+}
@@ -0,0 +1,254 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the miner's sync mode and job loop, using the mock backend,
+//! which don't require any plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use std::time::Duration;
+
+use cuckoo::{CuckooMiner, CuckooMinerError, CuckooMinerEvent, CuckooMinerJobStatus, CuckooMinerSolution,
+	MockBackend, NonceStrategy, SequentialNonce};
+
+// Known 42-cycle in the cuckoo 16 graph for a header of [82, 0, 0 ...]
+const KNOWN_CUCKOO_16_SOLUTION: [u32; 42] = [
+	1098, 1266, 1304, 1898, 2944, 3929, 4024, 4174, 4706, 5257, 5602, 5684, 5719, 5864,
+	6445, 7886, 7972, 10610, 11190, 13554, 14697, 16794, 17162, 18144, 18400, 19177, 20566,
+	22940, 23504, 24408, 26107, 26388, 27638, 28084, 29463, 29628, 29946, 30242, 32381,
+	32450, 32578, 32584,
+];
+
+// The header the known solution is for, split around a zero nonce
+const PRE_NONCE: &str = "52";
+const POST_NONCE: &str = "0000000000000000000000000000000000000000000000";
+
+fn header() -> [u8; 32] {
+	let mut header = [0; 32];
+	header[0] = 82;
+	header
+}
+
+// Always returns the nonce the known solution was found with
+struct ZeroNonce;
+
+impl NonceStrategy for ZeroNonce {
+	fn next_nonce(&mut self) -> u64 {
+		0
+	}
+}
+
+fn mock() -> MockBackend {
+	MockBackend::new("mock_cpu_16").solution_for(&header(), &KNOWN_CUCKOO_16_SOLUTION)
+}
+
+#[test]
+fn on_commit_mock_sync() {
+	let miner = CuckooMiner::with_backends(vec![Box::new(mock())]).unwrap();
+	let mut cuckoo_size = 0;
+	let mut solution = CuckooMinerSolution::new();
+	assert!(miner.mine(&header(), &mut cuckoo_size, &mut solution, 0).unwrap());
+	assert_eq!(cuckoo_size, 16);
+	assert!(solution.verify(&header()).is_ok());
+
+	let mut other = header();
+	other[1] = 1;
+	let mut solution = CuckooMinerSolution::new();
+	assert!(!miner.mine(&other, &mut cuckoo_size, &mut solution, 0).unwrap());
+
+	assert_eq!(miner.get_stats(0).unwrap()[0].iterations_completed, 2);
+	assert!(miner.get_parameter("NUM_THREADS", 0, 0).unwrap() == 1);
+	assert!(miner.get_parameter("NOT_A_PARAMETER", 0, 0).is_err());
+	match CuckooMiner::with_backends(Vec::new()) {
+		Err(CuckooMinerError::PluginNotLoadedError(_)) => {}
+		r => panic!("Unexpected result {:?}", r.map(|_| ())),
+	}
+}
+
+#[test]
+fn on_commit_mock_job_loop() {
+	let miner = CuckooMiner::with_backends(vec![Box::new(mock())]).unwrap();
+	let job_handle = miner
+		.notify(7, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+
+	// only the first nonce gives the header with a solution
+	let solution = job_handle.wait_for_solution(Duration::from_secs(10)).unwrap();
+	assert_eq!(solution.job_id, 7);
+	assert_eq!(solution.get_nonce_as_u64(), 0);
+	assert!(solution.verify(&header()).is_ok());
+	assert!(job_handle.is_current(&solution));
+	assert!(job_handle.wait_for_solution(Duration::from_millis(300)).is_none());
+	assert!(job_handle.get_stats(0).unwrap()[0].iterations_completed > 1);
+
+	// parameters are checked and set while the job runs
+	job_handle.set_parameter("NUM_THREADS", 0, 4, 0, Duration::from_secs(10)).unwrap();
+	assert_eq!(job_handle.get_parameter("NUM_THREADS", 0, 0).unwrap(), 4);
+	assert!(job_handle.set_parameter("NUM_THREADS", 0, 100, 0, Duration::from_secs(10)).is_err());
+
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	assert!(job_handle.get_solution().is_none());
+	assert_eq!(job_handle.get_rejected_solutions(0).unwrap(), 0);
+}
+
+#[test]
+fn on_commit_mock_update_job() {
+	let miner = CuckooMiner::with_backends(vec![Box::new(mock())]).unwrap();
+	let job_handle = miner
+		.notify(1, "53", POST_NONCE, 0, false, Box::new(ZeroNonce))
+		.unwrap();
+	assert!(job_handle.wait_for_solution(Duration::from_millis(300)).is_none());
+
+	// the new template is mined without restarting the job
+	job_handle.update_job(2, PRE_NONCE, POST_NONCE, 0).unwrap();
+	let solution = job_handle.wait_for_solution(Duration::from_secs(10)).unwrap();
+	assert_eq!(solution.job_id, 2);
+	assert!(job_handle.is_current(&solution));
+	assert!(solution.verify(&header()).is_ok());
+
+	// and solutions for it are no longer current once it's replaced
+	job_handle.update_job(3, "53", POST_NONCE, 0).unwrap();
+	assert!(!job_handle.is_current(&solution));
+	while let Some(s) = job_handle.wait_for_solution(Duration::from_millis(300)) {
+		assert_eq!(s.job_id, 2);
+	}
+	assert_eq!(job_handle.get_rejected_solutions(0).unwrap(), 0);
+
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	assert!(job_handle.update_job(4, PRE_NONCE, POST_NONCE, 0).is_err());
+}
+
+#[test]
+fn on_commit_mock_rejected_solutions() {
+	let backend = MockBackend::new("mock_cpu_16").solution_every(2, &KNOWN_CUCKOO_16_SOLUTION);
+	let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(1)))
+		.unwrap();
+
+	// the solution is only valid for nonce 0, which isn't mined
+	assert!(job_handle.wait_for_solution(Duration::from_millis(500)).is_none());
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	assert!(job_handle.get_rejected_solutions(0).unwrap() > 0);
+	assert_eq!(job_handle.get_stale_solutions(0).unwrap(), 0);
+}
+
+#[test]
+fn on_commit_mock_stop_timeout() {
+	let backend = mock().stop_delay(Duration::from_secs(1));
+	let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+	assert_eq!(job_handle.status(), CuckooMinerJobStatus::Running);
+
+	// the plugin takes longer to stop than we're willing to wait
+	match job_handle.stop_jobs(Duration::from_millis(100)) {
+		Err(CuckooMinerError::JobStopTimeoutError(_)) => {}
+		r => panic!("Unexpected result {:?}", r),
+	}
+	assert_eq!(job_handle.status(), CuckooMinerJobStatus::Running);
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	assert_eq!(job_handle.status(), CuckooMinerJobStatus::Stopped);
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+}
+
+#[test]
+fn on_commit_mock_job_panic() {
+	let miner = CuckooMiner::with_backends(vec![Box::new(mock().panic_when_read())]).unwrap();
+	let events = miner.events();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+
+	// the panic fails the job rather than leaving it running forever
+	assert!(job_handle.wait_for_solution(Duration::from_secs(10)).is_none());
+	match job_handle.status() {
+		CuckooMinerJobStatus::Failed(e) => assert!(e.contains("panicked")),
+		s => panic!("Unexpected status {:?}", s),
+	}
+	match events.iter().last().unwrap() {
+		CuckooMinerEvent::JobStopped { job_id, error } => {
+			assert_eq!(job_id, 1);
+			assert!(error.unwrap().contains("panicked"));
+		}
+		e => panic!("Unexpected event {:?}", e),
+	}
+	for _ in 0..2 {
+		match job_handle.stop_jobs(Duration::from_secs(10)) {
+			Err(CuckooMinerError::JobFailedError(e)) => assert!(e.contains("panicked")),
+			r => panic!("Unexpected result {:?}", r),
+		}
+	}
+}
+
+#[test]
+fn on_commit_mock_job_failed() {
+	let miner = CuckooMiner::with_backends(vec![Box::new(mock().start_error(5))]).unwrap();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+
+	// a plugin which never starts fails the job once it's been retried for a while
+	assert!(job_handle.wait_for_solution(Duration::from_secs(30)).is_none());
+	match job_handle.status() {
+		CuckooMinerJobStatus::Failed(e) => {
+			assert!(e.contains("cuckoo_start_processing"));
+			assert!(!e.contains("panicked"));
+		}
+		s => panic!("Unexpected status {:?}", s),
+	}
+	match job_handle.stop_jobs(Duration::from_secs(10)) {
+		Err(CuckooMinerError::JobFailedError(e)) => assert!(e.contains("failing")),
+		r => panic!("Unexpected result {:?}", r),
+	}
+}
+
+#[test]
+fn on_commit_mock_set_parameter_failures() {
+	// a plugin which doesn't stop in time isn't changed, but carries on
+	// mining once it has stopped
+	let backend = mock().stop_delay(Duration::from_secs(1));
+	let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(ZeroNonce))
+		.unwrap();
+	assert!(job_handle.wait_for_solution(Duration::from_secs(10)).is_some());
+	match job_handle.set_parameter("NUM_THREADS", 0, 4, 0, Duration::from_millis(100)) {
+		Err(CuckooMinerError::PluginProcessingError(e)) => assert!(e.contains("didn't stop")),
+		r => panic!("Unexpected result {:?}", r),
+	}
+	assert_eq!(job_handle.get_parameter("NUM_THREADS", 0, 0).unwrap(), 1);
+	while job_handle.get_solution().is_some() {}
+	assert!(job_handle.wait_for_solution(Duration::from_secs(10)).is_some());
+	assert_eq!(job_handle.status(), CuckooMinerJobStatus::Running);
+
+	// and can be changed when it's given long enough
+	job_handle.set_parameter("NUM_THREADS", 0, 4, 0, Duration::from_secs(10)).unwrap();
+	assert_eq!(job_handle.get_parameter("NUM_THREADS", 0, 0).unwrap(), 4);
+	while job_handle.get_solution().is_some() {}
+	assert!(job_handle.wait_for_solution(Duration::from_secs(10)).is_some());
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+
+	// nor does one which doesn't restart look like it's still mining
+	let miner = CuckooMiner::with_backends(vec![Box::new(mock().start_error(5))]).unwrap();
+	let job_handle = miner
+		.notify(1, PRE_NONCE, POST_NONCE, 0, false, Box::new(SequentialNonce::new(0)))
+		.unwrap();
+	match job_handle.set_parameter("NUM_THREADS", 0, 4, 0, Duration::from_secs(10)) {
+		Err(e) => assert!(e.to_string().contains("cuckoo_start_processing")),
+		r => panic!("Unexpected result {:?}", r),
+	}
+	let _ = job_handle.stop_jobs(Duration::from_secs(10));
+}
@@ -0,0 +1,63 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Tests for the nonce generation strategies used in async mode
+
+extern crate cuckoo_miner as cuckoo;
+
+use std::collections::HashSet;
+
+use cuckoo::{NonceStrategy, SequentialNonce, RandomNonce, PartitionedNonce};
+
+fn take(strategy: &mut dyn NonceStrategy, count: usize) -> Vec<u64> {
+	(0..count).map(|_| strategy.next_nonce()).collect()
+}
+
+#[test]
+fn on_commit_sequential_nonce() {
+	let mut s = SequentialNonce::new(100);
+	assert_eq!(take(&mut s, 3), vec![100, 101, 102]);
+
+	let mut s = SequentialNonce::new(u64::MAX);
+	assert_eq!(take(&mut s, 2), vec![u64::MAX, 0]);
+}
+
+#[test]
+fn on_commit_random_nonce_replay() {
+	let mut a = RandomNonce::from_seed(42);
+	let mut b = RandomNonce::from_seed(42);
+	let mut c = RandomNonce::from_seed(43);
+	let nonces = take(&mut a, 100);
+	assert_eq!(nonces, take(&mut b, 100));
+	assert!(nonces != take(&mut c, 100));
+}
+
+#[test]
+fn on_commit_partitioned_nonce() {
+	let mut s = PartitionedNonce::new(10, 5);
+	assert_eq!(take(&mut s, 3), vec![10, 15, 20]);
+
+	// workers don't overlap before wrapping
+	let num_workers = 4;
+	let mut seen = HashSet::new();
+	for w in 0..num_workers {
+		let mut s = PartitionedNonce::for_worker(w, num_workers).unwrap();
+		for n in take(&mut s, 1000) {
+			assert!(n % num_workers == w);
+			assert!(seen.insert(n));
+		}
+	}
+	assert!(PartitionedNonce::for_worker(num_workers, num_workers).is_err());
+	assert!(PartitionedNonce::for_worker(0, 0).is_err());
+}
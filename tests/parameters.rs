@@ -0,0 +1,94 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the typed plugin parameter builder, which don't require
+//! any plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use cuckoo::{CuckooMinerError, CuckooPluginCapabilities, CuckooPluginParameter, PluginParameters};
+
+fn parameter(name: &str, default_value: u32, min_value: u32, max_value: u32) -> CuckooPluginParameter {
+	CuckooPluginParameter {
+		name: String::from(name),
+		description: String::from(""),
+		default_value: default_value,
+		min_value: min_value,
+		max_value: max_value,
+	}
+}
+
+fn test_caps() -> CuckooPluginCapabilities {
+	CuckooPluginCapabilities {
+		full_path: String::from("/plugins/mean_cpu_16.cuckooplugin"),
+		parameters: vec![parameter("NUM_THREADS", 1, 1, 32), parameter("NUM_TRIMS", 8, 2, 64)],
+		..Default::default()
+	}
+}
+
+#[test]
+fn on_commit_parameters_defaults() {
+	let config = PluginParameters::new(&test_caps())
+		.set("NUM_THREADS", 4)
+		.build()
+		.unwrap();
+	assert_eq!(config.plugin_full_path, "/plugins/mean_cpu_16.cuckooplugin");
+	assert_eq!(
+		config.parameter_list,
+		vec![
+			(String::from("NUM_THREADS"), 0, 4),
+			(String::from("NUM_TRIMS"), 0, 8),
+		]
+	);
+}
+
+#[test]
+fn on_commit_parameters_devices() {
+	let config = PluginParameters::new(&test_caps())
+		.set("NUM_THREADS", 4)
+		.set("NUM_THREADS", 6)
+		.set_for_device("NUM_TRIMS", 1, 16)
+		.build()
+		.unwrap();
+	assert_eq!(
+		config.parameter_list,
+		vec![
+			(String::from("NUM_THREADS"), 0, 6),
+			(String::from("NUM_TRIMS"), 1, 16),
+			(String::from("NUM_TRIMS"), 0, 8),
+		]
+	);
+}
+
+#[test]
+fn on_commit_parameters_violations() {
+	let params = PluginParameters::new(&test_caps())
+		.set("NUM_THREADS", 0)
+		.set("NUM_TRIMS", 65)
+		.set("EXPAND", 1)
+		.set_for_device("NUM_TRIMS", 1, 64);
+	let violations = params.violations();
+	assert_eq!(violations.len(), 3);
+	assert!(violations[0].contains("NUM_THREADS"));
+	assert!(violations[1].contains("NUM_TRIMS"));
+	assert!(violations[2].contains("EXPAND"));
+
+	// every violation is reported at once
+	match params.build() {
+		Err(CuckooMinerError::ParameterError(e)) => {
+			assert!(e.contains("NUM_THREADS") && e.contains("NUM_TRIMS") && e.contains("EXPAND"));
+		}
+		r => panic!("Unexpected result {:?}", r),
+	}
+}
@@ -15,6 +15,8 @@
 
 //! Performance-related tests go here
 
+extern crate cuckoo_miner as cuckoo;
+
 pub mod common;
 
 //Test for profiling
@@ -44,3 +46,21 @@ fn perf_mean_30_compare() {
 	common::mine_sync_for_duration(plugin_path_vec[0].clone(), 20, Some(params.clone()));
 	common::mine_async_for_duration(plugin_path_vec, 20, Some(params.clone()));
 }
+
+//Benchmark the cuckoo 16 plugins against each other
+#[test]
+fn perf_benchmark_16() {
+	let caps = common::get_plugin_vec("cpu_16");
+	let mut benchmark = cuckoo::CuckooBenchmark::new().headers(10);
+	for c in &caps {
+		benchmark = benchmark.add_plugin(c);
+	}
+	let report = benchmark.run();
+	println!("{}", report.to_json().unwrap());
+	assert_eq!(report.results.len(), caps.len());
+	for r in report.results.iter().filter(|r| r.error.is_none()) {
+		assert_eq!(r.graphs, 10);
+		assert_eq!(r.invalid_solutions, 0);
+	}
+	assert!(report.best().is_some());
+}
@@ -158,6 +158,18 @@ fn call_cuckoo_parameter_list_tests(pl: &PluginLibrary){
 		&mut param_list_bytes_len);
 	//let result_list = String::from_utf8(param_list_bytes.to_vec()).unwrap();
 	assert!(ret_val==3);
+
+	//Test a length longer than the buffer is clamped to it
+	let mut param_list_bytes:[u8;TOO_SHORT_LENGTH]=[0;TOO_SHORT_LENGTH];
+	let mut param_list_bytes_len=1 << 20;
+	let ret_val=pl.call_cuckoo_parameter_list(&mut param_list_bytes,
+		&mut param_list_bytes_len);
+	assert!(ret_val==3);
+
+	//Test the owned result is exactly the JSON written
+	let result_list = pl.parameter_list().unwrap();
+	assert!(result_list.starts_with("["));
+	assert!(!result_list.contains('\0'));
 }
 
 //tests call_cuckoo_parameter_list() on all available plugins
@@ -0,0 +1,287 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for the stratum client against a mock stratum server, which
+//! don't require any plugins to be built
+
+#![cfg(feature = "stratum")]
+
+extern crate cuckoo_miner as cuckoo;
+extern crate serde_json;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use cuckoo::{CuckooGraphType, CuckooMiner, CuckooMinerSolution, MockBackend, NonceStrategy,
+	StratumClient, StratumEvent, StratumJob, StratumShareStats};
+
+const PRE_POW: &str = "0001000000000000002a";
+
+// Known 42-cycle in the cuckoo 16 graph for a header of [82, 0, 0 ...]
+const KNOWN_CUCKOO_16_SOLUTION: [u32; 42] = [
+	1098, 1266, 1304, 1898, 2944, 3929, 4024, 4174, 4706, 5257, 5602, 5684, 5719, 5864,
+	6445, 7886, 7972, 10610, 11190, 13554, 14697, 16794, 17162, 18144, 18400, 19177, 20566,
+	22940, 23504, 24408, 26107, 26388, 27638, 28084, 29463, 29628, 29946, 30242, 32381,
+	32450, 32578, 32584,
+];
+
+// Pre-pows which give the header of the known solution with a zero
+// nonce, and a header without one
+const SOLVED_PRE_POW: &str = "520000000000000000000000000000000000000000000000";
+const UNSOLVED_PRE_POW: &str = "530000000000000000000000000000000000000000000000";
+
+// Always returns the nonce the known solution was found with
+struct ZeroNonce;
+
+impl NonceStrategy for ZeroNonce {
+	fn next_nonce(&mut self) -> u64 {
+		0
+	}
+}
+
+// Minimal server side of a stratum connection
+struct MockServer {
+	reader: BufReader<TcpStream>,
+	writer: TcpStream,
+}
+
+impl MockServer {
+	fn read_request(&mut self) -> Value {
+		let mut line = String::new();
+		self.reader.read_line(&mut line).unwrap();
+		serde_json::from_str(&line).unwrap()
+	}
+
+	fn send(&mut self, msg: &str) {
+		self.writer.write_all(msg.as_bytes()).unwrap();
+		self.writer.write_all(b"\n").unwrap();
+	}
+
+	fn respond(&mut self, request: &Value, result: &str, error: &str) {
+		let msg = format!(
+			"{{\"id\":{},\"jsonrpc\":\"2.0\",\"method\":{},\"result\":{},\"error\":{}}}",
+			request["id"], request["method"], result, error
+		);
+		self.send(&msg);
+	}
+
+	fn send_job(&mut self, job_id: u32, pre_pow: &str) {
+		let msg = format!(
+			"{{\"id\":\"Stratum\",\"jsonrpc\":\"2.0\",\"method\":\"job\",\"params\":\
+			{{\"difficulty\":1,\"height\":42,\"job_id\":{},\"pre_pow\":\"{}\"}}}}",
+			job_id, pre_pow
+		);
+		self.send(&msg);
+	}
+
+	// Reads and ignores requests until the client hangs up
+	fn drain(&mut self) {
+		let mut line = String::new();
+		while self.reader.read_line(&mut line).unwrap_or(0) > 0 {
+			line.clear();
+		}
+	}
+}
+
+// Runs the given server script on a background thread, returning the
+// address to connect to
+fn start_server<F>(script: F) -> (String, thread::JoinHandle<()>)
+where
+	F: FnOnce(&mut MockServer) + Send + 'static,
+{
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap().to_string();
+	let handle = thread::spawn(move || {
+		let (stream, _) = listener.accept().unwrap();
+		let mut server = MockServer {
+			reader: BufReader::new(stream.try_clone().unwrap()),
+			writer: stream,
+		};
+		script(&mut server);
+	});
+	(address, handle)
+}
+
+fn solution_for_job(job_id: u32) -> CuckooMinerSolution {
+	let mut solution = CuckooMinerSolution::new();
+	solution.cuckoo_size = 29;
+	solution.graph_type = CuckooGraphType::Cuckatoo;
+	solution.nonce = [0, 0, 0, 0, 0, 0, 1, 2];
+	solution.set_solution(&(0..42).collect::<Vec<u32>>());
+	solution.job_id = job_id;
+	solution.job_template_hash = CuckooMinerSolution::template_hash(PRE_POW, "");
+	solution
+}
+
+fn next_event(client: &mut StratumClient) -> StratumEvent {
+	client
+		.poll(Duration::from_secs(5))
+		.unwrap()
+		.expect("no event from server")
+}
+
+#[test]
+fn on_commit_stratum_login_job_and_submit() {
+	let (address, server) = start_server(|server| {
+		let login = server.read_request();
+		assert_eq!(login["method"], "login");
+		assert_eq!(login["params"]["login"], "miner");
+		// a job can arrive before the login response
+		server.send_job(7, PRE_POW);
+		server.respond(&login, "\"ok\"", "null");
+
+		let submit = server.read_request();
+		assert_eq!(submit["method"], "submit");
+		assert_eq!(submit["params"]["job_id"], 7);
+		assert_eq!(submit["params"]["height"], 42);
+		assert_eq!(submit["params"]["edge_bits"], 29);
+		assert_eq!(submit["params"]["nonce"], 258);
+		assert_eq!(submit["params"]["pow"].as_array().unwrap().len(), 42);
+		server.respond(&submit, "\"ok\"", "null");
+
+		let submit = server.read_request();
+		server.respond(&submit, "null", "{\"code\":-32502,\"message\":\"Failed to validate solution\"}");
+
+		let request = server.read_request();
+		assert_eq!(request["method"], "getjobtemplate");
+		server.respond(
+			&request,
+			&format!("{{\"difficulty\":4,\"height\":43,\"job_id\":8,\"pre_pow\":\"{}\"}}", PRE_POW),
+			"null",
+		);
+	});
+
+	let mut client = StratumClient::connect(&address).unwrap();
+	client.login("miner", "", "cuckoo-miner-test").unwrap();
+	let job = StratumJob {
+		job_id: 7,
+		height: 42,
+		difficulty: 1,
+		pre_pow: String::from(PRE_POW),
+	};
+	assert_eq!(next_event(&mut client), StratumEvent::Job(job.clone()));
+	assert_eq!(client.current_job(), Some(&job));
+
+	client.submit(&solution_for_job(7)).unwrap();
+	assert_eq!(next_event(&mut client), StratumEvent::ShareAccepted);
+	client.submit(&solution_for_job(7)).unwrap();
+	assert_eq!(
+		next_event(&mut client),
+		StratumEvent::ShareRejected(String::from("Failed to validate solution"))
+	);
+
+	// solutions for other jobs aren't sent
+	assert!(client.submit(&solution_for_job(6)).is_err());
+
+	client.request_job().unwrap();
+	match next_event(&mut client) {
+		StratumEvent::Job(j) => assert!(j.job_id == 8 && j.difficulty == 4),
+		e => panic!("Unexpected event {:?}", e),
+	}
+
+	assert_eq!(
+		client.stats(),
+		StratumShareStats {
+			submitted: 2,
+			accepted: 1,
+			rejected: 1,
+		}
+	);
+	server.join().unwrap();
+}
+
+#[test]
+fn on_commit_stratum_login_rejected() {
+	let (address, server) = start_server(|server| {
+		let login = server.read_request();
+		server.respond(&login, "null", "{\"code\":-32500,\"message\":\"Unauthorized\"}");
+	});
+	let mut client = StratumClient::connect(&address).unwrap();
+	assert!(client.login("miner", "wrong", "cuckoo-miner-test").is_err());
+	server.join().unwrap();
+
+	// the server has hung up
+	assert!(client.poll(Duration::from_secs(5)).is_err());
+}
+
+#[test]
+fn on_commit_stratum_process() {
+	let (address, server) = start_server(|server| {
+		let login = server.read_request();
+		server.respond(&login, "\"ok\"", "null");
+		let request = server.read_request();
+		assert_eq!(request["method"], "getjobtemplate");
+		server.respond(
+			&request,
+			&format!("{{\"difficulty\":1,\"height\":42,\"job_id\":1,\"pre_pow\":\"{}\"}}", UNSOLVED_PRE_POW),
+			"null",
+		);
+		server.send_job(2, SOLVED_PRE_POW);
+
+		// only the new job has solutions
+		let submit = server.read_request();
+		assert_eq!(submit["method"], "submit");
+		assert_eq!(submit["params"]["job_id"], 2);
+		assert_eq!(submit["params"]["nonce"], 0);
+		assert_eq!(submit["params"]["edge_bits"], 15);
+		server.respond(&submit, "\"ok\"", "null");
+		let submit = server.read_request();
+		assert_eq!(submit["params"]["job_id"], 2);
+		server.respond(&submit, "null", "{\"code\":-32503,\"message\":\"Solution submitted too late\"}");
+		server.drain();
+	});
+
+	let mut client = StratumClient::connect(&address).unwrap();
+	client.login("miner", "", "cuckoo-miner-test").unwrap();
+	let job = client.wait_for_job(&AtomicBool::new(false)).unwrap().unwrap();
+	assert_eq!(job.job_id, 1);
+	let mut header = [0; 32];
+	header[0] = 82;
+	let backend = MockBackend::new("mock_cpu_16").solution_for(&header, &KNOWN_CUCKOO_16_SOLUTION);
+	let miner = CuckooMiner::with_backends(vec![Box::new(backend)]).unwrap();
+	let job_handle = miner
+		.notify(job.job_id, &job.pre_pow, "", job.difficulty, false, Box::new(ZeroNonce))
+		.unwrap();
+
+	// the pool's new job replaces the running one
+	let deadline = Instant::now() + Duration::from_secs(10);
+	loop {
+		assert!(Instant::now() < deadline);
+		if let Some(StratumEvent::Job(j)) = client.process(&job_handle, Duration::from_millis(100)).unwrap() {
+			assert_eq!(j.job_id, 2);
+			break;
+		}
+	}
+	let solution = job_handle.wait_for_solution(Duration::from_secs(10)).unwrap();
+	assert_eq!(solution.job_id, 2);
+	assert!(job_handle.is_current(&solution));
+
+	// and its solutions are submitted, with the pool's responses counted
+	while client.stats().accepted + client.stats().rejected < 2 {
+		assert!(Instant::now() < deadline);
+		client.process(&job_handle, Duration::from_millis(100)).unwrap();
+	}
+	let stats = client.stats();
+	assert!(stats.submitted >= 2);
+	assert_eq!((stats.accepted, stats.rejected), (1, 1));
+
+	job_handle.stop_jobs(Duration::from_secs(10)).unwrap();
+	drop(client);
+	server.join().unwrap();
+}
@@ -81,3 +81,27 @@ fn manual_mean_16_compat() {
 	let caps = common::get_plugin_vec("mean_compat_cpu_16");
 	common::mine_sync_for_duration(&caps[0].full_path, 3600, Some(params.clone()));
 }
+
+//reads back the parameters a plugin was configured with
+#[test]
+fn on_commit_read_back_parameters() {
+	let caps = common::get_plugin_vec("mean_cpu_16");
+	for c in &caps {
+		let config = cuckoo::PluginParameters::new(c)
+			.set("NUM_THREADS", 2)
+			.build()
+			.unwrap();
+		let miner = cuckoo::CuckooMiner::new(vec![config]).unwrap();
+		assert_eq!(miner.get_parameter("NUM_THREADS", 0, 0).unwrap(), 2);
+		assert!(miner.get_parameter("NUM_SANDWICHES", 0, 0).is_err());
+		assert!(miner.get_parameter("NUM_THREADS", 0, 1).is_err());
+
+		let values = miner.dump_parameters().unwrap();
+		for p in &c.parameters {
+			assert!(values.iter().any(|v| v.name == p.name && v.device_id == 0));
+		}
+		assert!(values
+			.iter()
+			.any(|v| v.name == "NUM_THREADS" && v.device_id == 0 && v.value == 2));
+	}
+}
@@ -0,0 +1,133 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/// Tests for the pure-Rust proof verifier, which don't require any
+/// plugins to be built
+
+extern crate cuckoo_miner as cuckoo;
+
+use cuckoo::{CuckooMinerSolution, CuckooGraphType};
+
+// Known 42-cycle in the cuckoo 16 graph for a header of [82, 0, 0 ...]
+const KNOWN_CUCKOO_16_SOLUTION: [u32; 42] = [
+	1098, 1266, 1304, 1898, 2944, 3929, 4024, 4174, 4706, 5257, 5602, 5684, 5719, 5864,
+	6445, 7886, 7972, 10610, 11190, 13554, 14697, 16794, 17162, 18144, 18400, 19177, 20566,
+	22940, 23504, 24408, 26107, 26388, 27638, 28084, 29463, 29628, 29946, 30242, 32381,
+	32450, 32578, 32584,
+];
+
+// Known 42-cycle in the cuckatoo 19 graph for a header of [173, 0, 0 ...]
+const KNOWN_CUCKATOO_19_SOLUTION: [u32; 42] = [
+	19497, 51968, 58590, 67351, 82810, 84585, 117561, 122625, 127842, 139560, 141701,
+	144740, 146394, 149872, 153869, 166212, 176324, 178263, 210143, 231322, 256752, 262663,
+	267332, 273421, 305377, 320805, 349850, 359284, 375290, 387176, 398365, 405738, 409739,
+	451446, 452140, 461520, 462310, 463232, 480261, 485965, 502754, 515928,
+];
+
+// Known 12-cycle in the cuckoo 16 graph for a header of [7, 0, 0 ...]
+const KNOWN_CUCKOO_16_12_CYCLE: [u32; 12] = [
+	2607, 10941, 12879, 13928, 14074, 14811, 15994, 20416, 20593, 20841, 22457, 30878,
+];
+
+fn header(first_byte: u8) -> [u8; 32] {
+	let mut header = [0; 32];
+	header[0] = first_byte;
+	header
+}
+
+fn cuckoo_16_solution() -> CuckooMinerSolution {
+	let mut solution = CuckooMinerSolution::new();
+	solution.cuckoo_size = 16;
+	solution.graph_type = CuckooGraphType::Cuckoo;
+	solution.set_solution(&KNOWN_CUCKOO_16_SOLUTION);
+	solution
+}
+
+fn cuckatoo_19_solution() -> CuckooMinerSolution {
+	let mut solution = CuckooMinerSolution::new();
+	solution.cuckoo_size = 19;
+	solution.graph_type = CuckooGraphType::Cuckatoo;
+	solution.set_solution(&KNOWN_CUCKATOO_19_SOLUTION);
+	solution
+}
+
+#[test]
+fn on_commit_verify_known_solutions() {
+	assert!(cuckoo_16_solution().verify(&header(82)).is_ok());
+	assert!(cuckatoo_19_solution().verify(&header(173)).is_ok());
+}
+
+#[test]
+fn on_commit_verify_proof_size() {
+	let mut solution = CuckooMinerSolution::with_proof_size(12);
+	solution.cuckoo_size = 16;
+	solution.set_solution(&KNOWN_CUCKOO_16_12_CYCLE);
+	assert!(solution.proof_size() == 12);
+	assert!(solution.verify(&header(7)).is_ok());
+
+	// a valid 12-cycle is not a valid 42-cycle
+	let mut solution = CuckooMinerSolution::new();
+	solution.cuckoo_size = 16;
+	solution.solution_nonces[..12].copy_from_slice(&KNOWN_CUCKOO_16_12_CYCLE);
+	assert!(solution.verify(&header(7)).is_err());
+
+	// odd proof sizes can't form a cycle
+	let mut solution = CuckooMinerSolution::new();
+	solution.cuckoo_size = 16;
+	solution.set_solution(&KNOWN_CUCKOO_16_12_CYCLE[..11]);
+	assert!(solution.verify(&header(7)).is_err());
+}
+
+#[test]
+fn on_commit_verify_wrong_header() {
+	assert!(cuckoo_16_solution().verify(&header(83)).is_err());
+	assert!(cuckatoo_19_solution().verify(&header(174)).is_err());
+}
+
+#[test]
+fn on_commit_verify_wrong_graph() {
+	let mut solution = cuckoo_16_solution();
+	solution.graph_type = CuckooGraphType::Cuckatoo;
+	assert!(solution.verify(&header(82)).is_err());
+
+	let mut solution = cuckatoo_19_solution();
+	solution.graph_type = CuckooGraphType::Cuckoo;
+	assert!(solution.verify(&header(173)).is_err());
+
+	let mut solution = cuckatoo_19_solution();
+	solution.cuckoo_size = 20;
+	assert!(solution.verify(&header(173)).is_err());
+}
+
+#[test]
+fn on_commit_verify_tampered_solutions() {
+	// unsorted
+	let mut solution = cuckoo_16_solution();
+	solution.solution_nonces.swap(3, 4);
+	assert!(solution.verify(&header(82)).is_err());
+
+	// out of range for the graph size
+	let mut solution = cuckoo_16_solution();
+	solution.solution_nonces[41] = 1 << 15;
+	assert!(solution.verify(&header(82)).is_err());
+
+	// one edge replaced
+	let mut solution = cuckatoo_19_solution();
+	solution.solution_nonces[20] += 1;
+	assert!(solution.verify(&header(173)).is_err());
+
+	// empty solution
+	let solution = CuckooMinerSolution::new();
+	assert!(solution.verify(&header(82)).is_err());
+}